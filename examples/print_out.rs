@@ -1,13 +1,20 @@
+//! Prints the classes and animations of an anm file.
+//!
+//! `cargo run --example print_out -- <file.anm>`, for example with a file from the
+//! `anims` folder of the game's install directory.
+
 use bhanm::AnmFile;
+use std::env;
 use std::error::Error;
 use std::fs::File;
 use std::io::BufReader;
 
 fn main() -> Result<(), Box<dyn Error>> {
-    const ANM_PATH: &str =
-        "C:/Program Files (x86)/Steam/steamapps/common/Brawlhalla/anims/Animation_Bow.anm";
+    let Some(path) = env::args().nth(1) else {
+        return Err("usage: print_out <file.anm>".into());
+    };
 
-    let file = File::open(ANM_PATH)?;
+    let file = File::open(path)?;
     let reader = BufReader::new(file);
     let anm_file = AnmFile::read(reader)?;
 
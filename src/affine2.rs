@@ -0,0 +1,156 @@
+use std::ops::Mul;
+
+/// A 2D affine transform, laid out like a Flash matrix:
+/// a point `(x, y)` maps to `(a * x + c * y + tx, b * x + d * y + ty)`.
+///
+/// For an `AnmBone`, `a`, `b`, `c` and `d` are `scale_x`, `rotate_skew0`, `rotate_skew1` and `scale_y`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Affine2 {
+    pub a: f64,
+    pub b: f64,
+    pub c: f64,
+    pub d: f64,
+    pub tx: f64,
+    pub ty: f64,
+}
+
+/// An affine transform split into simple parts. Applied to a point, the parts act in this order:
+/// flip (negating y), scale, skew, rotation, translation.
+///
+/// Angles are in radians, with y pointing down as in the game, so a positive rotation is clockwise on screen.
+/// The scales are never negative: mirroring shows up as `flip`, possibly with a half turn of rotation.
+///
+/// Transforms that the anm format stores in the symmetric encoding
+/// (`scale_y == -scale_x` and `rotate_skew1 == rotate_skew0`) are exactly the flipped ones
+/// with no skew and equal scales.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Decomposition {
+    pub translation: (f64, f64),
+    pub rotation: f64,
+    pub scale: (f64, f64),
+    /// The angle the y axis is sheared by towards the x axis.
+    pub skew: f64,
+    pub flip: bool,
+}
+
+impl Affine2 {
+    pub const IDENTITY: Self = Self::new(1., 0., 0., 1., 0., 0.);
+
+    pub const fn new(a: f64, b: f64, c: f64, d: f64, tx: f64, ty: f64) -> Self {
+        Self { a, b, c, d, tx, ty }
+    }
+
+    pub const fn translation(tx: f64, ty: f64) -> Self {
+        Self::new(1., 0., 0., 1., tx, ty)
+    }
+
+    pub fn rotation(angle: f64) -> Self {
+        let (sin, cos) = angle.sin_cos();
+        Self::new(cos, sin, -sin, cos, 0., 0.)
+    }
+
+    pub const fn scale(sx: f64, sy: f64) -> Self {
+        Self::new(sx, 0., 0., sy, 0., 0.)
+    }
+
+    /// Shears the y axis by `angle` towards the x axis.
+    pub fn skew(angle: f64) -> Self {
+        Self::new(1., 0., angle.tan(), 1., 0., 0.)
+    }
+
+    pub fn determinant(&self) -> f64 {
+        self.a * self.d - self.b * self.c
+    }
+
+    /// `None` if the transform is not invertible.
+    pub fn inverse(&self) -> Option<Self> {
+        let det = self.determinant();
+        if det == 0. || !det.is_finite() {
+            return None;
+        }
+        let a = self.d / det;
+        let b = -self.b / det;
+        let c = -self.c / det;
+        let d = self.a / det;
+        Some(Self {
+            a,
+            b,
+            c,
+            d,
+            tx: -(a * self.tx + c * self.ty),
+            ty: -(b * self.tx + d * self.ty),
+        })
+    }
+
+    pub fn transform_point(&self, (x, y): (f64, f64)) -> (f64, f64) {
+        (
+            self.a * x + self.c * y + self.tx,
+            self.b * x + self.d * y + self.ty,
+        )
+    }
+
+    /// Like `transform_point`, without the translation.
+    pub fn transform_vector(&self, (x, y): (f64, f64)) -> (f64, f64) {
+        (self.a * x + self.c * y, self.b * x + self.d * y)
+    }
+
+    pub fn decompose(&self) -> Decomposition {
+        let sx = self.a.hypot(self.b);
+        let rotation = self.b.atan2(self.a);
+        let (sin, cos) = rotation.sin_cos();
+        // the y axis with the rotation undone
+        let shear = cos * self.c + sin * self.d;
+        let sy = -sin * self.c + cos * self.d;
+        let skew = if sy == 0. { 0. } else { (shear / sy).atan() };
+
+        Decomposition {
+            translation: (self.tx, self.ty),
+            rotation,
+            scale: (sx, sy.abs()),
+            skew,
+            flip: sy.is_sign_negative(),
+        }
+    }
+}
+
+impl Decomposition {
+    pub fn recompose(&self) -> Affine2 {
+        let (sx, sy) = self.scale;
+        let sy = if self.flip { -sy } else { sy };
+        let (tx, ty) = self.translation;
+        Affine2::translation(tx, ty)
+            * Affine2::rotation(self.rotation)
+            * Affine2::skew(self.skew)
+            * Affine2::scale(sx, sy)
+    }
+}
+
+impl Default for Affine2 {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+impl Mul for Affine2 {
+    type Output = Self;
+
+    /// `self * rhs` applies `rhs` first, then `self`.
+    fn mul(self, rhs: Self) -> Self {
+        Self {
+            a: self.a * rhs.a + self.c * rhs.b,
+            b: self.b * rhs.a + self.d * rhs.b,
+            c: self.a * rhs.c + self.c * rhs.d,
+            d: self.b * rhs.c + self.d * rhs.d,
+            tx: self.a * rhs.tx + self.c * rhs.ty + self.tx,
+            ty: self.b * rhs.tx + self.d * rhs.ty + self.ty,
+        }
+    }
+}
+
+impl From<Decomposition> for Affine2 {
+    fn from(decomposition: Decomposition) -> Self {
+        decomposition.recompose()
+    }
+}
@@ -0,0 +1,213 @@
+//! Unpacking an anm file into a directory of text files, and packing it back.
+//!
+//! The directory holds a `manifest.txt` with the file header and, for each class,
+//! its key, index, file name and folder, followed by its animation files in order.
+//! Each animation is stored in the format of the `text` module.
+
+use crate::{
+    AnimationCollection, AnmClass, AnmFile, AnmReadingError, ClassCollection,
+    text::{self, Lines, TextParseError, quote},
+};
+use std::{
+    collections::HashSet,
+    fs, io,
+    path::{Component, Path, PathBuf},
+};
+use thiserror::Error;
+
+const MANIFEST: &str = "manifest.txt";
+
+#[derive(Error, Debug)]
+pub enum AnmDirError {
+    #[error(transparent)]
+    IOError(#[from] io::Error),
+    #[error(transparent)]
+    ReadingError(#[from] AnmReadingError),
+    #[error("{path}: {source}")]
+    ParseError {
+        path: PathBuf,
+        source: TextParseError,
+    },
+    #[error("{path}: not a plain file name: ({name:?})")]
+    InvalidFileNameError { path: PathBuf, name: String },
+}
+
+impl AnmFile {
+    /// Writes the file as a directory tree: a manifest, and a folder per class
+    /// with a text file per animation.
+    pub fn unpack_to_dir<P: AsRef<Path>>(&self, dir: P) -> Result<(), AnmDirError> {
+        let dir = dir.as_ref();
+        fs::create_dir_all(dir)?;
+
+        let mut manifest = String::new();
+        wln!(manifest, "header {}", self.header);
+
+        let mut class_dirs = UniqueNames::default();
+        for (key, class) in self.classes.iter() {
+            let class_dir = class_dirs.pick(key, "");
+            wln!(manifest);
+            wln!(manifest, "class {}", quote(key));
+            wln!(manifest, "index {}", quote(&class.index));
+            wln!(manifest, "file_name {}", quote(&class.file_name));
+            wln!(manifest, "dir {}", quote(&class_dir));
+
+            let class_path = dir.join(&class_dir);
+            fs::create_dir_all(&class_path)?;
+            let mut animation_files = UniqueNames::default();
+            for animation in class.animations.iter() {
+                let file_name = animation_files.pick(&animation.name, ".txt");
+                let text = text::print_animation(animation).map_err(|e| e.in_class(key))?;
+                fs::write(class_path.join(&file_name), text)?;
+                wln!(manifest, "animation {}", quote(&file_name));
+            }
+        }
+
+        fs::write(dir.join(MANIFEST), manifest)?;
+        Ok(())
+    }
+
+    /// Reads a directory tree written by `unpack_to_dir`.
+    /// Writing the result gives the same anm file that was unpacked.
+    pub fn pack_from_dir<P: AsRef<Path>>(dir: P) -> Result<Self, AnmDirError> {
+        let dir = dir.as_ref();
+        let manifest_path = dir.join(MANIFEST);
+        let manifest = fs::read_to_string(&manifest_path)?;
+        let parse_error = |path: &Path| {
+            let path = path.to_owned();
+            move |source| AnmDirError::ParseError { path, source }
+        };
+
+        let mut lines = Lines::new(&manifest);
+        let (header, entries) = parse_manifest(&mut lines).map_err(parse_error(&manifest_path))?;
+
+        let mut classes = ClassCollection::with_capacity(entries.len());
+        for entry in entries {
+            let class_path = dir.join(plain_file_name(&manifest_path, &entry.dir)?);
+            let mut animations = AnimationCollection::with_capacity(entry.animation_files.len());
+            for file_name in &entry.animation_files {
+                let path = class_path.join(plain_file_name(&manifest_path, file_name)?);
+                let text = fs::read_to_string(&path)?;
+                let animation = text::parse_animation(&text).map_err(parse_error(&path))?;
+                animations.push(animation);
+            }
+            classes.push(
+                entry.key,
+                AnmClass {
+                    index: entry.index,
+                    file_name: entry.file_name,
+                    animations,
+                },
+            );
+        }
+
+        Ok(Self::new(header, classes))
+    }
+}
+
+struct ClassEntry {
+    key: String,
+    index: String,
+    file_name: String,
+    dir: String,
+    animation_files: Vec<String>,
+}
+
+fn parse_manifest(lines: &mut Lines) -> Result<(i32, Vec<ClassEntry>), TextParseError> {
+    let mut line = lines.expect("header")?;
+    let header = line.number()?;
+    line.end()?;
+
+    let mut entries = Vec::new();
+    while let Some(mut line) = lines.next_if("class")? {
+        let key = line.string()?;
+        line.end()?;
+        let mut string_field = |name: &str| -> Result<String, TextParseError> {
+            let mut line = lines.expect(name)?;
+            let value = line.string()?;
+            line.end()?;
+            Ok(value)
+        };
+        let index = string_field("index")?;
+        let file_name = string_field("file_name")?;
+        let dir = string_field("dir")?;
+
+        let mut animation_files = Vec::new();
+        while let Some(mut line) = lines.next_if("animation")? {
+            animation_files.push(line.string()?);
+            line.end()?;
+        }
+        entries.push(ClassEntry {
+            key,
+            index,
+            file_name,
+            dir,
+            animation_files,
+        });
+    }
+
+    if let Some(line) = lines.next()? {
+        return Err(line.error("expected \"class\""));
+    }
+    Ok((header, entries))
+}
+
+/// Makes sure a name from the manifest can't point outside of the directory.
+fn plain_file_name<'a>(manifest_path: &Path, name: &'a str) -> Result<&'a str, AnmDirError> {
+    let mut components = Path::new(name).components();
+    match (components.next(), components.next()) {
+        (Some(Component::Normal(_)), None) => Ok(name),
+        _ => Err(AnmDirError::InvalidFileNameError {
+            path: manifest_path.to_owned(),
+            name: name.to_owned(),
+        }),
+    }
+}
+
+/// Names Windows reserves for devices, with or without an extension.
+const RESERVED_NAMES: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Leaves room for the extension and the counter within the usual limit of 255 bytes.
+const MAX_BASE_LENGTH: usize = 200;
+
+/// Turns class keys and animation names into file names that work on Windows, macOS and Linux,
+/// and are distinct even on case-insensitive file systems.
+/// Only ASCII letters, digits, `_` and `-` are kept, so there are no separators,
+/// leading or trailing dots or spaces. Reserved names like `CON` get a `_` appended.
+#[derive(Default)]
+struct UniqueNames {
+    taken: HashSet<String>,
+}
+
+impl UniqueNames {
+    fn pick(&mut self, name: &str, extension: &str) -> String {
+        let mut base: String = name
+            .chars()
+            .take(MAX_BASE_LENGTH)
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '_' || c == '-' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        if base.is_empty()
+            || RESERVED_NAMES
+                .iter()
+                .any(|reserved| base.eq_ignore_ascii_case(reserved))
+        {
+            base.push('_');
+        }
+
+        let mut candidate = format!("{base}{extension}");
+        let mut counter = 2;
+        while !self.taken.insert(candidate.to_lowercase()) {
+            candidate = format!("{base}~{counter}{extension}");
+            counter += 1;
+        }
+        candidate
+    }
+}
@@ -1,7 +1,14 @@
-use super::{AnmFrame, AnmReadingError, AnmWritingError};
+use super::{
+    AnmFrame, AnmReadingError, AnmWritingError, BoneBudget, PositionReader, ReadLimit, ReadLimits,
+    ReadOptions,
+};
 use byteorder::{LittleEndian as LE, ReadBytesExt, WriteBytesExt};
-use std::io::{Read, Write};
+use std::{
+    io::{Read, Write},
+    sync::OnceLock,
+};
 
+#[cfg_attr(feature = "serde", derive(serde::Deserialize))]
 pub struct AnmAnimation {
     pub name: String,
     pub loop_start: u32,
@@ -10,17 +17,161 @@ pub struct AnmAnimation {
     pub preview_frame: u32,
     pub base_start: u32,
     pub data: Vec<u32>,
-    pub frames: Vec<AnmFrame>,
+    #[cfg_attr(feature = "serde", serde(deserialize_with = "deserialize_frames"))]
+    frames: Frames,
+}
+
+enum Frames {
+    Decoded(Vec<AnmFrame>),
+    /// Read lazily and not modified since. Written back verbatim.
+    Raw(RawFrames),
+}
+
+struct RawFrames {
+    frame_count: usize,
+    bytes: Vec<u8>,
+    decoded: OnceLock<Vec<AnmFrame>>,
+    /// Where the bytes were in the decompressed data, for errors.
+    offset: u64,
+    class_key: String,
+    limits: ReadLimits,
+    /// Shared with the rest of the file, so decoding lazily is capped the same way.
+    bone_budget: BoneBudget,
+}
+
+impl RawFrames {
+    fn get(&self, name: &str) -> Result<&[AnmFrame], AnmReadingError> {
+        if let Some(frames) = self.decoded.get() {
+            return Ok(frames);
+        }
+        let frames = self.decode(name)?;
+        Ok(self.decoded.get_or_init(|| frames))
+    }
+
+    fn take(mut self, name: &str) -> Result<Vec<AnmFrame>, AnmReadingError> {
+        match self.decoded.take() {
+            Some(frames) => Ok(frames),
+            None => self.decode(name),
+        }
+    }
+
+    fn decode(&self, name: &str) -> Result<Vec<AnmFrame>, AnmReadingError> {
+        let mut reader = &self.bytes[..];
+        let result = AnmAnimation::read_frames(
+            &mut reader,
+            self.frame_count,
+            &self.limits,
+            &self.bone_budget,
+        )
+        .and_then(|frames| {
+            if reader.is_empty() {
+                Ok(frames)
+            } else {
+                Err(AnmReadingError::FramesByteSizeMismatchError {
+                    byte_count: self.bytes.len(),
+                    used: self.bytes.len() - reader.len(),
+                })
+            }
+        });
+        result.map_err(|e| {
+            let used = (self.bytes.len() - reader.len()) as u64;
+            e.at_offset(self.offset + used)
+                .in_class(&self.class_key)
+                .in_animation(name)
+        })
+    }
+}
+
+#[cfg(feature = "serde")]
+fn deserialize_frames<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Frames, D::Error> {
+    use serde::Deserialize;
+    Ok(Frames::Decoded(Vec::deserialize(deserializer)?))
 }
 
 impl AnmAnimation {
-    pub(super) fn read<R: Read>(mut reader: R) -> Result<Self, AnmReadingError> {
+    pub fn new(name: String, frames: Vec<AnmFrame>) -> Self {
+        Self {
+            name,
+            loop_start: 0,
+            recovery_start: 0,
+            free_start: 0,
+            preview_frame: 0,
+            base_start: 0,
+            data: Vec::new(),
+            frames: Frames::Decoded(frames),
+        }
+    }
+
+    /// Known without decoding the frames.
+    pub fn frame_count(&self) -> usize {
+        match &self.frames {
+            Frames::Decoded(frames) => frames.len(),
+            Frames::Raw(raw) => raw.frame_count,
+        }
+    }
+
+    /// Whether the frames were decoded. Frames read lazily are decoded on first access.
+    pub fn is_decoded(&self) -> bool {
+        match &self.frames {
+            Frames::Decoded(_) => true,
+            Frames::Raw(raw) => raw.decoded.get().is_some(),
+        }
+    }
+
+    /// Decodes the frames if they were read lazily. They are still written back verbatim.
+    pub fn frames(&self) -> Result<&[AnmFrame], AnmReadingError> {
+        match &self.frames {
+            Frames::Decoded(frames) => Ok(frames),
+            Frames::Raw(raw) => raw.get(&self.name),
+        }
+    }
+
+    /// Decodes the frames if they were read lazily. From now on, they are re-encoded when written.
+    pub fn frames_mut(&mut self) -> Result<&mut Vec<AnmFrame>, AnmReadingError> {
+        if let Frames::Raw(_) = self.frames {
+            let Frames::Raw(raw) = std::mem::replace(&mut self.frames, Frames::Decoded(Vec::new()))
+            else {
+                unreachable!()
+            };
+            self.frames = Frames::Decoded(raw.take(&self.name)?);
+        }
+        match &mut self.frames {
+            Frames::Decoded(frames) => Ok(frames),
+            Frames::Raw(_) => unreachable!(),
+        }
+    }
+
+    pub fn set_frames(&mut self, frames: Vec<AnmFrame>) {
+        self.frames = Frames::Decoded(frames);
+    }
+
+    pub(super) fn read<R: Read>(
+        reader: &mut PositionReader<R>,
+        class_key: &str,
+        options: &ReadOptions,
+        bone_budget: &BoneBudget,
+    ) -> Result<Self, AnmReadingError> {
         let name_length = reader.read_u16::<LE>()? as usize;
+        options.limits.check(ReadLimit::NameLength, name_length)?;
         let mut name_buf = vec![0u8; name_length];
         reader.read_exact(&mut name_buf)?;
         let name = String::from_utf8(name_buf)?;
 
+        Self::read_after_name(reader, name.clone(), class_key, options, bone_budget)
+            .map_err(|e| e.in_animation(&name))
+    }
+
+    fn read_after_name<R: Read>(
+        reader: &mut PositionReader<R>,
+        name: String,
+        class_key: &str,
+        options: &ReadOptions,
+        bone_budget: &BoneBudget,
+    ) -> Result<Self, AnmReadingError> {
         let frame_count = reader.read_u32::<LE>()? as usize;
+        options.limits.check(ReadLimit::FrameCount, frame_count)?;
         let loop_start = reader.read_u32::<LE>()?;
         let recovery_start = reader.read_u32::<LE>()?;
         let free_start = reader.read_u32::<LE>()?;
@@ -28,6 +179,7 @@ impl AnmAnimation {
         let base_start = reader.read_u32::<LE>()?;
 
         let data_size = reader.read_u32::<LE>()? as usize;
+        options.limits.check(ReadLimit::DataLength, data_size)?;
         let mut data = Vec::with_capacity(data_size);
         for _ in 0..data_size {
             data.push(reader.read_u32::<LE>()?);
@@ -36,15 +188,37 @@ impl AnmAnimation {
         /*
         this field stores the size of the frames array.
         it is used by the game to skip parsing the frames until it needs them.
-        we don't need it here, so we discard it.
+        we do the same when reading lazily.
         */
-        _ = reader.read_u32::<LE>()?;
+        let byte_count = reader.read_u32::<LE>()? as usize;
 
-        let mut frames = Vec::with_capacity(frame_count);
-        for _ in 0..frame_count {
-            let prev_frame = frames.last();
-            frames.push(AnmFrame::read(&mut reader, prev_frame)?);
-        }
+        let frames = if options.lazy_frames {
+            let offset = reader.position();
+            let mut bytes = Vec::new();
+            reader.take(byte_count as u64).read_to_end(&mut bytes)?;
+            if bytes.len() != byte_count {
+                return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into());
+            }
+            Frames::Raw(RawFrames {
+                frame_count,
+                bytes,
+                decoded: OnceLock::new(),
+                offset,
+                class_key: class_key.to_owned(),
+                limits: options.limits,
+                bone_budget: bone_budget.clone(),
+            })
+        } else {
+            let start = reader.position();
+            let frames =
+                Self::read_frames(&mut *reader, frame_count, &options.limits, bone_budget)?;
+            // checked like lazily read frames are, so both ways accept the same files
+            let used = (reader.position() - start) as usize;
+            if used != byte_count {
+                return Err(AnmReadingError::FramesByteSizeMismatchError { byte_count, used });
+            }
+            Frames::Decoded(frames)
+        };
 
         Ok(Self {
             name,
@@ -58,6 +232,22 @@ impl AnmAnimation {
         })
     }
 
+    fn read_frames<R: Read>(
+        mut reader: R,
+        frame_count: usize,
+        limits: &ReadLimits,
+        bone_budget: &BoneBudget,
+    ) -> Result<Vec<AnmFrame>, AnmReadingError> {
+        let mut frames = Vec::with_capacity(frame_count);
+        for i in 0..frame_count {
+            let prev_frame = frames.last();
+            let frame = AnmFrame::read(&mut reader, prev_frame, limits, bone_budget)
+                .map_err(|e| e.in_frame(i))?;
+            frames.push(frame);
+        }
+        Ok(frames)
+    }
+
     pub(super) fn write<W: Write>(&self, mut writer: W) -> Result<(), AnmWritingError> {
         let name_length = self.name.len();
         let name_length = match name_length.try_into() {
@@ -65,7 +255,7 @@ impl AnmAnimation {
             Err(_) => return Err(AnmWritingError::LongAnimNameError { name_length }),
         };
 
-        let frame_count = self.frames.len();
+        let frame_count = self.frame_count();
         let frame_count = match frame_count.try_into() {
             Ok(v) => v,
             Err(_) => return Err(AnmWritingError::TooManyFramesError { frame_count }),
@@ -97,28 +287,31 @@ impl AnmAnimation {
             writer.write_u32::<LE>(*datum)?;
         }
         writer.write_u32::<LE>(byte_count)?;
-        for (i, frame) in self.frames.iter().enumerate() {
-            let prev_frame = if i == 0 {
-                None
-            } else {
-                Some(&self.frames[i - 1])
-            };
-            frame.write(&mut writer, prev_frame)?;
+        match &self.frames {
+            Frames::Raw(raw) => writer.write_all(&raw.bytes)?,
+            Frames::Decoded(frames) => {
+                for (i, frame) in frames.iter().enumerate() {
+                    let prev_frame = if i == 0 { None } else { Some(&frames[i - 1]) };
+                    frame
+                        .write(&mut writer, prev_frame)
+                        .map_err(|e| e.in_frame(i))?;
+                }
+            }
         }
 
         Ok(())
     }
 
     fn get_frames_byte_size(&self) -> usize {
+        let frames = match &self.frames {
+            Frames::Raw(raw) => return raw.bytes.len(),
+            Frames::Decoded(frames) => frames,
+        };
         let mut result = 0usize;
-        for (i, frame) in self.frames.iter().enumerate() {
-            let prev_frame = if i == 0 {
-                None
-            } else {
-                Some(&self.frames[i - 1])
-            };
+        for (i, frame) in frames.iter().enumerate() {
+            let prev_frame = if i == 0 { None } else { Some(&frames[i - 1]) };
             result += frame.get_byte_size(prev_frame);
         }
-        return result;
+        result
     }
 }
@@ -1,8 +1,10 @@
 use super::{AnmReadingError, AnmWritingError};
+use crate::{Affine2, Decomposition};
 use byteorder::{LittleEndian as LE, ReadBytesExt, WriteBytesExt};
 use std::io::{Read, Write};
 
 #[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct AnmBone {
     pub id: i16,
     pub scale_x: f32,
@@ -13,6 +15,202 @@ pub struct AnmBone {
     pub y: f32,
     pub opacity: f64,
     pub frame: i8,
+    /// See `AnmBone::encoding`. Private, so that adding to what a bone records
+    /// doesn't break code that builds bones.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub(crate) encoding: Option<BoneEncoding>,
+}
+
+/// How a bone was stored in its frame.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum BoneEncoding {
+    /// The bone is a copy of the bone at the same index in the previous frame.
+    /// If `keep_frame` is false, the sprite frame is stored separately.
+    ClonePrevFrame { keep_frame: bool },
+    /// The bone is stored in full.
+    Stored(StoredBoneEncoding),
+}
+
+/// The compression choices of a stored bone.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StoredBoneEncoding {
+    pub transform: TransformEncoding,
+    /// The position is copied from the previous bone.
+    pub copy_position: bool,
+    /// The sprite frame is stored. If not, it is 1.
+    pub has_frame: bool,
+    /// The opacity is not stored. If so, it is 1.
+    pub opaque: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TransformEncoding {
+    /// Copied from the previous bone.
+    CopyPrev,
+    /// Not stored at all.
+    Identity,
+    /// Only `scale_x` and `rotate_skew0` are stored.
+    Symmetric,
+    /// The whole matrix is stored.
+    Full,
+}
+
+impl StoredBoneEncoding {
+    pub(super) fn byte_size(&self) -> usize {
+        let mut result = 0usize;
+        result += size_of::<u16>(); // id
+        result += size_of::<u8>(); // opaque
+
+        result += size_of::<u8>(); // copy transform indicator
+        match self.transform {
+            TransformEncoding::CopyPrev => {}
+            TransformEncoding::Identity => {
+                result += size_of::<u8>(); // identity/symmetric indicator
+                result += size_of::<u8>(); // 2nd indicator
+            }
+            TransformEncoding::Symmetric => {
+                result += size_of::<u8>(); // identity/symmetric indicator
+                result += size_of::<u8>(); // 2nd indicator
+                result += size_of::<f32>(); // scale_x
+                result += size_of::<f32>(); // rotate_skew0
+            }
+            TransformEncoding::Full => {
+                result += size_of::<u8>(); // identity/symmetric indicator
+                result += size_of::<f32>() * 4; // matrix
+            }
+        }
+
+        result += size_of::<u8>(); // copy position indicator
+        if !self.copy_position {
+            result += size_of::<f32>(); // x
+            result += size_of::<f32>(); // y
+        }
+
+        result += size_of::<u8>(); // default frame indicator
+        if self.has_frame {
+            result += size_of::<i8>(); // frame
+        }
+
+        if !self.opaque {
+            result += size_of::<u8>(); // opacity
+        }
+
+        result
+    }
+}
+
+impl AnmBone {
+    /// An opaque bone with the identity matrix at the origin, showing sprite frame 1.
+    pub fn new(id: i16) -> Self {
+        Self {
+            id,
+            scale_x: 1.,
+            rotate_skew0: 0.,
+            rotate_skew1: 0.,
+            scale_y: 1.,
+            x: 0.,
+            y: 0.,
+            opacity: 1.,
+            frame: 1,
+            encoding: None,
+        }
+    }
+
+    /// The encoding this bone was read with. When writing, every recorded choice
+    /// that is still valid for the bone's current values is replayed, so unmodified
+    /// bones are written exactly as they were read.
+    /// `None` for bones created by hand, in which case the most compact encoding is picked.
+    pub fn encoding(&self) -> Option<BoneEncoding> {
+        self.encoding
+    }
+
+    /// Choices that aren't valid for the bone's values when it is written are ignored.
+    pub fn set_encoding(&mut self, encoding: Option<BoneEncoding>) {
+        self.encoding = encoding;
+    }
+
+    /// The bone's matrix and position.
+    pub fn transform(&self) -> Affine2 {
+        Affine2::new(
+            self.scale_x as f64,
+            self.rotate_skew0 as f64,
+            self.rotate_skew1 as f64,
+            self.scale_y as f64,
+            self.x as f64,
+            self.y as f64,
+        )
+    }
+
+    /// Sets the matrix and position, rounding to `f32`.
+    pub fn set_transform(&mut self, transform: Affine2) {
+        self.scale_x = transform.a as f32;
+        self.rotate_skew0 = transform.b as f32;
+        self.rotate_skew1 = transform.c as f32;
+        self.scale_y = transform.d as f32;
+        self.x = transform.tx as f32;
+        self.y = transform.ty as f32;
+    }
+
+    pub fn decompose(&self) -> Decomposition {
+        self.transform().decompose()
+    }
+
+    pub fn recompose(&mut self, decomposition: Decomposition) {
+        self.set_transform(decomposition.recompose());
+    }
+
+    /// In radians. See `Decomposition` for the conventions.
+    pub fn rotation(&self) -> f64 {
+        self.decompose().rotation
+    }
+
+    pub fn scale(&self) -> (f64, f64) {
+        self.decompose().scale
+    }
+
+    /// In radians.
+    pub fn skew(&self) -> f64 {
+        self.decompose().skew
+    }
+
+    pub fn is_flipped(&self) -> bool {
+        self.decompose().flip
+    }
+
+    /// Keeps the position, scale, skew and flip.
+    pub fn set_rotation(&mut self, rotation: f64) {
+        self.recompose(Decomposition {
+            rotation,
+            ..self.decompose()
+        });
+    }
+
+    pub fn set_scale(&mut self, scale: (f64, f64)) {
+        self.recompose(Decomposition {
+            scale,
+            ..self.decompose()
+        });
+    }
+
+    pub fn set_skew(&mut self, skew: f64) {
+        self.recompose(Decomposition {
+            skew,
+            ..self.decompose()
+        });
+    }
+
+    pub fn set_flipped(&mut self, flip: bool) {
+        self.recompose(Decomposition {
+            flip,
+            ..self.decompose()
+        });
+    }
 }
 
 impl AnmBone {
@@ -28,14 +226,16 @@ impl AnmBone {
         let rotate_skew0;
         let rotate_skew1;
         let scale_y;
+        let transform_encoding;
         if copy_transform {
+            transform_encoding = TransformEncoding::CopyPrev;
             if let Some(prev_bone) = prev_bone {
                 scale_x = prev_bone.scale_x;
                 rotate_skew0 = prev_bone.rotate_skew0;
                 rotate_skew1 = prev_bone.rotate_skew1;
                 scale_y = prev_bone.scale_y;
             } else {
-                return Err(AnmReadingError::NoPrevBoneTransformError().into());
+                return Err(AnmReadingError::NoPrevBoneTransformError());
             }
         } else {
             let mut identity = false;
@@ -47,6 +247,14 @@ impl AnmBone {
                 symmetric = !identity;
             }
 
+            transform_encoding = if identity {
+                TransformEncoding::Identity
+            } else if symmetric {
+                TransformEncoding::Symmetric
+            } else {
+                TransformEncoding::Full
+            };
+
             if identity {
                 scale_x = 1.;
                 rotate_skew0 = 0.;
@@ -73,7 +281,7 @@ impl AnmBone {
                 x = prev_bone.x;
                 y = prev_bone.y;
             } else {
-                return Err(AnmReadingError::NoPrevBonePositionError().into());
+                return Err(AnmReadingError::NoPrevBonePositionError());
             }
         } else {
             x = reader.read_f32::<LE>()?;
@@ -102,56 +310,130 @@ impl AnmBone {
             y,
             opacity,
             frame,
+            encoding: Some(BoneEncoding::Stored(StoredBoneEncoding {
+                transform: transform_encoding,
+                copy_position,
+                has_frame,
+                opaque,
+            })),
         })
     }
 
+    /// Picks how to store this bone.
+    /// `prev_frame_bone` is the bone at the same index in the previous frame, which this bone can be a clone of.
+    /// `prev_bone` is the previous bone in the same frame, which this bone can copy its transform and position from.
+    pub(super) fn resolve_encoding(
+        &self,
+        prev_frame_bone: Option<&Self>,
+        prev_bone: Option<&Self>,
+    ) -> BoneEncoding {
+        let clone_source = prev_frame_bone.filter(|prev| self.is_partial_clone_of(prev));
+        if let Some(clone_source) = clone_source {
+            let same_frame = self.frame == clone_source.frame;
+            match self.encoding {
+                Some(BoneEncoding::ClonePrevFrame { keep_frame }) => {
+                    return BoneEncoding::ClonePrevFrame {
+                        keep_frame: keep_frame && same_frame,
+                    };
+                }
+                // the bone was stored in full, so keep it that way
+                Some(BoneEncoding::Stored(_)) => {}
+                None => {
+                    return BoneEncoding::ClonePrevFrame {
+                        keep_frame: same_frame,
+                    };
+                }
+            }
+        }
+
+        BoneEncoding::Stored(self.resolve_stored_encoding(prev_bone))
+    }
+
+    fn resolve_stored_encoding(&self, prev_bone: Option<&Self>) -> StoredBoneEncoding {
+        let recorded = match self.encoding {
+            Some(BoneEncoding::Stored(recorded)) => Some(recorded),
+            _ => None,
+        };
+
+        let can_copy_transform = prev_bone.is_some_and(|prev| self.has_same_transform_as(prev));
+        let transform = recorded
+            .map(|r| r.transform)
+            .filter(|&transform| match transform {
+                TransformEncoding::CopyPrev => can_copy_transform,
+                TransformEncoding::Identity => self.is_identity(),
+                TransformEncoding::Symmetric => self.is_symmetric(),
+                TransformEncoding::Full => true,
+            })
+            .unwrap_or(if can_copy_transform {
+                TransformEncoding::CopyPrev
+            } else if self.is_identity() {
+                TransformEncoding::Identity
+            } else if self.is_symmetric() {
+                TransformEncoding::Symmetric
+            } else {
+                TransformEncoding::Full
+            });
+
+        let can_copy_position = prev_bone.is_some_and(|prev| self.has_same_position_as(prev));
+        let copy_position = match recorded {
+            Some(r) => r.copy_position && can_copy_position,
+            None => can_copy_position,
+        };
+
+        let has_frame = match recorded {
+            Some(r) => r.has_frame || self.frame != 1,
+            None => self.frame != 1,
+        };
+
+        let opaque = match recorded {
+            Some(r) => r.opaque && self.opacity == 1.,
+            None => self.opacity == 1.,
+        };
+
+        StoredBoneEncoding {
+            transform,
+            copy_position,
+            has_frame,
+            opaque,
+        }
+    }
+
+    /// `encoding` must come from `resolve_stored_encoding`.
     pub(super) fn write<W: Write>(
         &self,
         mut writer: W,
-        prev_bone: Option<&Self>,
+        encoding: StoredBoneEncoding,
     ) -> Result<(), AnmWritingError> {
         writer.write_i16::<LE>(self.id)?;
-        let opaque = self.opacity == 1.;
-        writer.write_u8(if opaque { 1 } else { 0 })?;
+        writer.write_u8(if encoding.opaque { 1 } else { 0 })?;
 
-        let copy_transform = if let Some(prev_bone) = prev_bone {
-            self.has_same_transform_as(&prev_bone)
-        } else {
-            false
-        };
-        if copy_transform {
-            writer.write_u8(1)?;
-        } else {
-            writer.write_u8(0)?;
-            let is_identity = self.is_identity();
-            let is_symmetric = self.is_symmetric();
-            if is_identity || is_symmetric {
+        match encoding.transform {
+            TransformEncoding::CopyPrev => {
                 writer.write_u8(1)?;
-                if is_identity {
-                    writer.write_u8(1)?;
-                } else {
-                    writer.write_u8(0)?;
-                }
-            } else {
+            }
+            TransformEncoding::Identity => {
                 writer.write_u8(0)?;
+                writer.write_u8(1)?;
+                writer.write_u8(1)?;
             }
-
-            if !is_identity {
+            TransformEncoding::Symmetric => {
+                writer.write_u8(0)?;
+                writer.write_u8(1)?;
+                writer.write_u8(0)?;
                 writer.write_f32::<LE>(self.scale_x)?;
                 writer.write_f32::<LE>(self.rotate_skew0)?;
-                if !is_symmetric {
-                    writer.write_f32::<LE>(self.rotate_skew1)?;
-                    writer.write_f32::<LE>(self.scale_y)?;
-                }
+            }
+            TransformEncoding::Full => {
+                writer.write_u8(0)?;
+                writer.write_u8(0)?;
+                writer.write_f32::<LE>(self.scale_x)?;
+                writer.write_f32::<LE>(self.rotate_skew0)?;
+                writer.write_f32::<LE>(self.rotate_skew1)?;
+                writer.write_f32::<LE>(self.scale_y)?;
             }
         }
 
-        let copy_position = if let Some(prev_bone) = prev_bone {
-            self.has_same_position_as(&prev_bone)
-        } else {
-            false
-        };
-        if copy_position {
+        if encoding.copy_position {
             writer.write_u8(1)?;
         } else {
             writer.write_u8(0)?;
@@ -159,14 +441,14 @@ impl AnmBone {
             writer.write_f32::<LE>(self.y)?;
         }
 
-        if self.frame == 1 {
-            writer.write_u8(0)?;
-        } else {
+        if encoding.has_frame {
             writer.write_u8(1)?;
             writer.write_i8(self.frame)?;
+        } else {
+            writer.write_u8(0)?;
         }
 
-        if !opaque {
+        if !encoding.opaque {
             let opacity_byte = f64::round(self.opacity * 255.) as u8;
             writer.write_u8(opacity_byte)?;
         }
@@ -174,83 +456,36 @@ impl AnmBone {
         Ok(())
     }
 
+    /// Whether this bone can be stored as a copy of `other`, possibly with a different sprite frame.
     pub(super) fn is_partial_clone_of(&self, other: &Self) -> bool {
         self.has_same_transform_as(other)
             && self.has_same_position_as(other)
             && self.id == other.id
-            && self.frame == other.frame
-            && self.opacity == other.opacity
+            && self.opacity.to_bits() == other.opacity.to_bits()
     }
 
+    // the comparisons below are bitwise, so that -0.0 and NaN survive a round trip.
+
     fn has_same_transform_as(&self, other: &Self) -> bool {
-        self.scale_x == other.scale_x
-            && self.rotate_skew0 == other.rotate_skew0
-            && self.rotate_skew1 == other.rotate_skew1
-            && self.scale_y == other.scale_y
+        self.scale_x.to_bits() == other.scale_x.to_bits()
+            && self.rotate_skew0.to_bits() == other.rotate_skew0.to_bits()
+            && self.rotate_skew1.to_bits() == other.rotate_skew1.to_bits()
+            && self.scale_y.to_bits() == other.scale_y.to_bits()
     }
 
     fn has_same_position_as(&self, other: &Self) -> bool {
-        self.x == other.x && self.y == other.y
+        self.x.to_bits() == other.x.to_bits() && self.y.to_bits() == other.y.to_bits()
     }
 
     fn is_identity(&self) -> bool {
-        self.scale_x == 1.
-            && self.rotate_skew0 == 0.
-            && self.rotate_skew1 == 0.
-            && self.scale_y == 1.
+        self.scale_x.to_bits() == 1f32.to_bits()
+            && self.rotate_skew0.to_bits() == 0f32.to_bits()
+            && self.rotate_skew1.to_bits() == 0f32.to_bits()
+            && self.scale_y.to_bits() == 1f32.to_bits()
     }
 
     fn is_symmetric(&self) -> bool {
-        self.scale_y == -self.scale_x && self.rotate_skew0 == self.rotate_skew1
-    }
-
-    pub(super) fn get_byte_size(&self, prev_bone: Option<&Self>) -> usize {
-        let mut result = 0usize;
-        result += size_of::<u16>(); // id
-        result += size_of::<u8>(); // opaque
-
-        result += size_of::<u8>(); // copy transform indicator
-        let copy_transform = match prev_bone {
-            Some(prev) => self.has_same_transform_as(prev),
-            None => false,
-        };
-        // can't copy transform, so have to store more data
-        if !copy_transform {
-            result += size_of::<u8>(); // identity/symmetric indicator
-            if self.is_identity() {
-                result += size_of::<u8>(); // 2nd indicator
-            } else {
-                result += size_of::<f32>(); // scale_x
-                result += size_of::<f32>(); // rotate_skew0
-                if self.is_symmetric() {
-                    result += size_of::<f32>(); // 2nd indicator
-                } else {
-                    result += size_of::<f32>(); // rotate_skew1
-                    result += size_of::<f32>(); // scale_y
-                }
-            }
-        }
-
-        result += size_of::<u8>(); // copy position indicator
-        let copy_position = match prev_bone {
-            Some(prev) => self.has_same_position_as(prev),
-            None => false,
-        };
-        // can't copy position, so have to store more data
-        if !copy_position {
-            result += size_of::<f32>(); // x
-            result += size_of::<f32>(); // y
-        }
-
-        result += size_of::<u8>(); // default frame indicator
-        if self.frame != 1 {
-            result += size_of::<i8>(); // frame
-        }
-
-        if self.opacity != 1. {
-            result += size_of::<u8>(); // opacity
-        }
-
-        return result;
+        self.scale_y.to_bits() == (-self.scale_x).to_bits()
+            && self.rotate_skew0.to_bits() == self.rotate_skew1.to_bits()
     }
 }
@@ -1,9 +1,12 @@
 use crate::AnmWritingError;
 
-use super::{AnmAnimation, AnmReadingError};
+use super::{
+    AnmAnimation, AnmReadingError, BoneBudget, NameIndex, PositionReader, ReadLimit, ReadOptions,
+};
 use byteorder::{LittleEndian as LE, ReadBytesExt, WriteBytesExt};
-use std::{collections::HashMap, io::Read, io::Write};
+use std::{cmp::Ordering, io::Read, io::Write};
 
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct AnmClass {
     pub index: String,
     pub file_name: String,
@@ -11,22 +14,35 @@ pub struct AnmClass {
 }
 
 impl AnmClass {
-    pub(super) fn read<R: Read>(mut reader: R) -> Result<Self, AnmReadingError> {
+    pub(super) fn read<R: Read>(
+        reader: &mut PositionReader<R>,
+        key: &str,
+        options: &ReadOptions,
+        bone_budget: &BoneBudget,
+    ) -> Result<Self, AnmReadingError> {
         let index_length = reader.read_u16::<LE>()? as usize;
+        options.limits.check(ReadLimit::NameLength, index_length)?;
         let mut index_buf = vec![0u8; index_length];
         reader.read_exact(&mut index_buf)?;
         let index = String::from_utf8(index_buf)?;
 
         let file_name_length = reader.read_u16::<LE>()? as usize;
+        options
+            .limits
+            .check(ReadLimit::NameLength, file_name_length)?;
         let mut file_name_buf = vec![0u8; file_name_length];
         reader.read_exact(&mut file_name_buf)?;
         let file_name = String::from_utf8(file_name_buf)?;
 
         let animation_count = reader.read_u32::<LE>()? as usize;
+        options
+            .limits
+            .check(ReadLimit::AnimationCount, animation_count)?;
         let mut animations = AnimationCollection::with_capacity(animation_count);
         for _ in 0..animation_count {
-            let animation = AnmAnimation::read(&mut reader)?;
-            animations.insert(animation);
+            let animation = AnmAnimation::read(reader, key, options, bone_budget)?;
+            // duplicates are kept here. AnmFile decides what to do with them.
+            animations.push(animation);
         }
 
         Ok(Self {
@@ -62,27 +78,44 @@ impl AnmClass {
 
         writer.write_u32::<LE>(animation_count)?;
         for animation in self.animations.iter() {
-            animation.write(&mut writer)?;
+            animation
+                .write(&mut writer)
+                .map_err(|e| e.in_animation(&animation.name))?;
         }
 
         Ok(())
     }
 }
 
+/// The animations of a class, kept in the order they appear in the file.
+///
+/// Animations are found by name through an index. Animations borrowed mutably can be renamed,
+/// so after `iter_mut`, the index is rebuilt by the next call that takes `&mut self`,
+/// and `get` and `index_of` search the animations until then.
 pub struct AnimationCollection {
-    animations: HashMap<String, AnmAnimation>,
+    animations: Vec<AnmAnimation>,
+    index: NameIndex,
+    lent: Lent,
+}
+
+/// Animations borrowed mutably since the index was last checked.
+enum Lent {
+    None,
+    /// One animation, and its name when it was borrowed.
+    One(usize, String),
+    All,
 }
 
 impl AnimationCollection {
     pub fn new() -> Self {
-        Self {
-            animations: HashMap::new(),
-        }
+        Self::with_capacity(0)
     }
 
     pub fn with_capacity(capacity: usize) -> Self {
         Self {
-            animations: HashMap::with_capacity(capacity),
+            animations: Vec::with_capacity(capacity),
+            index: NameIndex::with_capacity(capacity),
+            lent: Lent::None,
         }
     }
 
@@ -90,15 +123,144 @@ impl AnimationCollection {
         self.animations.len()
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.animations.is_empty()
+    }
+
+    /// Inserts an animation. If an animation with the same name exists, it is replaced in place and returned.
+    /// Otherwise, the animation is appended at the end.
     pub fn insert(&mut self, animation: AnmAnimation) -> Option<AnmAnimation> {
-        self.animations.insert(animation.name.clone(), animation)
+        self.settle();
+        match self.index.get(&animation.name) {
+            Some(index) => Some(std::mem::replace(&mut self.animations[index], animation)),
+            None => {
+                self.push(animation);
+                None
+            }
+        }
+    }
+
+    /// Appends an animation, even if one with the same name exists.
+    pub fn push(&mut self, animation: AnmAnimation) {
+        // a stale index is rebuilt later, with this animation in it
+        self.index.push(&animation.name, self.animations.len());
+        self.animations.push(animation);
     }
 
+    /// Returns the first animation with this name.
     pub fn get(&self, name: &str) -> Option<&AnmAnimation> {
-        self.animations.get(name)
+        self.index_of(name).map(|i| &self.animations[i])
+    }
+
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut AnmAnimation> {
+        self.settle();
+        let index = self.index.get(name)?;
+        self.lent = Lent::One(index, name.to_owned());
+        Some(&mut self.animations[index])
+    }
+
+    pub fn get_index(&self, index: usize) -> Option<&AnmAnimation> {
+        self.animations.get(index)
+    }
+
+    pub fn get_index_mut(&mut self, index: usize) -> Option<&mut AnmAnimation> {
+        self.settle();
+        let animation = self.animations.get_mut(index)?;
+        self.lent = Lent::One(index, animation.name.clone());
+        Some(animation)
+    }
+
+    /// The index of the first animation with this name.
+    pub fn index_of(&self, name: &str) -> Option<usize> {
+        if self.is_index_stale() {
+            return self.animations.iter().position(|a| a.name == name);
+        }
+        self.index.get(name)
+    }
+
+    /// Removes an animation, shifting the following animations back.
+    pub fn remove(&mut self, name: &str) -> Option<AnmAnimation> {
+        let index = self.index_of(name)?;
+        self.remove_index(index)
+    }
+
+    /// Removes the animation at an index, shifting the following animations back.
+    pub fn remove_index(&mut self, index: usize) -> Option<AnmAnimation> {
+        if index >= self.animations.len() {
+            return None;
+        }
+        let animation = self.animations.remove(index);
+        self.reindex();
+        Some(animation)
+    }
+
+    /// Moves the animation at `from` to `to`, shifting the animations in between.
+    ///
+    /// Panics if either index is out of bounds.
+    pub fn move_index(&mut self, from: usize, to: usize) {
+        let animation = self.animations.remove(from);
+        self.animations.insert(to, animation);
+        self.reindex();
+    }
+
+    /// Panics if either index is out of bounds.
+    pub fn swap_indices(&mut self, a: usize, b: usize) {
+        self.animations.swap(a, b);
+        self.reindex();
+    }
+
+    /// Stable sort of the animations.
+    pub fn sort_by<F>(&mut self, compare: F)
+    where
+        F: FnMut(&AnmAnimation, &AnmAnimation) -> Ordering,
+    {
+        self.animations.sort_by(compare);
+        self.reindex();
     }
 
     pub fn iter(&self) -> impl Iterator<Item = &AnmAnimation> {
-        self.animations.values()
+        self.animations.iter()
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut AnmAnimation> {
+        self.lent = Lent::All;
+        self.animations.iter_mut()
+    }
+
+    fn is_index_stale(&self) -> bool {
+        match &self.lent {
+            Lent::None => false,
+            Lent::One(index, name) => self.animations[*index].name != *name,
+            Lent::All => true,
+        }
+    }
+
+    /// Rebuilds the index if a borrowed animation may have been renamed.
+    fn settle(&mut self) {
+        if self.is_index_stale() {
+            self.reindex();
+        }
+        self.lent = Lent::None;
+    }
+
+    fn reindex(&mut self) {
+        self.index
+            .rebuild(self.animations.iter().map(|a| a.name.as_str()));
+        self.lent = Lent::None;
+    }
+}
+
+impl Default for AnimationCollection {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl IntoIterator for AnimationCollection {
+    type Item = AnmAnimation;
+    type IntoIter = std::vec::IntoIter<AnmAnimation>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.animations.into_iter()
     }
 }
@@ -1,45 +1,159 @@
-use super::{AnmClass, AnmReadingError, AnmWritingError};
+use super::{
+    AnmClass, AnmReadingError, AnmReadingWarning, AnmWritingError, BoneBudget, DuplicateMode,
+    NameIndex, PositionReader, ReadLimit, ReadOptions,
+};
 use byteorder::{LittleEndian as LE, ReadBytesExt, WriteBytesExt};
 use flate2::{Compression, read::ZlibDecoder, write::ZlibEncoder};
 use std::{
-    collections::HashMap,
+    cmp::Ordering,
+    collections::HashSet,
     io::{Read, Write},
 };
 
-type ClassesCollection = HashMap<String, AnmClass>;
-
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct AnmFile {
     pub header: i32,
-    pub classes: ClassesCollection,
+    pub classes: ClassCollection,
+    /// The zlib stream as it was read, written back if the data in it is unchanged.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    compressed: Option<Vec<u8>>,
 }
 
 impl AnmFile {
-    pub fn read<R: Read>(mut reader: R) -> Result<Self, AnmReadingError> {
-        let header = reader.read_i32::<LE>()?;
-        let zlib = ZlibDecoder::new(reader);
-        Ok(Self {
+    pub fn new(header: i32, classes: ClassCollection) -> Self {
+        Self {
             header,
-            classes: Self::read_classes(zlib)?,
-        })
+            classes,
+            compressed: None,
+        }
+    }
+
+    /// Reads with the default options. Duplicate class keys and animation names are kept.
+    pub fn read<R: Read>(reader: R) -> Result<Self, AnmReadingError> {
+        Self::read_with_options(reader, &ReadOptions::default()).map(|(file, _)| file)
     }
 
+    pub fn read_with_options<R: Read>(
+        mut reader: R,
+        options: &ReadOptions,
+    ) -> Result<(Self, Vec<AnmReadingWarning>), AnmReadingError> {
+        let header = reader.read_i32::<LE>()?;
+        let mut zlib = PositionReader::new(
+            ZlibDecoder::new(Recorder::new(reader)),
+            options.limits.max_decompressed_size,
+        );
+        let mut warnings = Vec::new();
+        let classes = Self::read_classes(&mut zlib, options, &mut warnings)
+            .map_err(|e| e.at_offset(zlib.position()))?;
+        let compressed = Self::finish_stream(zlib);
+        Ok((
+            Self {
+                header,
+                classes,
+                compressed,
+            },
+            warnings,
+        ))
+    }
+
+    /// The compressed stream that was read, if it ends right after the classes.
+    fn finish_stream<R: Read>(
+        mut zlib: PositionReader<ZlibDecoder<Recorder<R>>>,
+    ) -> Option<Vec<u8>> {
+        // the stream's checksum comes after the data, and is only read here
+        if !matches!(zlib.read(&mut [0]), Ok(0)) {
+            return None;
+        }
+        let decoder = zlib.into_inner();
+        let length = decoder.total_in() as usize;
+        let mut bytes = decoder.into_inner().bytes;
+        // the decoder may have read ahead of the stream
+        bytes.truncate(length);
+        Some(bytes)
+    }
+
+    /// A file that was read is written back byte for byte, compressed stream included,
+    /// as long as the data in the stream is unchanged. Otherwise the data is compressed again.
+    /// Bones are written with the encoding they were read with where possible (see `AnmBone::encoding`),
+    /// so the data only changes where the file was edited.
+    ///
+    /// The writer is flushed at the end, so buffered writers report their errors here.
     pub fn write<W: Write>(&self, mut writer: W) -> Result<(), AnmWritingError> {
         writer.write_i32::<LE>(self.header)?;
-        let zlib = ZlibEncoder::new(writer, Compression::best());
-        self.write_classes(zlib)?;
+        let Some(compressed) = &self.compressed else {
+            let mut zlib = ZlibEncoder::new(writer, Compression::best());
+            self.write_classes(&mut zlib)?;
+            zlib.finish()?.flush()?;
+            return Ok(());
+        };
+
+        let mut data = Vec::new();
+        self.write_classes(&mut data)?;
+        if decompresses_to(compressed, &data) {
+            writer.write_all(compressed)?;
+        } else {
+            let mut zlib = ZlibEncoder::new(&mut writer, Compression::best());
+            zlib.write_all(&data)?;
+            zlib.finish()?;
+        }
+        writer.flush()?;
 
         Ok(())
     }
 
-    fn read_classes<R: Read>(mut reader: R) -> Result<ClassesCollection, AnmReadingError> {
-        let mut classes = ClassesCollection::new();
+    fn read_classes<R: Read>(
+        reader: &mut PositionReader<R>,
+        options: &ReadOptions,
+        warnings: &mut Vec<AnmReadingWarning>,
+    ) -> Result<ClassCollection, AnmReadingError> {
+        let mut classes = ClassCollection::new();
+        let mut seen_keys = HashSet::new();
+        let bone_budget = BoneBudget::default();
         while reader.read_u8()? != 0 {
             let key_length = reader.read_u16::<LE>()? as usize;
+            options.limits.check(ReadLimit::NameLength, key_length)?;
             let mut key_buf = vec![0u8; key_length];
             reader.read_exact(&mut key_buf)?;
             let key = String::from_utf8(key_buf)?;
-            let class = AnmClass::read(&mut reader)?;
-            classes.insert(key, class);
+            let class = AnmClass::read(reader, &key, options, &bone_budget)
+                .map_err(|e| e.in_class(&key))?;
+
+            let mut seen_names = HashSet::new();
+            for animation in class.animations.iter() {
+                if seen_names.insert(animation.name.as_str()) {
+                    continue;
+                }
+                let class_key = key.clone();
+                let name = animation.name.clone();
+                match options.duplicates {
+                    DuplicateMode::Strict => {
+                        return Err(AnmReadingError::DuplicateAnimationNameError {
+                            class_key,
+                            name: name.clone(),
+                        }
+                        .in_class(&key)
+                        .in_animation(&name));
+                    }
+                    DuplicateMode::Lenient => {
+                        warnings
+                            .push(AnmReadingWarning::DuplicateAnimationName { class_key, name });
+                    }
+                }
+            }
+
+            if !seen_keys.insert(key.clone()) {
+                let key = key.clone();
+                match options.duplicates {
+                    DuplicateMode::Strict => {
+                        return Err(AnmReadingError::DuplicateClassKeyError { key: key.clone() }
+                            .in_class(&key));
+                    }
+                    DuplicateMode::Lenient => {
+                        warnings.push(AnmReadingWarning::DuplicateClassKey { key });
+                    }
+                }
+            }
+            classes.push(key, class);
         }
 
         Ok(classes)
@@ -50,16 +164,193 @@ impl AnmFile {
             let key_length = key.len();
             let key_length = match key_length.try_into() {
                 Ok(v) => v,
-                Err(_) => return Err(AnmWritingError::TooLongClassKey { key_length }),
+                Err(_) => {
+                    return Err(AnmWritingError::TooLongClassKey { key_length }.in_class(key));
+                }
             };
 
             writer.write_u8(1)?;
             writer.write_u16::<LE>(key_length)?;
             writer.write_all(key.as_bytes())?;
-            class.write(&mut writer)?;
+            class.write(&mut writer).map_err(|e| e.in_class(key))?;
         }
         writer.write_u8(0)?;
 
         Ok(())
     }
 }
+
+/// Keeps a copy of the bytes read through it.
+struct Recorder<R> {
+    inner: R,
+    bytes: Vec<u8>,
+}
+
+impl<R: Read> Recorder<R> {
+    fn new(inner: R) -> Self {
+        Self {
+            inner,
+            bytes: Vec::new(),
+        }
+    }
+}
+
+impl<R: Read> Read for Recorder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.bytes.extend_from_slice(&buf[..read]);
+        Ok(read)
+    }
+}
+
+fn decompresses_to(compressed: &[u8], data: &[u8]) -> bool {
+    let mut decompressed = Vec::with_capacity(data.len());
+    // one byte more than `data` is enough to tell that it differs
+    let result = ZlibDecoder::new(compressed)
+        .take(data.len() as u64 + 1)
+        .read_to_end(&mut decompressed);
+    result.is_ok() && decompressed == data
+}
+
+/// The classes of an anm file, kept in the order they appear in the file.
+pub struct ClassCollection {
+    classes: Vec<(String, AnmClass)>,
+    index: NameIndex,
+}
+
+impl ClassCollection {
+    pub fn new() -> Self {
+        Self {
+            classes: Vec::new(),
+            index: NameIndex::default(),
+        }
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            classes: Vec::with_capacity(capacity),
+            index: NameIndex::with_capacity(capacity),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.classes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.classes.is_empty()
+    }
+
+    /// Inserts a class. If a class with the same key exists, it is replaced in place and returned.
+    /// Otherwise, the class is appended at the end.
+    pub fn insert(&mut self, key: String, class: AnmClass) -> Option<AnmClass> {
+        match self.index_of(&key) {
+            Some(index) => Some(std::mem::replace(&mut self.classes[index].1, class)),
+            None => {
+                self.push(key, class);
+                None
+            }
+        }
+    }
+
+    /// Appends a class, even if one with the same key exists.
+    pub fn push(&mut self, key: String, class: AnmClass) {
+        self.index.push(&key, self.classes.len());
+        self.classes.push((key, class));
+    }
+
+    /// Returns the first class with this key.
+    pub fn get(&self, key: &str) -> Option<&AnmClass> {
+        self.index_of(key).map(|i| &self.classes[i].1)
+    }
+
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut AnmClass> {
+        self.index_of(key).map(|i| &mut self.classes[i].1)
+    }
+
+    pub fn get_index(&self, index: usize) -> Option<(&str, &AnmClass)> {
+        self.classes.get(index).map(|(k, c)| (k.as_str(), c))
+    }
+
+    pub fn get_index_mut(&mut self, index: usize) -> Option<(&str, &mut AnmClass)> {
+        self.classes.get_mut(index).map(|(k, c)| (k.as_str(), c))
+    }
+
+    /// The index of the first class with this key.
+    pub fn index_of(&self, key: &str) -> Option<usize> {
+        self.index.get(key)
+    }
+
+    /// Removes a class, shifting the following classes back.
+    pub fn remove(&mut self, key: &str) -> Option<AnmClass> {
+        let index = self.index_of(key)?;
+        self.remove_index(index).map(|(_, class)| class)
+    }
+
+    /// Removes the class at an index, shifting the following classes back.
+    pub fn remove_index(&mut self, index: usize) -> Option<(String, AnmClass)> {
+        if index >= self.classes.len() {
+            return None;
+        }
+        let entry = self.classes.remove(index);
+        self.reindex();
+        Some(entry)
+    }
+
+    /// Moves the class at `from` to `to`, shifting the classes in between.
+    ///
+    /// Panics if either index is out of bounds.
+    pub fn move_index(&mut self, from: usize, to: usize) {
+        let entry = self.classes.remove(from);
+        self.classes.insert(to, entry);
+        self.reindex();
+    }
+
+    /// Panics if either index is out of bounds.
+    pub fn swap_indices(&mut self, a: usize, b: usize) {
+        self.classes.swap(a, b);
+        self.reindex();
+    }
+
+    /// Stable sort of the classes.
+    pub fn sort_by<F>(&mut self, mut compare: F)
+    where
+        F: FnMut(&str, &AnmClass, &str, &AnmClass) -> Ordering,
+    {
+        self.classes
+            .sort_by(|(k1, c1), (k2, c2)| compare(k1, c1, k2, c2));
+        self.reindex();
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.classes.iter().map(|(k, _)| k.as_str())
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &AnmClass)> {
+        self.classes.iter().map(|(k, c)| (k.as_str(), c))
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (&str, &mut AnmClass)> {
+        self.classes.iter_mut().map(|(k, c)| (k.as_str(), c))
+    }
+
+    fn reindex(&mut self) {
+        self.index
+            .rebuild(self.classes.iter().map(|(k, _)| k.as_str()));
+    }
+}
+
+impl Default for ClassCollection {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl IntoIterator for ClassCollection {
+    type Item = (String, AnmClass);
+    type IntoIter = std::vec::IntoIter<(String, AnmClass)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.classes.into_iter()
+    }
+}
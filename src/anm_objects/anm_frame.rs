@@ -1,13 +1,21 @@
-use super::{AnmBone, AnmReadingError, AnmWritingError};
+use super::{
+    AnmBone, AnmReadingError, AnmWritingError, BoneBudget, BoneEncoding, ReadLimit, ReadLimits,
+};
 use byteorder::{LittleEndian as LE, ReadBytesExt, WriteBytesExt};
-use std::io::{Read, Write};
+use std::{
+    collections::HashMap,
+    io::{Read, Write},
+};
 
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct AnmFrame {
     pub id: i16,
     pub bones: Vec<AnmBone>,
     /// affects gameplay! used by grab moves like caspian gauntlets ssig
+    #[cfg_attr(feature = "serde", serde(with = "super::serde_impls::point"))]
     pub fire_socket: Option<(f64, f64)>,
     /// unused by the game
+    #[cfg_attr(feature = "serde", serde(with = "super::serde_impls::point"))]
     pub eb_platform_pos: Option<(f64, f64)>,
 }
 
@@ -15,6 +23,8 @@ impl AnmFrame {
     pub(super) fn read<R: Read>(
         mut reader: R,
         prev_frame: Option<&Self>,
+        limits: &ReadLimits,
+        bone_budget: &BoneBudget,
     ) -> Result<Self, AnmReadingError> {
         let id = reader.read_i16::<LE>()?;
 
@@ -41,41 +51,53 @@ impl AnmFrame {
             return Err(AnmReadingError::NegativeBoneCountError { bone_count });
         }
         let bone_count = bone_count as usize;
+        limits.check(ReadLimit::BoneCount, bone_count)?;
+        bone_budget.spend(bone_count, limits)?;
         let mut bones: Vec<AnmBone> = Vec::with_capacity(bone_count);
         for i in 0..bone_count {
-            let clone_prev = reader.read_u8()? != 0;
-            if clone_prev {
-                if let Some(prev_frame) = prev_frame {
-                    if i >= prev_frame.bones.len() {
-                        return Err(AnmReadingError::NoPrevFrameBoneError());
-                    }
-                    let prev_bone = &prev_frame.bones[i];
-                    let clone_frame = reader.read_u8()? != 0;
-                    let bone = if !clone_frame {
-                        let new_frame = reader.read_i8()?;
-                        AnmBone {
-                            frame: new_frame,
-                            ..prev_bone.clone()
-                        }
-                    } else {
-                        prev_bone.clone()
-                    };
-                    bones.push(bone);
-                } else {
-                    return Err(AnmReadingError::NoPrevFrameError());
-                }
-            } else {
-                let prev_bone = bones.last();
-                bones.push(AnmBone::read(&mut reader, prev_bone)?);
-            }
+            let bone = Self::read_bone(&mut reader, i, prev_frame, bones.last())
+                .map_err(|e| e.in_bone(i))?;
+            bones.push(bone);
         }
 
-        return Ok(Self {
+        Ok(Self {
             id,
             bones,
             fire_socket,
             eb_platform_pos,
-        });
+        })
+    }
+
+    fn read_bone<R: Read>(
+        mut reader: R,
+        index: usize,
+        prev_frame: Option<&Self>,
+        prev_bone: Option<&AnmBone>,
+    ) -> Result<AnmBone, AnmReadingError> {
+        let clone_prev = reader.read_u8()? != 0;
+        if !clone_prev {
+            return AnmBone::read(&mut reader, prev_bone);
+        }
+
+        let Some(prev_frame) = prev_frame else {
+            return Err(AnmReadingError::NoPrevFrameError());
+        };
+        let Some(prev_frame_bone) = prev_frame.bones.get(index) else {
+            return Err(AnmReadingError::NoPrevFrameBoneError());
+        };
+        let clone_frame = reader.read_u8()? != 0;
+        let frame = if !clone_frame {
+            reader.read_i8()?
+        } else {
+            prev_frame_bone.frame
+        };
+        Ok(AnmBone {
+            frame,
+            encoding: Some(BoneEncoding::ClonePrevFrame {
+                keep_frame: clone_frame,
+            }),
+            ..prev_frame_bone.clone()
+        })
     }
 
     pub(super) fn write<W: Write>(
@@ -110,27 +132,25 @@ impl AnmFrame {
         writer.write_i16::<LE>(bone_count)?;
 
         for (i, bone) in self.bones.iter().enumerate() {
-            let prev_bone = prev_frame.and_then(|f| f.bones.get(i));
-
-            let cloned_prev_bone = prev_bone.and_then(|b| {
-                if bone.is_partial_clone_of(b) {
-                    Some(b)
-                } else {
-                    None
-                }
-            });
+            let prev_frame_bone = prev_frame.and_then(|f| f.bones.get(i));
+            // AnmBone::read copies from the previous bone in the same frame
+            let prev_bone = i.checked_sub(1).map(|j| &self.bones[j]);
 
-            if let Some(cloned_prev_bone) = cloned_prev_bone {
-                writer.write_u8(1)?;
-                if bone.frame == cloned_prev_bone.frame {
+            match bone.resolve_encoding(prev_frame_bone, prev_bone) {
+                BoneEncoding::ClonePrevFrame { keep_frame } => {
                     writer.write_u8(1)?;
-                } else {
+                    if keep_frame {
+                        writer.write_u8(1)?;
+                    } else {
+                        writer.write_u8(0)?;
+                        writer.write_i8(bone.frame)?;
+                    }
+                }
+                BoneEncoding::Stored(encoding) => {
                     writer.write_u8(0)?;
-                    writer.write_i8(bone.frame)?;
+                    bone.write(&mut writer, encoding)
+                        .map_err(|e| e.in_bone(i))?;
                 }
-            } else {
-                writer.write_u8(0)?;
-                bone.write(&mut writer, prev_bone)?;
             }
         }
 
@@ -152,44 +172,46 @@ impl AnmFrame {
 
         result += size_of::<i16>(); // bone count
         for (i, bone) in self.bones.iter().enumerate() {
-            let prev_bone = prev_frame.and_then(|f| f.bones.get(i));
+            let prev_frame_bone = prev_frame.and_then(|f| f.bones.get(i));
+            // AnmBone::read copies from the previous bone in the same frame
+            let prev_bone = i.checked_sub(1).map(|j| &self.bones[j]);
 
             result += size_of::<u8>(); // prev frame clone indicator
-
-            enum BoneCloneLevel {
-                None,
-                Partial,
-                Full,
-            }
-            let bone_clone_level = match prev_bone {
-                Some(prev_bone) => {
-                    if bone.is_partial_clone_of(&prev_bone) {
-                        if bone.frame == prev_bone.frame {
-                            BoneCloneLevel::Full
-                        } else {
-                            BoneCloneLevel::Partial
-                        }
-                    } else {
-                        BoneCloneLevel::None
-                    }
-                }
-                None => BoneCloneLevel::None,
-            };
-
-            match bone_clone_level {
-                BoneCloneLevel::Full => {
+            match bone.resolve_encoding(prev_frame_bone, prev_bone) {
+                BoneEncoding::ClonePrevFrame { keep_frame } => {
                     result += size_of::<u8>(); // full copy indicator
+                    if !keep_frame {
+                        result += size_of::<i8>(); // frame override
+                    }
                 }
-                BoneCloneLevel::Partial => {
-                    result += size_of::<u8>(); // full copy indicator
-                    result += size_of::<i8>(); // frame override
-                }
-                BoneCloneLevel::None => {
-                    result += bone.get_byte_size(prev_bone);
+                BoneEncoding::Stored(encoding) => {
+                    result += encoding.byte_size();
                 }
             }
         }
 
-        return result;
+        result
+    }
+
+    /// For each bone, its id and how many bones with the same id come before it on the frame.
+    /// Exporters use this to follow a bone from frame to frame when an id is drawn more than once.
+    pub(crate) fn bone_copies(&self) -> Vec<(i16, usize)> {
+        let mut copies: HashMap<i16, usize> = HashMap::new();
+        self.bones
+            .iter()
+            .map(|bone| {
+                let copy = copies.entry(bone.id).or_default();
+                *copy += 1;
+                (bone.id, *copy - 1)
+            })
+            .collect()
+    }
+
+    /// What exporters call a copy from `bone_copies`: the first copy is `name`, then `name#2`, `name#3`...
+    pub(crate) fn copy_name(name: String, copy: usize) -> String {
+        match copy {
+            0 => name,
+            copy => format!("{name}#{}", copy + 1),
+        }
     }
 }
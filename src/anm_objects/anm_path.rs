@@ -0,0 +1,35 @@
+use std::fmt;
+
+/// The location of something inside an anm file.
+/// Levels that don't apply, or that were not reached, are `None`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AnmPath {
+    pub class_key: Option<String>,
+    pub animation: Option<String>,
+    pub frame: Option<usize>,
+    pub bone: Option<usize>,
+}
+
+impl fmt::Display for AnmPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts = Vec::new();
+        if let Some(class_key) = &self.class_key {
+            parts.push(format!("class {class_key:?}"));
+        }
+        if let Some(animation) = &self.animation {
+            parts.push(format!("animation {animation:?}"));
+        }
+        if let Some(frame) = self.frame {
+            parts.push(format!("frame {frame}"));
+        }
+        if let Some(bone) = self.bone {
+            parts.push(format!("bone {bone}"));
+        }
+
+        if parts.is_empty() {
+            write!(f, "file")
+        } else {
+            write!(f, "{}", parts.join(" > "))
+        }
+    }
+}
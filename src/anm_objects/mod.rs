@@ -4,7 +4,7 @@ use thiserror::Error;
 #[derive(Error, Debug)]
 pub enum AnmReadingError {
     #[error(transparent)]
-    IOError(#[from] std::io::Error),
+    IOError(std::io::Error),
     #[error(transparent)]
     FromUtf8Error(#[from] FromUtf8Error),
     #[error("A bone tries to copy transform from a previous bone, but there is no previous bone")]
@@ -21,6 +21,91 @@ pub enum AnmReadingError {
     NoPrevFrameBoneError(),
     #[error("A frame has a negative number of bones: ({bone_count:?})")]
     NegativeBoneCountError { bone_count: i16 },
+    #[error("Animation frames take up {used:?} bytes, but the stored size is ({byte_count:?})")]
+    FramesByteSizeMismatchError { byte_count: usize, used: usize },
+    #[error("Class key appears more than once: ({key:?})")]
+    DuplicateClassKeyError { key: String },
+    #[error("Animation name appears more than once in class {class_key:?}: ({name:?})")]
+    DuplicateAnimationNameError { class_key: String, name: String },
+    #[error("{limit} exceeds the limit of {max:?}: ({value:?})")]
+    LimitExceededError {
+        limit: ReadLimit,
+        value: u64,
+        max: u64,
+    },
+    #[error("{source} (at byte {offset} of the decompressed data, in {path})")]
+    ContextError {
+        /// Offset into the decompressed data.
+        offset: u64,
+        path: AnmPath,
+        source: Box<AnmReadingError>,
+    },
+}
+
+impl From<std::io::Error> for AnmReadingError {
+    fn from(error: std::io::Error) -> Self {
+        // errors raised by our own readers, like PositionReader, travel inside io errors
+        if error.get_ref().is_some_and(|e| e.is::<AnmReadingError>()) {
+            let inner = error.into_inner().unwrap();
+            return *inner.downcast::<AnmReadingError>().unwrap();
+        }
+        Self::IOError(error)
+    }
+}
+
+impl AnmReadingError {
+    fn map_context(self, f: impl FnOnce(&mut u64, &mut AnmPath)) -> Self {
+        let (mut offset, mut path, source) = match self {
+            Self::ContextError {
+                offset,
+                path,
+                source,
+            } => (offset, path, source),
+            error => (0, AnmPath::default(), Box::new(error)),
+        };
+        f(&mut offset, &mut path);
+        Self::ContextError {
+            offset,
+            path,
+            source,
+        }
+    }
+
+    pub(crate) fn at_offset(self, offset: u64) -> Self {
+        self.map_context(|o, _| *o = offset)
+    }
+
+    pub(crate) fn in_class(self, key: &str) -> Self {
+        self.map_context(|_, path| path.class_key = Some(key.to_owned()))
+    }
+
+    pub(crate) fn in_animation(self, name: &str) -> Self {
+        self.map_context(|_, path| path.animation = Some(name.to_owned()))
+    }
+
+    pub(crate) fn in_frame(self, index: usize) -> Self {
+        self.map_context(|_, path| path.frame = Some(index))
+    }
+
+    pub(crate) fn in_bone(self, index: usize) -> Self {
+        self.map_context(|_, path| path.bone = Some(index))
+    }
+
+    /// The error without its context.
+    pub fn root_cause(&self) -> &Self {
+        match self {
+            Self::ContextError { source, .. } => source.root_cause(),
+            error => error,
+        }
+    }
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum AnmReadingWarning {
+    #[error("Class key appears more than once: ({key:?})")]
+    DuplicateClassKey { key: String },
+    #[error("Animation name appears more than once in class {class_key:?}: ({name:?})")]
+    DuplicateAnimationName { class_key: String, name: String },
 }
 
 #[derive(Error, Debug)]
@@ -45,15 +130,66 @@ pub enum AnmWritingError {
     TooManyAnimationsError { animation_count: usize },
     #[error("Class key length exceeds u16 max: ({key_length:?})")]
     TooLongClassKey { key_length: usize },
+    #[error("{source} (in {path})")]
+    ContextError {
+        path: AnmPath,
+        source: Box<AnmWritingError>,
+    },
+}
+
+impl AnmWritingError {
+    fn map_path(self, f: impl FnOnce(&mut AnmPath)) -> Self {
+        let (mut path, source) = match self {
+            Self::ContextError { path, source } => (path, source),
+            error => (AnmPath::default(), Box::new(error)),
+        };
+        f(&mut path);
+        Self::ContextError { path, source }
+    }
+
+    pub(crate) fn in_class(self, key: &str) -> Self {
+        self.map_path(|path| path.class_key = Some(key.to_owned()))
+    }
+
+    pub(crate) fn in_animation(self, name: &str) -> Self {
+        self.map_path(|path| path.animation = Some(name.to_owned()))
+    }
+
+    pub(crate) fn in_frame(self, index: usize) -> Self {
+        self.map_path(|path| path.frame = Some(index))
+    }
+
+    pub(crate) fn in_bone(self, index: usize) -> Self {
+        self.map_path(|path| path.bone = Some(index))
+    }
+
+    /// The error without its context.
+    pub fn root_cause(&self) -> &Self {
+        match self {
+            Self::ContextError { source, .. } => source.root_cause(),
+            error => error,
+        }
+    }
 }
 
+mod anm_path;
+#[cfg(feature = "serde")]
+mod serde_impls;
+pub use anm_path::AnmPath;
+mod name_index;
+use name_index::NameIndex;
+mod position_reader;
+use position_reader::PositionReader;
+mod read_options;
+use read_options::BoneBudget;
+pub use read_options::{DuplicateMode, ReadLimit, ReadLimits, ReadOptions};
 mod anm_bone;
-pub use anm_bone::AnmBone;
+pub use anm_bone::{AnmBone, BoneEncoding, StoredBoneEncoding, TransformEncoding};
 mod anm_frame;
 pub use anm_frame::AnmFrame;
 mod anm_animation;
 pub use anm_animation::AnmAnimation;
 mod anm_class;
-pub use anm_class::AnmClass;
+pub use anm_class::{AnimationCollection, AnmClass};
 mod anm_file;
-pub use anm_file::AnmFile;
+pub use anm_file::{AnmFile, ClassCollection};
@@ -0,0 +1,34 @@
+use std::collections::HashMap;
+
+/// The position of the first entry with each name, so collections find entries without a scan.
+#[derive(Default)]
+pub(super) struct NameIndex {
+    first: HashMap<String, usize>,
+}
+
+impl NameIndex {
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            first: HashMap::with_capacity(capacity),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<usize> {
+        self.first.get(name).copied()
+    }
+
+    /// Records an entry appended at `index`.
+    pub fn push(&mut self, name: &str, index: usize) {
+        if !self.first.contains_key(name) {
+            self.first.insert(name.to_owned(), index);
+        }
+    }
+
+    /// Indexes the names from scratch, after entries were removed or moved around.
+    pub fn rebuild<'a>(&mut self, names: impl IntoIterator<Item = &'a str>) {
+        self.first.clear();
+        for (index, name) in names.into_iter().enumerate() {
+            self.push(name, index);
+        }
+    }
+}
@@ -0,0 +1,43 @@
+use super::{AnmReadingError, ReadLimit};
+use std::io::{Error, Read, Result};
+
+/// Counts the bytes read through it, so errors can say where they happened,
+/// and fails once more than `limit` bytes are read.
+pub(super) struct PositionReader<R> {
+    inner: R,
+    position: u64,
+    limit: u64,
+}
+
+impl<R: Read> PositionReader<R> {
+    pub(super) fn new(inner: R, limit: u64) -> Self {
+        Self {
+            inner,
+            position: 0,
+            limit,
+        }
+    }
+
+    pub(super) fn position(&self) -> u64 {
+        self.position
+    }
+
+    pub(super) fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: Read> Read for PositionReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.position += read as u64;
+        if self.position > self.limit {
+            return Err(Error::other(AnmReadingError::LimitExceededError {
+                limit: ReadLimit::DecompressedSize,
+                value: self.position,
+                max: self.limit,
+            }));
+        }
+        Ok(read)
+    }
+}
@@ -0,0 +1,144 @@
+use super::AnmReadingError;
+use std::{
+    fmt,
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+};
+
+/// How to handle class keys and animation names that appear more than once.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DuplicateMode {
+    /// Fail on the first duplicate.
+    Strict,
+    /// Keep every entry, and report each duplicate as a warning.
+    #[default]
+    Lenient,
+}
+
+/// Options for `AnmFile::read_with_options`.
+#[derive(Clone, Debug, Default)]
+pub struct ReadOptions {
+    pub duplicates: DuplicateMode,
+    /// Keep the frames of each animation undecoded until they are first accessed.
+    /// Animations whose frames are never modified are written back verbatim.
+    pub lazy_frames: bool,
+    pub limits: ReadLimits,
+}
+
+/// Caps on what a file may contain, so that a malicious or broken file
+/// can't make the reader allocate huge amounts of memory.
+/// The defaults are far above anything found in the game's files.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ReadLimits {
+    /// Total size of the decompressed data, in bytes.
+    pub max_decompressed_size: u64,
+    /// Length of class keys, indices, file names and animation names, in bytes.
+    pub max_name_length: u64,
+    /// Animations in a single class.
+    pub max_animations: u64,
+    /// Frames in a single animation.
+    pub max_frames: u64,
+    /// Bones in a single frame.
+    pub max_bones: u64,
+    /// Entries in the data array of a single animation.
+    pub max_data_length: u64,
+    /// Bones in all the frames of a file, including frames read lazily and decoded later.
+    /// Bones cloned from the previous frame take two bytes each, so this bounds
+    /// what a small file can grow into in memory.
+    pub max_total_bones: u64,
+}
+
+impl ReadLimits {
+    pub const UNLIMITED: Self = Self {
+        max_decompressed_size: u64::MAX,
+        max_name_length: u64::MAX,
+        max_animations: u64::MAX,
+        max_frames: u64::MAX,
+        max_bones: u64::MAX,
+        max_data_length: u64::MAX,
+        max_total_bones: u64::MAX,
+    };
+
+    pub fn max(&self, limit: ReadLimit) -> u64 {
+        match limit {
+            ReadLimit::DecompressedSize => self.max_decompressed_size,
+            ReadLimit::NameLength => self.max_name_length,
+            ReadLimit::AnimationCount => self.max_animations,
+            ReadLimit::FrameCount => self.max_frames,
+            ReadLimit::BoneCount => self.max_bones,
+            ReadLimit::DataLength => self.max_data_length,
+            ReadLimit::TotalBoneCount => self.max_total_bones,
+        }
+    }
+
+    pub(super) fn check(&self, limit: ReadLimit, value: usize) -> Result<(), AnmReadingError> {
+        let value = value as u64;
+        let max = self.max(limit);
+        if value > max {
+            return Err(AnmReadingError::LimitExceededError { limit, value, max });
+        }
+        Ok(())
+    }
+}
+
+impl Default for ReadLimits {
+    fn default() -> Self {
+        Self {
+            max_decompressed_size: 1 << 30,
+            max_name_length: 1 << 12,
+            max_animations: 1 << 16,
+            max_frames: 1 << 16,
+            max_bones: 1 << 12,
+            max_data_length: 1 << 16,
+            max_total_bones: 1 << 23,
+        }
+    }
+}
+
+/// The bones decoded so far from one file, shared with its lazily read animations.
+#[derive(Clone, Default)]
+pub(super) struct BoneBudget(Arc<AtomicU64>);
+
+impl BoneBudget {
+    pub fn spend(&self, bone_count: usize, limits: &ReadLimits) -> Result<(), AnmReadingError> {
+        let bone_count = bone_count as u64;
+        let total = self.0.fetch_add(bone_count, Ordering::Relaxed) + bone_count;
+        let max = limits.max_total_bones;
+        if total > max {
+            return Err(AnmReadingError::LimitExceededError {
+                limit: ReadLimit::TotalBoneCount,
+                value: total,
+                max,
+            });
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReadLimit {
+    DecompressedSize,
+    NameLength,
+    AnimationCount,
+    FrameCount,
+    BoneCount,
+    DataLength,
+    TotalBoneCount,
+}
+
+impl fmt::Display for ReadLimit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::DecompressedSize => "Decompressed size",
+            Self::NameLength => "Name length",
+            Self::AnimationCount => "Animation count",
+            Self::FrameCount => "Frame count",
+            Self::BoneCount => "Bone count",
+            Self::DataLength => "Data array length",
+            Self::TotalBoneCount => "Total bone count",
+        };
+        write!(f, "{name}")
+    }
+}
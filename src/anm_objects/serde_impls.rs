@@ -0,0 +1,100 @@
+//! Serde impls that can't be derived.
+
+use super::{AnimationCollection, AnmAnimation, AnmClass, ClassCollection};
+use serde::{
+    Deserialize, Deserializer, Serialize, Serializer,
+    ser::{Error, SerializeStruct},
+};
+
+/// Stores `(x, y)` tuples as `{ "x": .., "y": .. }`.
+pub(super) mod point {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    #[derive(Serialize, Deserialize)]
+    struct Point {
+        x: f64,
+        y: f64,
+    }
+
+    pub fn serialize<S: Serializer>(
+        value: &Option<(f64, f64)>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        value.map(|(x, y)| Point { x, y }).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<(f64, f64)>, D::Error> {
+        Ok(Option::<Point>::deserialize(deserializer)?.map(|p| (p.x, p.y)))
+    }
+}
+
+// frames read lazily are decoded when serialized
+impl Serialize for AnmAnimation {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let frames = self.frames().map_err(S::Error::custom)?;
+        let mut state = serializer.serialize_struct("AnmAnimation", 8)?;
+        state.serialize_field("name", &self.name)?;
+        state.serialize_field("loop_start", &self.loop_start)?;
+        state.serialize_field("recovery_start", &self.recovery_start)?;
+        state.serialize_field("free_start", &self.free_start)?;
+        state.serialize_field("preview_frame", &self.preview_frame)?;
+        state.serialize_field("base_start", &self.base_start)?;
+        state.serialize_field("data", &self.data)?;
+        state.serialize_field("frames", frames)?;
+        state.end()
+    }
+}
+
+/// Animations are stored as a sequence, in file order.
+impl Serialize for AnimationCollection {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.iter())
+    }
+}
+
+impl<'de> Deserialize<'de> for AnimationCollection {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let animations = Vec::<AnmAnimation>::deserialize(deserializer)?;
+        let mut collection = AnimationCollection::with_capacity(animations.len());
+        for animation in animations {
+            collection.push(animation);
+        }
+        Ok(collection)
+    }
+}
+
+/// A class with its key, as stored in the sequence of classes.
+#[derive(Serialize)]
+struct ClassEntryRef<'a> {
+    key: &'a str,
+    #[serde(flatten)]
+    class: &'a AnmClass,
+}
+
+#[derive(Deserialize)]
+struct ClassEntry {
+    key: String,
+    #[serde(flatten)]
+    class: AnmClass,
+}
+
+/// Classes are stored as a sequence in file order, each with a `key` field,
+/// so duplicate keys survive a round trip.
+impl Serialize for ClassCollection {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.iter().map(|(key, class)| ClassEntryRef { key, class }))
+    }
+}
+
+impl<'de> Deserialize<'de> for ClassCollection {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let entries = Vec::<ClassEntry>::deserialize(deserializer)?;
+        let mut classes = ClassCollection::with_capacity(entries.len());
+        for entry in entries {
+            classes.push(entry.key, entry.class);
+        }
+        Ok(classes)
+    }
+}
@@ -0,0 +1,381 @@
+//! Command-line tool for inspecting anm files.
+
+use bhanm::{
+    AnmAnimation, AnmFile, ReadOptions,
+    diff::diff as diff_files,
+    frame_data as fd,
+    godot::{self, GodotOptions},
+    patch_notes::PatchNotes,
+    spine::{self, SpineOptions},
+    svg::{self, SvgOptions, render_frame},
+    text,
+};
+use std::{
+    env,
+    error::Error,
+    fs::File,
+    io::{BufReader, BufWriter, Write},
+    path::{Component, Path},
+    process::ExitCode,
+};
+
+const USAGE: &str = "\
+Usage:
+  bhanm list <file.anm>
+      List classes and animations, with frame counts and timing fields.
+  bhanm dump <file.anm> <class key> <animation name>
+      Print the frames and bones of an animation.
+  bhanm stats <file.anm>...
+      Print summary statistics.
+  bhanm unpack <file.anm> <dir>
+      Unpack into a directory with a text file per animation.
+  bhanm pack <dir> <file.anm>
+      Pack a directory made by unpack back into an anm file.
+  bhanm text <file.anm>
+      Print the whole file as text, for reviews or as a git textconv driver.
+  bhanm from-text <file.txt> <file.anm>
+      Write an anm file from text printed by the text command.
+  bhanm diff <old.anm> <new.anm>
+      Print what changed between two files.
+  bhanm patch-notes <old.anm> <new.anm> [--html]
+      Print the gameplay changes between two files as Markdown, or HTML.
+  bhanm frame-data <file.anm> [--json]
+      Print the timing of every animation as CSV, or JSON.
+  bhanm svg <file.anm> <class key> <animation name> <frame>
+      Print a wireframe SVG of a frame.
+  bhanm svg-anim <file.anm> <class key> <animation name> [--html]
+      Print a looping wireframe SVG of an animation, or an HTML page animated with CSS.
+  bhanm godot <file.anm> <class key> <animation name> <dir>
+      Write an animation as a Godot Animation resource and scene, named after it.
+  bhanm spine <file.anm> <class key>
+      Print the animations of a class as Spine skeleton JSON.
+  bhanm spine-import <in.anm> <class key> <skeleton.json> <out.anm>
+      Add the animations of a Spine skeleton to a class, keeping the timing of replaced ones.
+      Needs the spine-import feature.
+";
+
+type CommandResult = Result<(), Box<dyn Error>>;
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let Some((command, args)) = args.split_first() else {
+        eprint!("{USAGE}");
+        return ExitCode::FAILURE;
+    };
+
+    let result = match (command.as_str(), args) {
+        ("list", [path]) => list(path),
+        ("dump", [path, class_key, name]) => dump(path, class_key, name),
+        ("stats", paths) if !paths.is_empty() => stats(paths),
+        ("unpack", [path, dir]) => unpack(path, dir),
+        ("pack", [dir, path]) => pack(dir, path),
+        ("text", [path]) => print_text(path),
+        ("from-text", [text, path]) => from_text(text, path),
+        ("diff", [old, new]) => diff(old, new),
+        ("patch-notes", [old, new]) => patch_notes(old, new, false),
+        ("patch-notes", [old, new, flag]) if flag == "--html" => patch_notes(old, new, true),
+        ("frame-data", [path]) => frame_data(path, false),
+        ("frame-data", [path, flag]) if flag == "--json" => frame_data(path, true),
+        ("svg", [path, class_key, name, frame]) => svg(path, class_key, name, frame),
+        ("svg-anim", [path, class_key, name]) => svg_animation(path, class_key, name, false),
+        ("svg-anim", [path, class_key, name, flag]) if flag == "--html" => {
+            svg_animation(path, class_key, name, true)
+        }
+        ("godot", [path, class_key, name, dir]) => godot_export(path, class_key, name, dir),
+        ("spine", [path, class_key]) => spine_json(path, class_key),
+        #[cfg(feature = "spine-import")]
+        ("spine-import", [path, class_key, json, out]) => spine_import(path, class_key, json, out),
+        #[cfg(not(feature = "spine-import"))]
+        ("spine-import", [_, _, _, _]) => Err("built without the spine-import feature".into()),
+        ("help" | "-h" | "--help", _) => {
+            print!("{USAGE}");
+            Ok(())
+        }
+        _ => {
+            eprint!("{USAGE}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("error: {error}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Frames are only decoded when accessed, so commands that don't need them stay fast.
+fn read_lazy(path: impl AsRef<Path>) -> Result<AnmFile, Box<dyn Error>> {
+    let reader = BufReader::new(File::open(path)?);
+    let options = ReadOptions {
+        lazy_frames: true,
+        ..Default::default()
+    };
+    let (file, warnings) = AnmFile::read_with_options(reader, &options)?;
+    for warning in warnings {
+        eprintln!("warning: {warning}");
+    }
+    Ok(file)
+}
+
+/// Flushes before returning, so errors writing the end of the file are not lost.
+fn write_file(file: &AnmFile, path: impl AsRef<Path>) -> CommandResult {
+    let mut writer = BufWriter::new(File::create(path)?);
+    file.write(&mut writer)?;
+    writer.flush()?;
+    Ok(())
+}
+
+fn list(path: &str) -> CommandResult {
+    let file = read_lazy(path)?;
+    for (key, class) in file.classes.iter() {
+        println!(
+            "{key} (index {:?}, file {:?}, {} animations)",
+            class.index,
+            class.file_name,
+            class.animations.len()
+        );
+        for animation in class.animations.iter() {
+            println!("  {}", animation_summary(animation));
+        }
+    }
+    Ok(())
+}
+
+fn animation_summary(animation: &AnmAnimation) -> String {
+    format!(
+        "{}: frames {}, loop_start {}, recovery_start {}, free_start {}, base_start {}, preview_frame {}",
+        animation.name,
+        animation.frame_count(),
+        animation.loop_start,
+        animation.recovery_start,
+        animation.free_start,
+        animation.base_start,
+        animation.preview_frame
+    )
+}
+
+fn find_animation<'a>(
+    file: &'a AnmFile,
+    class_key: &str,
+    name: &str,
+) -> Result<&'a AnmAnimation, Box<dyn Error>> {
+    let class = file
+        .classes
+        .get(class_key)
+        .ok_or_else(|| format!("no class {class_key:?}"))?;
+    let animation = class
+        .animations
+        .get(name)
+        .ok_or_else(|| format!("no animation {name:?} in class {class_key:?}"))?;
+    Ok(animation)
+}
+
+fn dump(path: &str, class_key: &str, name: &str) -> CommandResult {
+    let file = read_lazy(path)?;
+    let animation = find_animation(&file, class_key, name)?;
+
+    println!("{}", animation_summary(animation));
+    println!("data: {:?}", animation.data);
+    for (i, frame) in animation.frames()?.iter().enumerate() {
+        println!("frame {i} (id {}, {} bones)", frame.id, frame.bones.len());
+        if let Some((x, y)) = frame.fire_socket {
+            println!("  fire_socket: ({x}, {y})");
+        }
+        if let Some((x, y)) = frame.eb_platform_pos {
+            println!("  eb_platform_pos: ({x}, {y})");
+        }
+        for (j, bone) in frame.bones.iter().enumerate() {
+            println!(
+                "  bone {j}: id {}, matrix [{}, {}, {}, {}], position ({}, {}), opacity {}, frame {}",
+                bone.id,
+                bone.scale_x,
+                bone.rotate_skew0,
+                bone.rotate_skew1,
+                bone.scale_y,
+                bone.x,
+                bone.y,
+                bone.opacity,
+                bone.frame
+            );
+        }
+    }
+    Ok(())
+}
+
+fn stats(paths: &[String]) -> CommandResult {
+    let mut classes = 0usize;
+    let mut animations = 0usize;
+    let mut frames = 0usize;
+    let mut bones = 0usize;
+    let mut longest: Option<(usize, String)> = None;
+
+    for path in paths {
+        let file = read_lazy(path)?;
+        classes += file.classes.len();
+        for (key, class) in file.classes.iter() {
+            animations += class.animations.len();
+            for animation in class.animations.iter() {
+                let frame_count = animation.frame_count();
+                frames += frame_count;
+                bones += animation
+                    .frames()?
+                    .iter()
+                    .map(|f| f.bones.len())
+                    .sum::<usize>();
+                if longest
+                    .as_ref()
+                    .is_none_or(|(count, _)| frame_count > *count)
+                {
+                    longest = Some((frame_count, format!("{key}/{}", animation.name)));
+                }
+            }
+        }
+    }
+
+    println!("files: {}", paths.len());
+    println!("classes: {classes}");
+    println!("animations: {animations}");
+    println!("frames: {frames}");
+    println!("bones: {bones}");
+    if animations > 0 {
+        println!(
+            "average frames per animation: {:.2}",
+            frames as f64 / animations as f64
+        );
+    }
+    if frames > 0 {
+        println!(
+            "average bones per frame: {:.2}",
+            bones as f64 / frames as f64
+        );
+    }
+    if let Some((count, name)) = longest {
+        println!("longest animation: {name} ({count} frames)");
+    }
+    Ok(())
+}
+
+fn unpack(path: &str, dir: &str) -> CommandResult {
+    read_lazy(path)?.unpack_to_dir(dir)?;
+    Ok(())
+}
+
+fn pack(dir: &str, path: &str) -> CommandResult {
+    let file = AnmFile::pack_from_dir(dir)?;
+    write_file(&file, path)
+}
+
+fn print_text(path: &str) -> CommandResult {
+    print!("{}", text::print_file(&read_lazy(path)?)?);
+    Ok(())
+}
+
+fn from_text(text_path: &str, path: &str) -> CommandResult {
+    let file = text::parse_file(&std::fs::read_to_string(text_path)?)?;
+    write_file(&file, path)
+}
+
+fn diff(old: &str, new: &str) -> CommandResult {
+    let changes = diff_files(&read_lazy(old)?, &read_lazy(new)?)?;
+    print!("{changes}");
+    Ok(())
+}
+
+fn patch_notes(old: &str, new: &str, html: bool) -> CommandResult {
+    let changes = diff_files(&read_lazy(old)?, &read_lazy(new)?)?;
+    let notes = PatchNotes::new(&changes);
+    if html {
+        print!("{}", notes.to_html());
+    } else {
+        print!("{}", notes.to_markdown());
+    }
+    Ok(())
+}
+
+fn frame_data(path: &str, json: bool) -> CommandResult {
+    let rows = fd::frame_data(&read_lazy(path)?)?;
+    if json {
+        print!("{}", fd::to_json(&rows));
+    } else {
+        print!("{}", fd::to_csv(&rows));
+    }
+    Ok(())
+}
+
+fn svg(path: &str, class_key: &str, name: &str, frame: &str) -> CommandResult {
+    let file = read_lazy(path)?;
+    let animation = find_animation(&file, class_key, name)?;
+    let index: usize = frame.parse()?;
+    let frame = animation
+        .frames()?
+        .get(index)
+        .ok_or_else(|| format!("no frame {index} in animation {name:?}"))?;
+    print!("{}", render_frame(frame, &SvgOptions::default()));
+    Ok(())
+}
+
+fn svg_animation(path: &str, class_key: &str, name: &str, html: bool) -> CommandResult {
+    let file = read_lazy(path)?;
+    let animation = find_animation(&file, class_key, name)?;
+    let options = SvgOptions::default();
+    if html {
+        print!("{}", svg::render_animation_html(animation, &options)?);
+    } else {
+        print!("{}", svg::render_animation(animation, &options)?);
+    }
+    Ok(())
+}
+
+fn godot_export(path: &str, class_key: &str, name: &str, dir: &str) -> CommandResult {
+    let file = read_lazy(path)?;
+    let animation = find_animation(&file, class_key, name)?;
+    let mut components = Path::new(name).components();
+    if !matches!(
+        (components.next(), components.next()),
+        (Some(Component::Normal(_)), None)
+    ) {
+        return Err(format!("animation name {name:?} is not a plain file name").into());
+    }
+    let dir = Path::new(dir);
+    let tres = godot::animation_tres(animation, &GodotOptions::default())?;
+    std::fs::write(dir.join(format!("{name}.tres")), tres)?;
+    // the scene expects the resource next to it, at the root of the project
+    let scene = godot::scene_tscn(animation, &format!("res://{name}.tres"))?;
+    std::fs::write(dir.join(format!("{name}.tscn")), scene)?;
+    Ok(())
+}
+
+fn spine_json(path: &str, class_key: &str) -> CommandResult {
+    let file = read_lazy(path)?;
+    let class = file
+        .classes
+        .get(class_key)
+        .ok_or_else(|| format!("no class {class_key:?}"))?;
+    print!("{}", spine::to_json(class, &SpineOptions::default())?);
+    Ok(())
+}
+
+#[cfg(feature = "spine-import")]
+fn spine_import(path: &str, class_key: &str, json: &str, out: &str) -> CommandResult {
+    let mut file = read_lazy(path)?;
+    let class = file
+        .classes
+        .get_mut(class_key)
+        .ok_or_else(|| format!("no class {class_key:?}"))?;
+    let text = std::fs::read_to_string(json)?;
+    for mut animation in spine::from_json(&text, &spine::SpineImportOptions::default())? {
+        if let Some(old) = class.animations.get(&animation.name) {
+            animation.loop_start = old.loop_start;
+            animation.recovery_start = old.recovery_start;
+            animation.free_start = old.free_start;
+            animation.preview_frame = old.preview_frame;
+            animation.base_start = old.base_start;
+            animation.data = old.data.clone();
+        }
+        class.animations.insert(animation);
+    }
+    write_file(&file, out)
+}
@@ -0,0 +1,447 @@
+//! Structural comparison of two anm files.
+
+use crate::{AnmAnimation, AnmBone, AnmFile, AnmFrame, AnmPath, AnmReadingError};
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt,
+};
+
+/// The changes between two anm files, in file order.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AnmDiff {
+    pub changes: Vec<Change>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Change {
+    pub path: AnmPath,
+    pub kind: ChangeKind,
+    pub entry: Entry,
+}
+
+/// Which of the matched classes, and of the matched animations in it, a change is in,
+/// numbered in diff order. Tells entries apart when a key or name appears more than once.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Entry {
+    pub class: usize,
+    pub animation: Option<usize>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ChangeKind {
+    ClassAdded,
+    ClassRemoved,
+    AnimationAdded,
+    AnimationRemoved,
+    Timing {
+        field: TimingField,
+        old: u32,
+        new: u32,
+    },
+    FrameCount {
+        old: usize,
+        new: usize,
+    },
+    FrameId {
+        old: i16,
+        new: i16,
+    },
+    FireSocket {
+        old: Option<(f64, f64)>,
+        new: Option<(f64, f64)>,
+    },
+    EbPlatformPos {
+        old: Option<(f64, f64)>,
+        new: Option<(f64, f64)>,
+    },
+    BoneAdded,
+    BoneRemoved,
+    BoneId {
+        old: i16,
+        new: i16,
+    },
+    /// `[scale_x, rotate_skew0, rotate_skew1, scale_y]`
+    Transform {
+        old: [f32; 4],
+        new: [f32; 4],
+    },
+    Position {
+        old: (f32, f32),
+        new: (f32, f32),
+    },
+    Opacity {
+        old: f64,
+        new: f64,
+    },
+    SpriteFrame {
+        old: i8,
+        new: i8,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TimingField {
+    LoopStart,
+    RecoveryStart,
+    FreeStart,
+    PreviewFrame,
+    BaseStart,
+}
+
+impl ChangeKind {
+    /// Whether the change can matter in a match: added or removed classes and animations,
+    /// frame counts, the timing fields other than `preview_frame`, and `fire_socket`.
+    /// Bone changes are only visual.
+    pub fn affects_gameplay(&self) -> bool {
+        match self {
+            Self::ClassAdded
+            | Self::ClassRemoved
+            | Self::AnimationAdded
+            | Self::AnimationRemoved
+            | Self::FrameCount { .. }
+            | Self::FireSocket { .. } => true,
+            Self::Timing { field, .. } => *field != TimingField::PreviewFrame,
+            _ => false,
+        }
+    }
+}
+
+impl TimingField {
+    pub const ALL: [Self; 5] = [
+        Self::LoopStart,
+        Self::RecoveryStart,
+        Self::FreeStart,
+        Self::PreviewFrame,
+        Self::BaseStart,
+    ];
+
+    pub fn get(&self, animation: &AnmAnimation) -> u32 {
+        match self {
+            Self::LoopStart => animation.loop_start,
+            Self::RecoveryStart => animation.recovery_start,
+            Self::FreeStart => animation.free_start,
+            Self::PreviewFrame => animation.preview_frame,
+            Self::BaseStart => animation.base_start,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::LoopStart => "loop_start",
+            Self::RecoveryStart => "recovery_start",
+            Self::FreeStart => "free_start",
+            Self::PreviewFrame => "preview_frame",
+            Self::BaseStart => "base_start",
+        }
+    }
+}
+
+/// Compares two files. Classes are matched by key, animations by name, and frames and bones by index.
+/// When a key or name appears more than once, occurrences are matched in order.
+/// Frames that were read lazily are decoded.
+pub fn diff(old: &AnmFile, new: &AnmFile) -> Result<AnmDiff, AnmReadingError> {
+    let mut changes = Vec::new();
+    let old_classes: Vec<_> = old.classes.iter().collect();
+    let new_classes: Vec<_> = new.classes.iter().collect();
+
+    let class_matches = match_by_name(&old_classes, &new_classes, |(key, _)| key);
+    for (class, matched) in class_matches.into_iter().enumerate() {
+        let entry = Entry {
+            class,
+            animation: None,
+        };
+        let class_path = |(key, _): (&str, _)| AnmPath {
+            class_key: Some(key.to_owned()),
+            ..Default::default()
+        };
+        match matched {
+            Matched::Removed(old) => changes.push(Change {
+                path: class_path(*old),
+                kind: ChangeKind::ClassRemoved,
+                entry,
+            }),
+            Matched::Added(new) => changes.push(Change {
+                path: class_path(*new),
+                kind: ChangeKind::ClassAdded,
+                entry,
+            }),
+            Matched::Both((key, old), (_, new)) => {
+                let old_animations: Vec<_> = old.animations.iter().collect();
+                let new_animations: Vec<_> = new.animations.iter().collect();
+                let animation_matches =
+                    match_by_name(&old_animations, &new_animations, |a| a.name.as_str());
+                for (animation, matched) in animation_matches.into_iter().enumerate() {
+                    let entry = Entry {
+                        animation: Some(animation),
+                        ..entry
+                    };
+                    let animation_path = |animation: &AnmAnimation| AnmPath {
+                        class_key: Some(key.to_string()),
+                        animation: Some(animation.name.clone()),
+                        ..Default::default()
+                    };
+                    match matched {
+                        Matched::Removed(old) => changes.push(Change {
+                            path: animation_path(old),
+                            kind: ChangeKind::AnimationRemoved,
+                            entry,
+                        }),
+                        Matched::Added(new) => changes.push(Change {
+                            path: animation_path(new),
+                            kind: ChangeKind::AnimationAdded,
+                            entry,
+                        }),
+                        Matched::Both(old, new) => {
+                            diff_animations(old, new, animation_path(old), entry, &mut changes)
+                                .map_err(|e| e.in_class(key))?;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(AnmDiff { changes })
+}
+
+fn diff_animations(
+    old: &AnmAnimation,
+    new: &AnmAnimation,
+    path: AnmPath,
+    entry: Entry,
+    changes: &mut Vec<Change>,
+) -> Result<(), AnmReadingError> {
+    for field in TimingField::ALL {
+        let (old, new) = (field.get(old), field.get(new));
+        if old != new {
+            changes.push(Change {
+                path: path.clone(),
+                kind: ChangeKind::Timing { field, old, new },
+                entry,
+            });
+        }
+    }
+
+    if old.frame_count() != new.frame_count() {
+        changes.push(Change {
+            path: path.clone(),
+            kind: ChangeKind::FrameCount {
+                old: old.frame_count(),
+                new: new.frame_count(),
+            },
+            entry,
+        });
+    }
+
+    let (old, new) = (old.frames()?, new.frames()?);
+    for (i, (old, new)) in old.iter().zip(new).enumerate() {
+        let path = AnmPath {
+            frame: Some(i),
+            ..path.clone()
+        };
+        diff_frames(old, new, path, entry, changes);
+    }
+
+    Ok(())
+}
+
+fn diff_frames(
+    old: &AnmFrame,
+    new: &AnmFrame,
+    path: AnmPath,
+    entry: Entry,
+    changes: &mut Vec<Change>,
+) {
+    let mut push = |path: &AnmPath, kind| {
+        changes.push(Change {
+            path: path.clone(),
+            kind,
+            entry,
+        })
+    };
+
+    if old.id != new.id {
+        push(
+            &path,
+            ChangeKind::FrameId {
+                old: old.id,
+                new: new.id,
+            },
+        );
+    }
+    if !same_point(old.fire_socket, new.fire_socket) {
+        push(
+            &path,
+            ChangeKind::FireSocket {
+                old: old.fire_socket,
+                new: new.fire_socket,
+            },
+        );
+    }
+    if !same_point(old.eb_platform_pos, new.eb_platform_pos) {
+        push(
+            &path,
+            ChangeKind::EbPlatformPos {
+                old: old.eb_platform_pos,
+                new: new.eb_platform_pos,
+            },
+        );
+    }
+
+    let bone_count = old.bones.len().max(new.bones.len());
+    for i in 0..bone_count {
+        let path = AnmPath {
+            bone: Some(i),
+            ..path.clone()
+        };
+        match (old.bones.get(i), new.bones.get(i)) {
+            (Some(old), Some(new)) => {
+                for kind in diff_bones(old, new) {
+                    push(&path, kind);
+                }
+            }
+            (Some(_), None) => push(&path, ChangeKind::BoneRemoved),
+            (None, Some(_)) => push(&path, ChangeKind::BoneAdded),
+            (None, None) => unreachable!(),
+        }
+    }
+}
+
+fn diff_bones(old: &AnmBone, new: &AnmBone) -> Vec<ChangeKind> {
+    let mut kinds = Vec::new();
+    if old.id != new.id {
+        kinds.push(ChangeKind::BoneId {
+            old: old.id,
+            new: new.id,
+        });
+    }
+
+    let old_transform = [old.scale_x, old.rotate_skew0, old.rotate_skew1, old.scale_y];
+    let new_transform = [new.scale_x, new.rotate_skew0, new.rotate_skew1, new.scale_y];
+    if old_transform.map(f32::to_bits) != new_transform.map(f32::to_bits) {
+        kinds.push(ChangeKind::Transform {
+            old: old_transform,
+            new: new_transform,
+        });
+    }
+
+    if (old.x.to_bits(), old.y.to_bits()) != (new.x.to_bits(), new.y.to_bits()) {
+        kinds.push(ChangeKind::Position {
+            old: (old.x, old.y),
+            new: (new.x, new.y),
+        });
+    }
+
+    if old.opacity.to_bits() != new.opacity.to_bits() {
+        kinds.push(ChangeKind::Opacity {
+            old: old.opacity,
+            new: new.opacity,
+        });
+    }
+
+    if old.frame != new.frame {
+        kinds.push(ChangeKind::SpriteFrame {
+            old: old.frame,
+            new: new.frame,
+        });
+    }
+
+    kinds
+}
+
+fn same_point(a: Option<(f64, f64)>, b: Option<(f64, f64)>) -> bool {
+    a.map(|(x, y)| (x.to_bits(), y.to_bits())) == b.map(|(x, y)| (x.to_bits(), y.to_bits()))
+}
+
+enum Matched<'a, T> {
+    Both(&'a T, &'a T),
+    Removed(&'a T),
+    Added(&'a T),
+}
+
+/// Pairs up entries with the same name, in order.
+/// Removed entries come in their old position, added entries come last.
+fn match_by_name<'a, T>(
+    old: &'a [T],
+    new: &'a [T],
+    name: impl Fn(&T) -> &str,
+) -> Vec<Matched<'a, T>> {
+    let mut new_by_name: HashMap<&str, VecDeque<usize>> = HashMap::new();
+    for (i, entry) in new.iter().enumerate() {
+        new_by_name.entry(name(entry)).or_default().push_back(i);
+    }
+
+    let mut matched = Vec::new();
+    let mut used = vec![false; new.len()];
+    for entry in old {
+        match new_by_name
+            .get_mut(name(entry))
+            .and_then(|indices| indices.pop_front())
+        {
+            Some(i) => {
+                used[i] = true;
+                matched.push(Matched::Both(entry, &new[i]));
+            }
+            None => matched.push(Matched::Removed(entry)),
+        }
+    }
+    for (entry, used) in new.iter().zip(used) {
+        if !used {
+            matched.push(Matched::Added(entry));
+        }
+    }
+    matched
+}
+
+impl fmt::Display for AnmDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for change in &self.changes {
+            writeln!(f, "{change}")?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path, self.kind)
+    }
+}
+
+impl fmt::Display for ChangeKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ClassAdded => write!(f, "class added"),
+            Self::ClassRemoved => write!(f, "class removed"),
+            Self::AnimationAdded => write!(f, "animation added"),
+            Self::AnimationRemoved => write!(f, "animation removed"),
+            Self::Timing { field, old, new } => write!(f, "{} {old} -> {new}", field.name()),
+            Self::FrameCount { old, new } => write!(f, "frame count {old} -> {new}"),
+            Self::FrameId { old, new } => write!(f, "frame id {old} -> {new}"),
+            Self::FireSocket { old, new } => {
+                write!(f, "fire_socket {} -> {}", Point(old), Point(new))
+            }
+            Self::EbPlatformPos { old, new } => {
+                write!(f, "eb_platform_pos {} -> {}", Point(old), Point(new))
+            }
+            Self::BoneAdded => write!(f, "bone added"),
+            Self::BoneRemoved => write!(f, "bone removed"),
+            Self::BoneId { old, new } => write!(f, "id {old} -> {new}"),
+            Self::Transform { old, new } => write!(f, "transform {old:?} -> {new:?}"),
+            Self::Position { old, new } => write!(f, "position {old:?} -> {new:?}"),
+            Self::Opacity { old, new } => write!(f, "opacity {old} -> {new}"),
+            Self::SpriteFrame { old, new } => write!(f, "frame {old} -> {new}"),
+        }
+    }
+}
+
+struct Point<'a>(&'a Option<(f64, f64)>);
+
+impl fmt::Display for Point<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Some((x, y)) => write!(f, "({x}, {y})"),
+            None => write!(f, "none"),
+        }
+    }
+}
@@ -0,0 +1,174 @@
+//! Frame data tables: one row per animation, as CSV or JSON.
+
+use crate::{AnmAnimation, AnmFile, AnmReadingError, json};
+
+/// The timing of one animation.
+///
+/// The phase lengths split the frames at `loop_start`, `recovery_start` and `free_start`.
+/// A phase whose bounds are out of order is 0 frames long.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct FrameDataRow {
+    pub class_key: String,
+    pub animation: String,
+    pub frames: usize,
+    pub loop_start: u32,
+    pub recovery_start: u32,
+    pub free_start: u32,
+    pub base_start: u32,
+    pub preview_frame: u32,
+    /// The frames that have a `fire_socket`.
+    pub fire_socket_frames: Vec<usize>,
+    /// Frames before `loop_start`.
+    pub startup: usize,
+    /// Frames from `loop_start` to `recovery_start`.
+    pub looping: usize,
+    /// Frames from `recovery_start` to `free_start`.
+    pub recovery: usize,
+    /// Frames from `free_start` to the end.
+    pub free: usize,
+}
+
+const COLUMNS: [&str; 13] = [
+    "class_key",
+    "animation",
+    "frames",
+    "loop_start",
+    "recovery_start",
+    "free_start",
+    "base_start",
+    "preview_frame",
+    "fire_socket_frames",
+    "startup",
+    "looping",
+    "recovery",
+    "free",
+];
+
+impl FrameDataRow {
+    pub fn new(class_key: &str, animation: &AnmAnimation) -> Result<Self, AnmReadingError> {
+        let fire_socket_frames = animation
+            .frames()?
+            .iter()
+            .enumerate()
+            .filter(|(_, frame)| frame.fire_socket.is_some())
+            .map(|(i, _)| i)
+            .collect();
+
+        let frames = animation.frame_count();
+        let clamp = |frame: u32| (frame as usize).min(frames);
+        let loop_start = clamp(animation.loop_start);
+        let recovery_start = clamp(animation.recovery_start);
+        let free_start = clamp(animation.free_start);
+
+        Ok(Self {
+            class_key: class_key.to_owned(),
+            animation: animation.name.clone(),
+            frames,
+            loop_start: animation.loop_start,
+            recovery_start: animation.recovery_start,
+            free_start: animation.free_start,
+            base_start: animation.base_start,
+            preview_frame: animation.preview_frame,
+            fire_socket_frames,
+            startup: loop_start,
+            looping: recovery_start.saturating_sub(loop_start),
+            recovery: free_start.saturating_sub(recovery_start),
+            free: frames - free_start,
+        })
+    }
+
+    fn fields(&self) -> [String; 13] {
+        let fire_socket_frames: Vec<_> = self
+            .fire_socket_frames
+            .iter()
+            .map(|i| i.to_string())
+            .collect();
+        [
+            self.class_key.clone(),
+            self.animation.clone(),
+            self.frames.to_string(),
+            self.loop_start.to_string(),
+            self.recovery_start.to_string(),
+            self.free_start.to_string(),
+            self.base_start.to_string(),
+            self.preview_frame.to_string(),
+            fire_socket_frames.join(" "),
+            self.startup.to_string(),
+            self.looping.to_string(),
+            self.recovery.to_string(),
+            self.free.to_string(),
+        ]
+    }
+}
+
+/// A row for every animation in the file, in file order.
+pub fn frame_data(file: &AnmFile) -> Result<Vec<FrameDataRow>, AnmReadingError> {
+    let mut rows = Vec::new();
+    for (key, class) in file.classes.iter() {
+        for animation in class.animations.iter() {
+            rows.push(FrameDataRow::new(key, animation)?);
+        }
+    }
+    Ok(rows)
+}
+
+/// CSV with a header line. `fire_socket_frames` is a space separated list.
+pub fn to_csv(rows: &[FrameDataRow]) -> String {
+    let mut out = String::new();
+    write_csv_line(&mut out, COLUMNS.iter().copied());
+    for row in rows {
+        write_csv_line(&mut out, row.fields().iter().map(String::as_str));
+    }
+    out
+}
+
+fn write_csv_line<'a>(out: &mut String, fields: impl Iterator<Item = &'a str>) {
+    for (i, field) in fields.enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        if field.contains([',', '"', '\n', '\r']) {
+            out.push('"');
+            out.push_str(&field.replace('"', "\"\""));
+            out.push('"');
+        } else {
+            out.push_str(field);
+        }
+    }
+    out.push_str("\r\n");
+}
+
+/// A JSON array of objects, with the same fields as the CSV.
+pub fn to_json(rows: &[FrameDataRow]) -> String {
+    let mut out = String::from("[");
+    for (i, row) in rows.iter().enumerate() {
+        out.push_str(if i == 0 { "\n  {" } else { ",\n  {" });
+        json::write_string(&mut out, "class_key");
+        out.push(':');
+        json::write_string(&mut out, &row.class_key);
+        out.push(',');
+        json::write_string(&mut out, "animation");
+        out.push(':');
+        json::write_string(&mut out, &row.animation);
+        w!(
+            out,
+            ",\"frames\":{},\"loop_start\":{},\"recovery_start\":{},\"free_start\":{},\
+             \"base_start\":{},\"preview_frame\":{},\"fire_socket_frames\":{:?},\
+             \"startup\":{},\"looping\":{},\"recovery\":{},\"free\":{}}}",
+            row.frames,
+            row.loop_start,
+            row.recovery_start,
+            row.free_start,
+            row.base_start,
+            row.preview_frame,
+            row.fire_socket_frames,
+            row.startup,
+            row.looping,
+            row.recovery,
+            row.free,
+        );
+    }
+    out.push_str(if rows.is_empty() { "]\n" } else { "\n]\n" });
+    out
+}
@@ -0,0 +1,277 @@
+//! Godot 4 export: an animation as an `Animation` resource (`.tres`), and a scene (`.tscn`)
+//! with a `Sprite2D` per bone and an `AnimationPlayer` that plays it.
+//!
+//! Each bone id becomes a sprite named `Bone<id>`, and each extra copy of an id on a frame
+//! gets its own sprite, named `Bone<id>#2`, `Bone<id>#3`, as in the Spine export.
+//! The tracks drive each sprite's `transform`, `visible`, `modulate:a`, `frame` and `z_index`,
+//! so the bones keep their drawing order. Sprite frames count from 0 in Godot, and from 1 here.
+//! The `fire_socket` drives a `Marker2D` named `FireSocket`.
+//!
+//! Godot loops whole animations only. An animation whose loop covers all of its frames
+//! is exported with `loop_mode` set; any other loop is written as `loop_start` and `loop_end`
+//! metadata, in seconds, for game code to seek with.
+
+use crate::{AnmAnimation, AnmFrame, AnmReadingError};
+use std::collections::HashMap;
+
+/// How to export an animation.
+#[derive(Clone, Debug)]
+pub struct GodotOptions {
+    /// The frame rate the animation is authored at, to turn frames into seconds.
+    pub fps: f64,
+    /// Whether transforms and opacity are interpolated between frames,
+    /// rather than held like in the game.
+    pub interpolate: bool,
+}
+
+impl Default for GodotOptions {
+    fn default() -> Self {
+        Self {
+            fps: 24.,
+            interpolate: false,
+        }
+    }
+}
+
+/// A sprite for a copy of a bone id, with the highest sprite frame it shows.
+struct Sprite {
+    name: String,
+    frames: i32,
+}
+
+fn sprites(frames: &[AnmFrame]) -> (Vec<Sprite>, HashMap<(i16, usize), usize>) {
+    let mut sprites: Vec<Sprite> = Vec::new();
+    let mut indices = HashMap::new();
+    for frame in frames {
+        for (bone, key) in frame.bones.iter().zip(frame.bone_copies()) {
+            let index = *indices.entry(key).or_insert_with(|| {
+                let name = AnmFrame::copy_name(format!("Bone{}", key.0), key.1);
+                sprites.push(Sprite { name, frames: 1 });
+                sprites.len() - 1
+            });
+            let sprite = &mut sprites[index];
+            sprite.frames = sprite.frames.max(bone.frame as i32);
+        }
+    }
+    (sprites, indices)
+}
+
+/// The tracks of each sprite: `visible`, `transform`, `modulate:a`, `frame` and `z_index`.
+const TRACKS_PER_SPRITE: usize = 5;
+
+/// A value track, with keys as Godot text, and a key only where the value changes.
+struct Track {
+    path: String,
+    interpolate: bool,
+    keys: Vec<(f64, String)>,
+}
+
+impl Track {
+    fn new(path: String, interpolate: bool) -> Self {
+        Self {
+            path,
+            interpolate,
+            keys: Vec::new(),
+        }
+    }
+
+    fn key(&mut self, time: f64, value: String) {
+        if self.keys.last().is_none_or(|(_, last)| *last != value) {
+            self.keys.push((time, value));
+        }
+    }
+}
+
+/// Writes the animation as an `Animation` resource.
+pub fn animation_tres(
+    animation: &AnmAnimation,
+    options: &GodotOptions,
+) -> Result<String, AnmReadingError> {
+    let frames = animation.frames()?;
+    let (sprites, indices) = sprites(frames);
+    let interpolate = options.interpolate;
+
+    let mut tracks = Vec::new();
+    for sprite in &sprites {
+        let name = &sprite.name;
+        tracks.push(Track::new(format!("{name}:visible"), false));
+        tracks.push(Track::new(format!("{name}:transform"), interpolate));
+        tracks.push(Track::new(format!("{name}:modulate:a"), interpolate));
+        tracks.push(Track::new(format!("{name}:frame"), false));
+        tracks.push(Track::new(format!("{name}:z_index"), false));
+    }
+    let mut fire_socket_visible = Track::new("FireSocket:visible".into(), false);
+    let mut fire_socket_position = Track::new("FireSocket:position".into(), interpolate);
+
+    for (i, frame) in frames.iter().enumerate() {
+        let time = i as f64 / options.fps;
+        let mut shown = vec![None; sprites.len()];
+        for (z_index, key) in frame.bone_copies().into_iter().enumerate() {
+            shown[indices[&key]] = Some(z_index);
+        }
+        for (index, z_index) in shown.into_iter().enumerate() {
+            let tracks = &mut tracks[index * TRACKS_PER_SPRITE..(index + 1) * TRACKS_PER_SPRITE];
+            let Some(z_index) = z_index else {
+                tracks[0].key(time, "false".into());
+                continue;
+            };
+            let bone = &frame.bones[z_index];
+            let m = bone.transform();
+            tracks[0].key(time, "true".into());
+            tracks[1].key(
+                time,
+                format!(
+                    "Transform2D({}, {}, {}, {}, {}, {})",
+                    num(m.a),
+                    num(m.b),
+                    num(m.c),
+                    num(m.d),
+                    num(m.tx),
+                    num(m.ty)
+                ),
+            );
+            tracks[2].key(time, num(bone.opacity.clamp(0., 1.)));
+            tracks[3].key(time, (bone.frame as i32 - 1).max(0).to_string());
+            tracks[4].key(time, z_index.to_string());
+        }
+        match frame.fire_socket {
+            Some((x, y)) => {
+                fire_socket_visible.key(time, "true".into());
+                fire_socket_position.key(time, format!("Vector2({}, {})", num(x), num(y)));
+            }
+            None => fire_socket_visible.key(time, "false".into()),
+        }
+    }
+    tracks.push(fire_socket_visible);
+    tracks.push(fire_socket_position);
+
+    let mut out = String::from("[gd_resource type=\"Animation\" format=3]\n\n[resource]\n");
+    wln!(out, "resource_name = {}", string(&animation.name));
+    wln!(out, "length = {}", num(frames.len() as f64 / options.fps));
+    let frame_count = frames.len();
+    let loop_start = animation.loop_start as usize;
+    let loop_end = (animation.recovery_start as usize).min(frame_count);
+    let loops_all = loop_start == 0 && loop_end == frame_count && frame_count > 0;
+    wln!(out, "loop_mode = {}", if loops_all { 1 } else { 0 });
+    wln!(out, "step = {}", num(1. / options.fps));
+
+    for (i, track) in tracks.iter().filter(|t| !t.keys.is_empty()).enumerate() {
+        wln!(out, "tracks/{i}/type = \"value\"");
+        wln!(out, "tracks/{i}/imported = false");
+        wln!(out, "tracks/{i}/enabled = true");
+        wln!(out, "tracks/{i}/path = NodePath({})", string(&track.path));
+        wln!(out, "tracks/{i}/interp = {}", track.interpolate as u8);
+        wln!(out, "tracks/{i}/loop_wrap = true");
+        let times: Vec<String> = track.keys.iter().map(|(t, _)| num(*t)).collect();
+        let values: Vec<&str> = track.keys.iter().map(|(_, v)| v.as_str()).collect();
+        wln!(out, "tracks/{i}/keys = {{");
+        wln!(out, "\"times\": PackedFloat32Array({}),", times.join(", "));
+        wln!(
+            out,
+            "\"transitions\": PackedFloat32Array({}),",
+            vec!["1"; times.len()].join(", ")
+        );
+        // continuous updates interpolate, discrete ones hold each key
+        wln!(
+            out,
+            "\"update\": {},",
+            if track.interpolate { 0 } else { 1 }
+        );
+        wln!(out, "\"values\": [{}]", values.join(", "));
+        wln!(out, "}}");
+    }
+
+    if !loops_all && loop_start < loop_end {
+        wln!(
+            out,
+            "metadata/loop_start = {}",
+            num(loop_start as f64 / options.fps)
+        );
+        wln!(
+            out,
+            "metadata/loop_end = {}",
+            num(loop_end as f64 / options.fps)
+        );
+    }
+    Ok(out)
+}
+
+/// Writes a scene with a sprite per bone, and an `AnimationPlayer` that autoplays
+/// the animation resource at `animation_path`, such as `res://Attack.tres`.
+///
+/// The sprites have no texture, and `hframes` set to the highest sprite frame of their bone,
+/// so the frame track can run once a sprite sheet is assigned.
+pub fn scene_tscn(
+    animation: &AnmAnimation,
+    animation_path: &str,
+) -> Result<String, AnmReadingError> {
+    let (sprites, _) = sprites(animation.frames()?);
+    let name = string(&animation.name);
+
+    let mut out = String::from("[gd_scene load_steps=3 format=3]\n\n");
+    wln!(
+        out,
+        "[ext_resource type=\"Animation\" path={} id=\"1\"]\n",
+        string(animation_path)
+    );
+    wln!(
+        out,
+        "[sub_resource type=\"AnimationLibrary\" id=\"AnimationLibrary_1\"]"
+    );
+    wln!(out, "_data = {{\n{name}: ExtResource(\"1\")\n}}\n");
+    wln!(
+        out,
+        "[node name={} type=\"Node2D\"]\n",
+        string(&node_name(&animation.name))
+    );
+    for sprite in &sprites {
+        wln!(
+            out,
+            "[node name={} type=\"Sprite2D\" parent=\".\"]",
+            string(&sprite.name)
+        );
+        wln!(out, "visible = false");
+        wln!(out, "hframes = {}\n", sprite.frames);
+    }
+    wln!(
+        out,
+        "[node name=\"FireSocket\" type=\"Marker2D\" parent=\".\"]"
+    );
+    wln!(out, "visible = false\n");
+    wln!(
+        out,
+        "[node name=\"AnimationPlayer\" type=\"AnimationPlayer\" parent=\".\"]"
+    );
+    wln!(
+        out,
+        "libraries = {{\n\"\": SubResource(\"AnimationLibrary_1\")\n}}"
+    );
+    wln!(out, "autoplay = {name}");
+    Ok(out)
+}
+
+/// A quoted Godot string.
+fn string(value: &str) -> String {
+    let mut out = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// Node names can't have these characters.
+fn node_name(name: &str) -> String {
+    name.replace(['.', ':', '@', '/', '"', '%'], "_")
+}
+
+/// As `f32`, the precision Godot keeps, with NaN and infinities as 0.
+fn num(value: f64) -> String {
+    let value = if value.is_finite() { value } else { 0. };
+    format!("{}", value as f32)
+}
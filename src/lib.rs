@@ -7,11 +7,68 @@
 //! This library exports the following:
 //! * `AnmBone`: A sprite to be positioned in the world.
 //! * `AnmFrame`: A single frame of animation.
-//! * `AnmAnimation`: A complete animation.
-//! * `AnmClass`: A collection of animations, indexed by their name.
-//! * `AnmFile`: A collection of animation classes.
+//! * `AnmAnimation`: A complete animation. `AnmAnimation::sample` gives the pose in between frames.
+//! * `AnmClass`: A collection of animations, indexed by their name and kept in file order.
+//! * `AnmFile`: A collection of animation classes, indexed by their key and kept in file order.
+//! * `AnimationPlayer`: Plays an animation frame by frame, following its loop and recovery timing.
+//! * `Affine2`: A 2D transform, used to decompose a bone's matrix into rotation, scale, skew and flip.
+//!
+//! The `diff` module compares two files, and `patch_notes` turns the comparison
+//! into a report of the gameplay changes.
+//!
+//! The `frame_data` module exports the timing of every animation as CSV or JSON.
+//!
+//! The `godot` module exports an animation as a Godot `Animation` resource and scene.
+//!
+//! The `render` module draws frames into images, with placeholder sprites or,
+//! with the `swf` feature, the real sprites from the class's SWF file.
+//!
+//! The `spine` module exports a class's animations as a Spine skeleton, and with the
+//! `spine-import` feature, imports them back.
+//!
+//! The `svg` module draws frames as wireframes, without needing the game's sprites,
+//! and plays animations on a loop as animated SVG, or HTML with CSS keyframes.
+//!
+//! The `text` module holds a human-readable format for animations, used by
+//! `AnmFile::unpack_to_dir` and `AnmFile::pack_from_dir`, and for whole files,
+//! which parses back to the same bytes.
+
+/// `write!` into a `String`. Writing to a `String` can't fail, so the result is dropped.
+macro_rules! w {
+    ($out:expr, $($arg:tt)*) => {{
+        use std::fmt::Write as _;
+        _ = $out.write_fmt(format_args!($($arg)*));
+    }};
+}
+
+/// `writeln!` into a `String`, like `w!`.
+macro_rules! wln {
+    ($out:expr) => {
+        $out.push('\n')
+    };
+    ($out:expr, $($arg:tt)*) => {{
+        w!($out, $($arg)*);
+        $out.push('\n');
+    }};
+}
 
+mod affine2;
+mod anm_dir;
 mod anm_objects;
+pub mod diff;
+pub mod frame_data;
+pub mod godot;
+mod json;
+pub mod patch_notes;
+mod player;
+pub mod render;
+mod sample;
+pub mod spine;
+pub mod svg;
+pub mod text;
 
 // Re-exports
+pub use affine2::{Affine2, Decomposition};
+pub use anm_dir::AnmDirError;
 pub use anm_objects::*;
+pub use player::{AnimationPlayer, Phase, Tick};
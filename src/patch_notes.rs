@@ -0,0 +1,239 @@
+//! Patch notes for players: the gameplay-relevant part of a diff, rendered as Markdown or HTML.
+
+use crate::diff::{AnmDiff, ChangeKind, Entry};
+use std::fmt;
+
+/// Gameplay changes grouped by class and animation, in file order.
+/// A key or name that appears more than once gets notes for each entry.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PatchNotes {
+    pub classes: Vec<ClassNotes>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ClassNotes {
+    pub key: String,
+    pub status: Status,
+    pub animations: Vec<AnimationNotes>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct AnimationNotes {
+    pub name: String,
+    pub status: Status,
+    /// Changes with the frame they happen on, if any.
+    pub changes: Vec<(Option<usize>, ChangeKind)>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Status {
+    Added,
+    Removed,
+    Changed,
+}
+
+impl PatchNotes {
+    /// Keeps the changes for which `ChangeKind::affects_gameplay` is true.
+    pub fn new(diff: &AnmDiff) -> Self {
+        let mut classes: Vec<ClassNotes> = Vec::new();
+        // the entry of the last class and animation notes, since keys and names can repeat
+        let mut last_entry = None;
+        for change in &diff.changes {
+            if !change.kind.affects_gameplay() {
+                continue;
+            }
+            let Some(key) = &change.path.class_key else {
+                continue;
+            };
+
+            let same_class = last_entry.map(|last: Entry| last.class) == Some(change.entry.class);
+            let same_animation = last_entry == Some(change.entry);
+            last_entry = Some(change.entry);
+
+            let class = match classes.last_mut() {
+                Some(class) if same_class => class,
+                _ => {
+                    classes.push(ClassNotes {
+                        key: key.clone(),
+                        status: Status::Changed,
+                        animations: Vec::new(),
+                    });
+                    classes.last_mut().unwrap()
+                }
+            };
+            let Some(name) = &change.path.animation else {
+                class.status = match change.kind {
+                    ChangeKind::ClassAdded => Status::Added,
+                    ChangeKind::ClassRemoved => Status::Removed,
+                    _ => class.status,
+                };
+                continue;
+            };
+
+            let animation = match class.animations.last_mut() {
+                Some(animation) if same_animation => animation,
+                _ => {
+                    class.animations.push(AnimationNotes {
+                        name: name.clone(),
+                        status: Status::Changed,
+                        changes: Vec::new(),
+                    });
+                    class.animations.last_mut().unwrap()
+                }
+            };
+            match change.kind {
+                ChangeKind::AnimationAdded => animation.status = Status::Added,
+                ChangeKind::AnimationRemoved => animation.status = Status::Removed,
+                _ => animation
+                    .changes
+                    .push((change.path.frame, change.kind.clone())),
+            }
+        }
+
+        Self { classes }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.classes.is_empty()
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let mut out = String::new();
+        if self.is_empty() {
+            wln!(out, "No gameplay changes.");
+        }
+        for class in &self.classes {
+            wln!(
+                out,
+                "## {}{}",
+                escape_markdown(&class.key),
+                status_suffix(class.status)
+            );
+            wln!(out);
+            for animation in &class.animations {
+                wln!(
+                    out,
+                    "* **{}**{}",
+                    escape_markdown(&animation.name),
+                    status_suffix(animation.status)
+                );
+                for (frame, kind) in &animation.changes {
+                    wln!(out, "  * {}", Note(*frame, kind));
+                }
+            }
+            if !class.animations.is_empty() {
+                wln!(out);
+            }
+        }
+        out
+    }
+
+    pub fn to_html(&self) -> String {
+        let mut out = String::new();
+        if self.is_empty() {
+            wln!(out, "<p>No gameplay changes.</p>");
+        }
+        for class in &self.classes {
+            wln!(
+                out,
+                "<h2>{}{}</h2>",
+                escape_html(&class.key),
+                status_suffix(class.status)
+            );
+            if class.animations.is_empty() {
+                continue;
+            }
+            wln!(out, "<ul>");
+            for animation in &class.animations {
+                w!(
+                    out,
+                    "<li><strong>{}</strong>{}",
+                    escape_html(&animation.name),
+                    status_suffix(animation.status)
+                );
+                if !animation.changes.is_empty() {
+                    wln!(out, "<ul>");
+                    for (frame, kind) in &animation.changes {
+                        let note = Note(*frame, kind).to_string();
+                        wln!(out, "<li>{}</li>", escape_html(&note));
+                    }
+                    w!(out, "</ul>");
+                }
+                wln!(out, "</li>");
+            }
+            wln!(out, "</ul>");
+        }
+        out
+    }
+}
+
+fn status_suffix(status: Status) -> &'static str {
+    match status {
+        Status::Added => " (added)",
+        Status::Removed => " (removed)",
+        Status::Changed => "",
+    }
+}
+
+/// Backslash-escapes all ASCII punctuation, so names show as written instead of as
+/// emphasis, code, links or inline HTML. Line breaks would end the line, so they become spaces.
+fn escape_markdown(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        if c.is_ascii_punctuation() {
+            out.push('\\');
+        }
+        out.push(if c.is_control() { ' ' } else { c });
+    }
+    out
+}
+
+fn escape_html(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// One line of the notes.
+struct Note<'a>(Option<usize>, &'a ChangeKind);
+
+impl fmt::Display for Note<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.1, self.0) {
+            (ChangeKind::FrameCount { old, new }, _) => {
+                write!(
+                    f,
+                    "frames: {old} -> {new} ({:+})",
+                    *new as i64 - *old as i64
+                )
+            }
+            (ChangeKind::Timing { field, old, new }, _) => write!(
+                f,
+                "{}: {old} -> {new} ({:+})",
+                field.name(),
+                *new as i64 - *old as i64
+            ),
+            (ChangeKind::FireSocket { old, new }, Some(frame)) => {
+                write!(f, "fire_socket on frame {frame}: ")?;
+                match (old, new) {
+                    (None, Some((x, y))) => write!(f, "added at ({x}, {y})"),
+                    (Some(_), None) => write!(f, "removed"),
+                    (Some((x0, y0)), Some((x1, y1))) => {
+                        write!(f, "moved from ({x0}, {y0}) to ({x1}, {y1})")
+                    }
+                    (None, None) => Ok(()),
+                }
+            }
+            (kind, _) => write!(f, "{kind}"),
+        }
+    }
+}
@@ -0,0 +1,177 @@
+use crate::AnmAnimation;
+
+/// Steps through an animation one frame per tick, driven by its timing fields:
+/// * playback starts at `base_start`, or at the last frame if `base_start` is past it.
+/// * frames from `loop_start` up to `recovery_start` repeat until `exit_loop` is called.
+///   If `loop_start` is not before `recovery_start`, nothing loops.
+/// * the character is free to act from `free_start` on, and once the animation has finished.
+/// * after the last frame, the player stays on it and reports that it finished.
+///
+/// `loop_start` and `recovery_start` decide the loop, like they do for the exporters' default range.
+/// The phases are kept in the order `frame_data` splits them in: recovery doesn't start before
+/// `loop_start`, and the character isn't free before `recovery_start`.
+/// A `recovery_start` or `free_start` of 0 is taken as unset, as it is in animations without that
+/// phase: an unset `recovery_start` means no loop and no recovery, and an unset `free_start` means
+/// the character is only free once the animation has finished. So an animation whose timing
+/// fields are all 0 plays once through in `Startup`.
+///
+/// This follows the names of the fields, and is not a model of the game's own playback.
+/// Only the timing fields and the frame count are used, so lazily read frames are not decoded.
+#[derive(Clone, Debug)]
+pub struct AnimationPlayer {
+    frame_count: usize,
+    base_start: usize,
+    loop_start: usize,
+    /// Where the loop jumps back, if there is a loop.
+    loop_end: Option<usize>,
+    /// The frame count if unset.
+    recovery_start: usize,
+    /// The frame count if unset.
+    free_start: usize,
+
+    frame: usize,
+    started: bool,
+    exiting: bool,
+    finished: bool,
+    was_free: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Phase {
+    /// Before `loop_start`.
+    Startup,
+    /// From `loop_start` up to `recovery_start`.
+    Loop,
+    /// From `recovery_start` up to `free_start`.
+    Recovery,
+    /// From `free_start` on, and once the animation has finished.
+    Free,
+}
+
+/// What happened on a tick.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Tick {
+    /// The frame to show.
+    pub frame: usize,
+    pub phase: Phase,
+    /// Whether the loop jumped back to `loop_start` on this tick.
+    pub looped: bool,
+    /// Whether this is the first tick in the `Free` phase.
+    pub became_free: bool,
+    /// Whether the animation has run out of frames. The last frame keeps being shown.
+    pub finished: bool,
+}
+
+impl AnimationPlayer {
+    pub fn new(animation: &AnmAnimation) -> Self {
+        let frame_count = animation.frame_count();
+        let clamp = |frame: u32| (frame as usize).min(frame_count);
+        let set = |frame: u32| (frame != 0).then(|| clamp(frame));
+        let loop_start = clamp(animation.loop_start);
+        let base_start = clamp(animation.base_start).min(frame_count.saturating_sub(1));
+
+        let recovery_start = set(animation.recovery_start);
+        let free_start = set(animation.free_start);
+        let loop_end = recovery_start.filter(|&recovery_start| loop_start < recovery_start);
+        // each phase starts no earlier than the one before it
+        let recovery_start = match recovery_start {
+            Some(recovery_start) => recovery_start.max(loop_start),
+            None => free_start.unwrap_or(frame_count),
+        };
+        let free_start =
+            free_start.map_or(frame_count, |free_start| free_start.max(recovery_start));
+
+        Self {
+            frame_count,
+            base_start,
+            loop_start,
+            loop_end,
+            recovery_start,
+            free_start,
+            frame: base_start,
+            started: false,
+            exiting: false,
+            finished: frame_count == 0,
+            was_free: false,
+        }
+    }
+
+    /// Goes back to the state before the first tick.
+    pub fn restart(&mut self) {
+        self.frame = self.base_start;
+        self.started = false;
+        self.exiting = false;
+        self.finished = self.frame_count == 0;
+        self.was_free = false;
+    }
+
+    /// Lets the loop run into recovery instead of jumping back. Stays in effect until `restart`.
+    pub fn exit_loop(&mut self) {
+        self.exiting = true;
+    }
+
+    /// Advances by one frame. The first tick shows the starting frame.
+    pub fn tick(&mut self) -> Tick {
+        let mut looped = false;
+        if !self.started {
+            self.started = true;
+        } else if !self.finished {
+            let mut next = self.frame + 1;
+            if !self.exiting && Some(next) == self.loop_end {
+                next = self.loop_start;
+                looped = true;
+            }
+            if next < self.frame_count {
+                self.frame = next;
+            } else {
+                self.finished = true;
+            }
+        }
+
+        let phase = self.phase();
+        let is_free = phase == Phase::Free;
+        let became_free = is_free && !self.was_free;
+        self.was_free = is_free;
+        Tick {
+            frame: self.frame,
+            phase,
+            looped,
+            became_free,
+            finished: self.finished,
+        }
+    }
+
+    pub fn frame(&self) -> usize {
+        self.frame
+    }
+
+    pub fn phase(&self) -> Phase {
+        if self.finished || self.frame >= self.free_start {
+            Phase::Free
+        } else if self.frame >= self.recovery_start {
+            Phase::Recovery
+        } else if self.loop_end.is_some() && self.frame >= self.loop_start {
+            Phase::Loop
+        } else {
+            Phase::Startup
+        }
+    }
+
+    pub fn is_looping(&self) -> bool {
+        self.loop_end.is_some() && !self.exiting
+    }
+
+    pub fn is_free(&self) -> bool {
+        self.phase() == Phase::Free
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+}
+
+impl AnmAnimation {
+    pub fn player(&self) -> AnimationPlayer {
+        AnimationPlayer::new(self)
+    }
+}
@@ -0,0 +1,183 @@
+//! Rendering whole animations, and writing them as animated GIF (`gif` feature) or APNG (`png` feature).
+
+use super::{RenderOptions, RgbaImage, SpriteSource, TooLargeImageError, render_bones};
+use crate::{AnmAnimation, AnmReadingError};
+use std::{io, ops::Range};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum AnimationExportError {
+    #[error(transparent)]
+    IOError(#[from] io::Error),
+    #[error(transparent)]
+    ReadingError(#[from] AnmReadingError),
+    #[cfg(feature = "gif")]
+    #[error(transparent)]
+    GifError(#[from] gif::EncodingError),
+    #[cfg(feature = "png")]
+    #[error(transparent)]
+    PngError(#[from] png::EncodingError),
+    #[error("image size {width}x{height} is too large")]
+    TooLargeImageError { width: u32, height: u32 },
+    #[error("nothing to export: frame range {start}..{end} is empty")]
+    EmptyRangeError { start: usize, end: usize },
+    #[error("frame rates must be finite and positive: ({fps:?})")]
+    InvalidFrameRateError { fps: f64 },
+    #[error("scale must be finite and positive: ({scale:?})")]
+    InvalidScaleError { scale: f64 },
+    #[error("too many frames to render, the most is {MAX_OUTPUT_FRAMES}: ({frame_count:?})")]
+    TooManyFramesError { frame_count: usize },
+}
+
+impl From<TooLargeImageError> for AnimationExportError {
+    fn from(TooLargeImageError { width, height }: TooLargeImageError) -> Self {
+        Self::TooLargeImageError { width, height }
+    }
+}
+
+/// The most images `render_animation` renders, since they are all kept in memory.
+pub const MAX_OUTPUT_FRAMES: usize = 1 << 14;
+
+#[derive(Clone, Debug)]
+pub struct AnimationOptions {
+    /// The canvas size, placement and background.
+    pub render: RenderOptions,
+    /// The frame rate of the output.
+    pub fps: f64,
+    /// The frame rate the animation is authored at. When it differs from `fps`,
+    /// poses in between frames are interpolated with `AnmAnimation::sample`.
+    pub animation_fps: f64,
+    /// The frames to export, played on a loop.
+    /// `None` takes the loop of the animation, from `loop_start` up to `recovery_start`,
+    /// or all frames if the animation doesn't loop.
+    pub range: Option<Range<usize>>,
+}
+
+impl Default for AnimationOptions {
+    fn default() -> Self {
+        Self {
+            render: RenderOptions::default(),
+            fps: 24.,
+            animation_fps: 24.,
+            range: None,
+        }
+    }
+}
+
+impl AnimationOptions {
+    /// The frame range that `range` resolves to, clamped to the animation.
+    pub fn frame_range(&self, animation: &AnmAnimation) -> Range<usize> {
+        let frame_count = animation.frame_count();
+        let range = self.range.clone().unwrap_or_else(|| {
+            let loop_start = animation.loop_start as usize;
+            let recovery_start = (animation.recovery_start as usize).min(frame_count);
+            if loop_start < recovery_start {
+                loop_start..recovery_start
+            } else {
+                0..frame_count
+            }
+        });
+        range.start.min(frame_count)..range.end.min(frame_count)
+    }
+
+    /// The output frame delay in seconds.
+    pub fn delay(&self) -> f64 {
+        self.fps.recip()
+    }
+}
+
+/// Renders the frames of the range, at the output frame rate.
+///
+/// Both frame rates must be finite and positive, the render size must fit in `MAX_SIZE` and `MAX_PIXELS`,
+/// and at most `MAX_OUTPUT_FRAMES` images are rendered.
+pub fn render_animation(
+    animation: &AnmAnimation,
+    sprites: &impl SpriteSource,
+    options: &AnimationOptions,
+) -> Result<Vec<RgbaImage>, AnimationExportError> {
+    let range = options.frame_range(animation);
+    if range.is_empty() {
+        return Err(AnimationExportError::EmptyRangeError {
+            start: range.start,
+            end: range.end,
+        });
+    }
+
+    for fps in [options.fps, options.animation_fps] {
+        if !(fps.is_finite() && fps > 0.) {
+            return Err(AnimationExportError::InvalidFrameRateError { fps });
+        }
+    }
+    super::check_size(options.render.width, options.render.height)?;
+
+    let step = options.animation_fps / options.fps;
+    // infinite if the step rounds down to 0
+    let output_count = (range.len() as f64 / step).round().max(1.);
+    if output_count > MAX_OUTPUT_FRAMES as f64 {
+        return Err(AnimationExportError::TooManyFramesError {
+            frame_count: output_count as usize,
+        });
+    }
+    let output_count = output_count as usize;
+
+    let mut images = Vec::with_capacity(output_count);
+    for i in 0..output_count {
+        let t = range.start as f64 + i as f64 * step;
+        let t = t.min((range.end - 1) as f64);
+        let bones = animation.sample(t)?;
+        images.push(render_bones(&bones, sprites, &options.render)?);
+    }
+    Ok(images)
+}
+
+/// Writes an animated GIF that loops forever.
+/// GIF has no partial transparency, so pixels are either fully transparent or opaque;
+/// an opaque `background` avoids jagged edges.
+#[cfg(feature = "gif")]
+pub fn write_gif<W: io::Write>(
+    animation: &AnmAnimation,
+    sprites: &impl SpriteSource,
+    options: &AnimationOptions,
+    writer: W,
+) -> Result<(), AnimationExportError> {
+    let (width, height) = (options.render.width, options.render.height);
+    let too_large = || AnimationExportError::TooLargeImageError { width, height };
+    let gif_width = u16::try_from(width).map_err(|_| too_large())?;
+    let gif_height = u16::try_from(height).map_err(|_| too_large())?;
+
+    let images = render_animation(animation, sprites, options)?;
+    let mut encoder = gif::Encoder::new(writer, gif_width, gif_height, &[])?;
+    encoder.set_repeat(gif::Repeat::Infinite)?;
+    let delay = (options.delay() * 100.).round().clamp(1., u16::MAX as f64) as u16;
+    for mut image in images {
+        let mut frame = gif::Frame::from_rgba_speed(gif_width, gif_height, &mut image.pixels, 10);
+        frame.delay = delay;
+        frame.dispose = gif::DisposalMethod::Background;
+        encoder.write_frame(&frame)?;
+    }
+    Ok(())
+}
+
+/// Writes an animated PNG that loops forever.
+#[cfg(feature = "png")]
+pub fn write_apng<W: io::Write>(
+    animation: &AnmAnimation,
+    sprites: &impl SpriteSource,
+    options: &AnimationOptions,
+    writer: W,
+) -> Result<(), AnimationExportError> {
+    let images = render_animation(animation, sprites, options)?;
+    let mut encoder = png::Encoder::new(writer, options.render.width, options.render.height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_animated(images.len() as u32, 0)?;
+    let delay_ms = (options.delay() * 1000.).round().clamp(1., u16::MAX as f64) as u16;
+    encoder.set_frame_delay(delay_ms, 1000)?;
+
+    let mut writer = encoder.write_header()?;
+    for image in &images {
+        writer.write_image_data(&image.pixels)?;
+    }
+    writer.finish()?;
+    Ok(())
+}
@@ -0,0 +1,223 @@
+//! Sprite-sheet atlases: every frame of an animation rendered and packed into one image,
+//! with a JSON description of where each frame is.
+
+use super::{
+    MAX_SIZE, RenderOptions, RgbaImage, SpriteSource, animated::AnimationExportError, bounds,
+    check_size, render_bones,
+};
+use crate::{AnmAnimation, json};
+
+/// How to render and pack the frames.
+#[derive(Clone, Debug)]
+pub struct AtlasOptions {
+    /// Must be finite and positive.
+    pub scale: f64,
+    /// Transparent pixels around each frame's bounds, inside its rectangle.
+    pub margin: u32,
+    /// Transparent pixels between rectangles.
+    pub spacing: u32,
+    /// The width at which to start a new row. Wider frames get a row of their own.
+    pub max_width: u32,
+}
+
+impl Default for AtlasOptions {
+    fn default() -> Self {
+        Self {
+            scale: 1.,
+            margin: 1,
+            spacing: 1,
+            max_width: 2048,
+        }
+    }
+}
+
+/// Where a frame is in the atlas, in pixels.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AtlasFrame {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    /// Where the frame's `(0, 0)`, the origin the bones are placed from, lands,
+    /// from the top left of the rectangle. It can be outside the rectangle.
+    pub pivot: (f64, f64),
+    /// The `fire_socket` of the frame, from the pivot, scaled.
+    pub fire_socket: Option<(f64, f64)>,
+}
+
+/// The packed image and a rectangle per frame, in frame order.
+/// Frames that draw nothing have an empty rectangle.
+#[derive(Clone, Debug)]
+pub struct Atlas {
+    pub image: RgbaImage,
+    pub frames: Vec<AtlasFrame>,
+}
+
+/// Renders each frame cropped to the bounds of its sprites, and packs them into rows,
+/// tallest first. Each frame is copied into the atlas as soon as it is rendered.
+///
+/// `scale` must be finite and positive. Fails with `TooLargeImageError` if a frame's rectangle
+/// or the atlas doesn't fit in `MAX_SIZE` and `MAX_PIXELS`.
+pub fn build_atlas(
+    animation: &AnmAnimation,
+    sprites: &impl SpriteSource,
+    options: &AtlasOptions,
+) -> Result<Atlas, AnimationExportError> {
+    let scale = options.scale;
+    if !(scale.is_finite() && scale > 0.) {
+        return Err(AnimationExportError::InvalidScaleError { scale });
+    }
+    let margin = options.margin as f64;
+    let animation_frames = animation.frames()?;
+    let mut frames = Vec::with_capacity(animation_frames.len());
+    for frame in animation_frames {
+        let (left, top, width, height) = match bounds(&frame.bones, sprites) {
+            Some((x0, y0, x1, y1)) => {
+                let left = (x0 * scale).floor() - margin;
+                let top = (y0 * scale).floor() - margin;
+                let right = (x1 * scale).ceil() + margin;
+                let bottom = (y1 * scale).ceil() + margin;
+                let (width, height) = (right - left, bottom - top);
+                // also false for NaN
+                let fits = |size: f64| size <= MAX_SIZE as f64;
+                if !fits(width) || !fits(height) {
+                    return Err(AnimationExportError::TooLargeImageError {
+                        width: width as u32,
+                        height: height as u32,
+                    });
+                }
+                check_size(width as u32, height as u32)?;
+                (left, top, width as u32, height as u32)
+            }
+            None => (0., 0., 0, 0),
+        };
+        frames.push(AtlasFrame {
+            x: 0,
+            y: 0,
+            width,
+            height,
+            pivot: (-left, -top),
+            fire_socket: frame.fire_socket.map(|(x, y)| (x * scale, y * scale)),
+        });
+    }
+
+    // shelf packing: rows of frames sorted by height, each row as tall as its first frame
+    let mut order: Vec<usize> = (0..frames.len()).collect();
+    order.sort_by_key(|&i| std::cmp::Reverse(frames[i].height));
+    // sums in u64 can't overflow, since every size fits in a u32
+    let (mut x, mut y, mut row_height) = (0u64, 0u64, 0u64);
+    let (mut atlas_width, mut atlas_height) = (0u64, 0u64);
+    for i in order {
+        let frame = &mut frames[i];
+        if frame.width == 0 || frame.height == 0 {
+            continue;
+        }
+        let (width, height) = (frame.width as u64, frame.height as u64);
+        if x > 0 && x + width > options.max_width as u64 {
+            x = 0;
+            y += row_height + options.spacing as u64;
+            row_height = 0;
+        }
+        atlas_width = atlas_width.max(x + width);
+        atlas_height = atlas_height.max(y + height);
+        if atlas_width > MAX_SIZE as u64 || atlas_height > MAX_SIZE as u64 {
+            return Err(AnimationExportError::TooLargeImageError {
+                width: atlas_width.min(u32::MAX as u64) as u32,
+                height: atlas_height.min(u32::MAX as u64) as u32,
+            });
+        }
+        frame.x = x as u32;
+        frame.y = y as u32;
+        x += width + options.spacing as u64;
+        row_height = row_height.max(height);
+    }
+    let (atlas_width, atlas_height) = (atlas_width as u32, atlas_height as u32);
+    check_size(atlas_width, atlas_height)?;
+
+    let mut image = RgbaImage {
+        width: atlas_width,
+        height: atlas_height,
+        pixels: vec![0; atlas_width as usize * atlas_height as usize * 4],
+    };
+    for (frame, atlas_frame) in animation_frames.iter().zip(&frames) {
+        if atlas_frame.width == 0 || atlas_frame.height == 0 {
+            continue;
+        }
+        let cell = render_bones(
+            &frame.bones,
+            sprites,
+            &RenderOptions {
+                width: atlas_frame.width,
+                height: atlas_frame.height,
+                origin: atlas_frame.pivot,
+                scale,
+                background: [0; 4],
+            },
+        )?;
+        let row_len = cell.width as usize * 4;
+        for row in 0..cell.height as usize {
+            let from = row * row_len;
+            let to = ((atlas_frame.y as usize + row) * atlas_width as usize
+                + atlas_frame.x as usize)
+                * 4;
+            image.pixels[to..to + row_len].copy_from_slice(&cell.pixels[from..from + row_len]);
+        }
+    }
+    Ok(Atlas { image, frames })
+}
+
+impl Atlas {
+    /// Describes the atlas as JSON, for tools that read the image named `image_name`.
+    pub fn to_json(&self, image_name: &str) -> String {
+        let mut out = String::from("{\"image\":");
+        json::write_string(&mut out, image_name);
+        w!(
+            out,
+            ",\"width\":{},\"height\":{},\"frames\":[",
+            self.image.width,
+            self.image.height
+        );
+        for (i, frame) in self.frames.iter().enumerate() {
+            out.push_str(if i == 0 { "\n  {" } else { ",\n  {" });
+            w!(
+                out,
+                "\"frame\":{i},\"x\":{},\"y\":{},\"width\":{},\"height\":{},\"pivot\":",
+                frame.x,
+                frame.y,
+                frame.width,
+                frame.height
+            );
+            write_point(&mut out, Some(frame.pivot));
+            out.push_str(",\"fire_socket\":");
+            write_point(&mut out, frame.fire_socket);
+            out.push('}');
+        }
+        out.push_str(if self.frames.is_empty() {
+            "]}\n"
+        } else {
+            "\n]}\n"
+        });
+        out
+    }
+
+    /// Writes the image as a PNG.
+    #[cfg(feature = "png")]
+    pub fn write_png<W: std::io::Write>(&self, writer: W) -> Result<(), png::EncodingError> {
+        let mut encoder = png::Encoder::new(writer, self.image.width, self.image.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&self.image.pixels)?;
+        writer.finish()
+    }
+}
+
+/// JSON has no NaN or infinity, so a point with one is written as `null`.
+fn write_point(out: &mut String, point: Option<(f64, f64)>) {
+    match point {
+        Some((x, y)) if x.is_finite() && y.is_finite() => {
+            w!(out, "{{\"x\":{x},\"y\":{y}}}");
+        }
+        _ => out.push_str("null"),
+    }
+}
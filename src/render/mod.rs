@@ -0,0 +1,217 @@
+//! Rendering frames to images on the CPU.
+//!
+//! What each bone looks like comes from a `SpriteSource`. `Placeholders` draws a colored quad
+//! per bone, and, with the `swf` feature, `swf::SwfSprites` draws the real sprites from a class's SWF file.
+//!
+//! The `animated` module renders whole animations, and writes them as GIF or APNG,
+//! and the `atlas` module packs the frames of an animation into a sprite sheet.
+
+pub mod animated;
+pub mod atlas;
+mod raster;
+#[cfg(feature = "swf")]
+pub mod swf;
+
+use crate::{Affine2, AnmBone, AnmFrame};
+use raster::Canvas;
+use std::collections::HashMap;
+use thiserror::Error;
+
+/// The largest width or height of a rendered image.
+pub const MAX_SIZE: u32 = 1 << 14;
+/// The most pixels a single render can have. Rendering takes about 20 bytes per pixel.
+pub const MAX_PIXELS: u64 = 1 << 24;
+
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq)]
+#[error("image size {width}x{height} is too large")]
+pub struct TooLargeImageError {
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Fails if an image of this size can't be rendered within `MAX_SIZE` and `MAX_PIXELS`.
+pub(crate) fn check_size(width: u32, height: u32) -> Result<(), TooLargeImageError> {
+    let pixels = width as u64 * height as u64;
+    if width > MAX_SIZE || height > MAX_SIZE || pixels > MAX_PIXELS {
+        return Err(TooLargeImageError { width, height });
+    }
+    Ok(())
+}
+
+/// An image with straight (not premultiplied) RGBA pixels, row by row from the top.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RgbaImage {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+impl RgbaImage {
+    /// Panics if the pixel is out of bounds.
+    pub fn pixel(&self, x: u32, y: u32) -> [u8; 4] {
+        assert!(x < self.width && y < self.height, "pixel out of bounds");
+        let i = (y as usize * self.width as usize + x as usize) * 4;
+        [
+            self.pixels[i],
+            self.pixels[i + 1],
+            self.pixels[i + 2],
+            self.pixels[i + 3],
+        ]
+    }
+}
+
+/// A filled area of a sprite.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FillPath {
+    /// Closed outlines, as line segments in any order. Filled with the nonzero rule.
+    pub segments: Vec<[(f64, f64); 2]>,
+    /// Straight RGBA, from 0 to 1.
+    pub color: [f32; 4],
+}
+
+impl FillPath {
+    /// A rectangle from `(x, y)` with a size of `(w, h)`.
+    pub fn rect(x: f64, y: f64, w: f64, h: f64, color: [f32; 4]) -> Self {
+        let corners = [(x, y), (x + w, y), (x + w, y + h), (x, y + h)];
+        Self {
+            segments: (0..4).map(|i| [corners[i], corners[(i + 1) % 4]]).collect(),
+            color,
+        }
+    }
+}
+
+/// Provides what a bone looks like.
+pub trait SpriteSource {
+    /// The fills of the sprite for a bone id and sprite frame, back to front,
+    /// in the bone's own coordinates. Unknown sprites are empty.
+    fn sprite(&self, id: i16, frame: i8) -> Vec<FillPath>;
+}
+
+/// Draws each bone as a translucent quad centered on its origin, colored by its id.
+#[derive(Clone, Debug)]
+pub struct Placeholders {
+    pub sizes: HashMap<i16, (f64, f64)>,
+    pub default_size: (f64, f64),
+}
+
+impl Default for Placeholders {
+    fn default() -> Self {
+        Self {
+            sizes: HashMap::new(),
+            default_size: (40., 40.),
+        }
+    }
+}
+
+impl SpriteSource for Placeholders {
+    fn sprite(&self, id: i16, _frame: i8) -> Vec<FillPath> {
+        let (w, h) = self.sizes.get(&id).copied().unwrap_or(self.default_size);
+        let hue = (id as i32 * 47).rem_euclid(360) as f32;
+        let [r, g, b] = hsl_to_rgb(hue, 0.7, 0.45);
+        vec![FillPath::rect(-w / 2., -h / 2., w, h, [r, g, b, 0.6])]
+    }
+}
+
+fn hsl_to_rgb(hue: f32, saturation: f32, lightness: f32) -> [f32; 3] {
+    let chroma = (1. - (2. * lightness - 1.).abs()) * saturation;
+    let h = hue / 60.;
+    let x = chroma * (1. - (h % 2. - 1.).abs());
+    let (r, g, b) = match h as u32 {
+        0 => (chroma, x, 0.),
+        1 => (x, chroma, 0.),
+        2 => (0., chroma, x),
+        3 => (0., x, chroma),
+        4 => (x, 0., chroma),
+        _ => (chroma, 0., x),
+    };
+    let m = lightness - chroma / 2.;
+    [r + m, g + m, b + m]
+}
+
+/// Where and how big to draw.
+#[derive(Clone, Debug)]
+pub struct RenderOptions {
+    pub width: u32,
+    pub height: u32,
+    /// Where the frame's `(0, 0)` lands, in pixels.
+    pub origin: (f64, f64),
+    pub scale: f64,
+    /// Straight RGBA.
+    pub background: [u8; 4],
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        Self {
+            width: 256,
+            height: 256,
+            origin: (128., 128.),
+            scale: 1.,
+            background: [0; 4],
+        }
+    }
+}
+
+impl RenderOptions {
+    /// From frame coordinates to pixels.
+    pub fn view(&self) -> Affine2 {
+        Affine2::translation(self.origin.0, self.origin.1) * Affine2::scale(self.scale, self.scale)
+    }
+}
+
+/// Draws the bones of a frame in order, so later bones are on top, faded by their opacity.
+///
+/// Fails if the size in `options` is larger than `MAX_SIZE` or `MAX_PIXELS` allow.
+pub fn render_frame(
+    frame: &AnmFrame,
+    sprites: &impl SpriteSource,
+    options: &RenderOptions,
+) -> Result<RgbaImage, TooLargeImageError> {
+    render_bones(&frame.bones, sprites, options)
+}
+
+/// Like `render_frame`, for any list of bones, such as one from `AnmAnimation::sample`.
+pub fn render_bones(
+    bones: &[AnmBone],
+    sprites: &impl SpriteSource,
+    options: &RenderOptions,
+) -> Result<RgbaImage, TooLargeImageError> {
+    check_size(options.width, options.height)?;
+    let mut canvas = Canvas::new(options.width, options.height, options.background);
+    let view = options.view();
+    for bone in bones {
+        let transform = view * bone.transform();
+        let opacity = bone.opacity.clamp(0., 1.) as f32;
+        for fill in sprites.sprite(bone.id, bone.frame) {
+            for [a, b] in &fill.segments {
+                canvas.line(transform.transform_point(*a), transform.transform_point(*b));
+            }
+            let [r, g, b, a] = fill.color;
+            let alpha = (a * opacity).clamp(0., 1.);
+            canvas.fill([r * alpha, g * alpha, b * alpha, alpha]);
+        }
+    }
+    Ok(canvas.into_image())
+}
+
+/// The smallest rectangle `(min_x, min_y, max_x, max_y)` holding every sprite of the bones,
+/// in frame coordinates. `None` if nothing is drawn.
+pub fn bounds(bones: &[AnmBone], sprites: &impl SpriteSource) -> Option<(f64, f64, f64, f64)> {
+    let mut result: Option<(f64, f64, f64, f64)> = None;
+    for bone in bones {
+        let transform = bone.transform();
+        for fill in sprites.sprite(bone.id, bone.frame) {
+            for (x, y) in fill.segments.iter().flatten() {
+                let (x, y) = transform.transform_point((*x, *y));
+                if !x.is_finite() || !y.is_finite() {
+                    continue;
+                }
+                result = Some(match result {
+                    None => (x, y, x, y),
+                    Some((x0, y0, x1, y1)) => (x0.min(x), y0.min(y), x1.max(x), y1.max(y)),
+                });
+            }
+        }
+    }
+    result
+}
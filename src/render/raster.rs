@@ -0,0 +1,174 @@
+//! A coverage accumulation rasterizer: each segment adds signed area to the cells it crosses,
+//! and a running sum along each row gives the winding, anti-aliased.
+
+use super::RgbaImage;
+
+pub(super) struct Canvas {
+    width: usize,
+    height: usize,
+    /// Premultiplied RGBA.
+    pixels: Vec<[f32; 4]>,
+    /// One extra column on each row, for segments on the right edge.
+    accumulation: Vec<f32>,
+    touched: Option<(usize, usize)>,
+}
+
+impl Canvas {
+    pub(super) fn new(width: u32, height: u32, background: [u8; 4]) -> Self {
+        let (width, height) = (width as usize, height as usize);
+        let alpha = background[3] as f32 / 255.;
+        let background = [
+            background[0] as f32 / 255. * alpha,
+            background[1] as f32 / 255. * alpha,
+            background[2] as f32 / 255. * alpha,
+            alpha,
+        ];
+        Self {
+            width,
+            height,
+            pixels: vec![background; width * height],
+            accumulation: vec![0.; (width + 2) * height],
+            touched: None,
+        }
+    }
+
+    fn stride(&self) -> usize {
+        self.width + 2
+    }
+
+    /// Adds a segment of an outline, in pixels.
+    pub(super) fn line(&mut self, p0: (f64, f64), p1: (f64, f64)) {
+        let all_finite = [p0.0, p0.1, p1.0, p1.1].iter().all(|v| v.is_finite());
+        if !all_finite || self.width == 0 || self.height == 0 {
+            return;
+        }
+        // split at the left and right edges, so every piece can be clamped into the canvas
+        let right = self.width as f64;
+        let mut cuts = [0., 1., 1., 1.];
+        let mut count = 1;
+        for edge in [0., right] {
+            let t = (edge - p0.0) / (p1.0 - p0.0);
+            if t > 0. && t < 1. {
+                cuts[count] = t;
+                count += 1;
+            }
+        }
+        cuts[count] = 1.;
+        cuts[..=count].sort_by(f64::total_cmp);
+
+        let at = |t: f64| (p0.0 + (p1.0 - p0.0) * t, p0.1 + (p1.1 - p0.1) * t);
+        for pair in cuts[..=count].windows(2) {
+            let (mut a, mut b) = (at(pair[0]), at(pair[1]));
+            a.0 = a.0.clamp(0., right);
+            b.0 = b.0.clamp(0., right);
+            self.clipped_line(a, b);
+        }
+    }
+
+    fn clipped_line(&mut self, p0: (f64, f64), p1: (f64, f64)) {
+        if p0.1 == p1.1 {
+            return;
+        }
+        let (dir, p0, p1) = if p0.1 < p1.1 {
+            (1., p0, p1)
+        } else {
+            (-1., p1, p0)
+        };
+        if p1.1 <= 0. || p0.1 >= self.height as f64 {
+            return;
+        }
+        let dxdy = (p1.0 - p0.0) / (p1.1 - p0.1);
+        let mut x = p0.0;
+        if p0.1 < 0. {
+            x = (x - p0.1 * dxdy).clamp(0., self.width as f64);
+        }
+        let y_start = p0.1.max(0.) as usize;
+        let y_end = (p1.1.ceil() as usize).min(self.height);
+        self.touched = Some(match self.touched {
+            None => (y_start, y_end),
+            Some((a, b)) => (a.min(y_start), b.max(y_end)),
+        });
+
+        let stride = self.stride();
+        let max_x = self.width as f64;
+        for y in y_start..y_end {
+            let row = &mut self.accumulation[y * stride..(y + 1) * stride];
+            let dy = ((y + 1) as f64).min(p1.1) - (y as f64).max(p0.1);
+            let x_next = (x + dxdy * dy).clamp(0., max_x);
+            let d = (dy * dir) as f32;
+            let (x0, x1) = if x < x_next { (x, x_next) } else { (x_next, x) };
+            let x0_floor = x0.floor();
+            let x0i = x0_floor as usize;
+            let x1_ceil = x1.ceil();
+            let x1i = x1_ceil as usize;
+
+            if x1i <= x0i + 1 {
+                // the segment stays within one pixel on this row
+                let mid = (0.5 * (x + x_next) - x0_floor) as f32;
+                row[x0i] += d - d * mid;
+                row[x0i + 1] += d * mid;
+            } else {
+                let s = (x1 - x0).recip() as f32;
+                let x0f = (x0 - x0_floor) as f32;
+                let a0 = 0.5 * s * (1. - x0f) * (1. - x0f);
+                let x1f = (x1 - x1_ceil + 1.) as f32;
+                let am = 0.5 * s * x1f * x1f;
+                row[x0i] += d * a0;
+                if x1i == x0i + 2 {
+                    row[x0i + 1] += d * (1. - a0 - am);
+                } else {
+                    let a1 = s * (1.5 - x0f);
+                    row[x0i + 1] += d * (a1 - a0);
+                    for cell in &mut row[x0i + 2..x1i - 1] {
+                        *cell += d * s;
+                    }
+                    let a2 = a1 + (x1i - x0i - 3) as f32 * s;
+                    row[x1i - 1] += d * (1. - a2 - am);
+                }
+                row[x1i] += d * am;
+            }
+            x = x_next;
+        }
+    }
+
+    /// Paints the outlines added since the last fill with a premultiplied color,
+    /// using the nonzero rule, and clears them.
+    pub(super) fn fill(&mut self, color: [f32; 4]) {
+        let Some((y_start, y_end)) = self.touched.take() else {
+            return;
+        };
+        let stride = self.stride();
+        for y in y_start..y_end {
+            let row = &mut self.accumulation[y * stride..(y + 1) * stride];
+            let pixels = &mut self.pixels[y * self.width..(y + 1) * self.width];
+            let mut winding = 0f32;
+            for (cell, pixel) in row.iter_mut().zip(pixels) {
+                winding += *cell;
+                *cell = 0.;
+                let coverage = winding.abs().min(1.);
+                if coverage > 0. {
+                    let keep = 1. - color[3] * coverage;
+                    for (channel, source) in pixel.iter_mut().zip(color) {
+                        *channel = source * coverage + *channel * keep;
+                    }
+                }
+            }
+            row[self.width..].fill(0.);
+        }
+    }
+
+    pub(super) fn into_image(self) -> RgbaImage {
+        let mut pixels = Vec::with_capacity(self.pixels.len() * 4);
+        for [r, g, b, a] in self.pixels {
+            let unpremultiply = if a > 0. { a.recip() } else { 0. };
+            for channel in [r * unpremultiply, g * unpremultiply, b * unpremultiply, a] {
+                pixels.push((channel.clamp(0., 1.) * 255.).round() as u8);
+            }
+        }
+        RgbaImage {
+            width: self.width as u32,
+            height: self.height as u32,
+            pixels,
+        }
+    }
+}
@@ -0,0 +1,796 @@
+//! Sprites from the SWF files that `AnmClass::file_name` refers to.
+//!
+//! Only what the game's sprites need is supported: shapes with solid fills, sprites and their timelines,
+//! and exported symbol names. Gradients are drawn with the average of their colors,
+//! bitmap fills are skipped, strokes are drawn without joins or caps, and masks are ignored.
+//! Sprites nested inside a bone's sprite show their first frame.
+
+use super::{FillPath, SpriteSource};
+use crate::{Affine2, AnmClass};
+use flate2::read::ZlibDecoder;
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    io::{self, Read},
+    path::{Component, Path},
+};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum SwfError {
+    #[error(transparent)]
+    IOError(#[from] io::Error),
+    #[error("not a swf file")]
+    InvalidSignatureError(),
+    #[error("lzma compressed swf files are not supported")]
+    UnsupportedCompressionError(),
+    #[error("unexpected end of data in tag {tag}")]
+    UnexpectedEndError { tag: u16 },
+    #[error("decompressed size doesn't match the {expected} bytes in the header")]
+    DecompressedSizeError { expected: u64 },
+    #[error("SWF file name is not a relative path inside the directory: ({file_name:?})")]
+    InvalidFileNameError { file_name: String },
+}
+
+/// The shapes, sprites and symbol names of a SWF file.
+pub struct SwfFile {
+    shapes: HashMap<u16, Vec<FillPath>>,
+    /// The display list of each frame, by depth.
+    sprites: HashMap<u16, Vec<Vec<Placement>>>,
+    symbols: HashMap<String, u16>,
+}
+
+#[derive(Clone, Copy)]
+struct Placement {
+    character: u16,
+    matrix: Affine2,
+    color: ColorTransform,
+    is_mask: bool,
+}
+
+#[derive(Clone, Copy)]
+struct ColorTransform {
+    mult: [f32; 4],
+    /// From 0 to 1.
+    add: [f32; 4],
+}
+
+impl ColorTransform {
+    const IDENTITY: Self = Self {
+        mult: [1.; 4],
+        add: [0.; 4],
+    };
+
+    fn apply(&self, color: [f32; 4]) -> [f32; 4] {
+        let mut result = [0.; 4];
+        for i in 0..4 {
+            result[i] = (color[i] * self.mult[i] + self.add[i]).clamp(0., 1.);
+        }
+        result
+    }
+
+    /// `inner` applied first, then `self`.
+    fn then(&self, inner: &Self) -> Self {
+        let mut result = Self::IDENTITY;
+        for i in 0..4 {
+            result.mult[i] = inner.mult[i] * self.mult[i];
+            result.add[i] = inner.add[i] * self.mult[i] + self.add[i];
+        }
+        result
+    }
+}
+
+const TAG_END: u16 = 0;
+const TAG_SHOW_FRAME: u16 = 1;
+const TAG_DEFINE_SHAPE: u16 = 2;
+const TAG_REMOVE_OBJECT: u16 = 5;
+const TAG_DEFINE_SHAPE2: u16 = 22;
+const TAG_PLACE_OBJECT2: u16 = 26;
+const TAG_REMOVE_OBJECT2: u16 = 28;
+const TAG_DEFINE_SHAPE3: u16 = 32;
+const TAG_DEFINE_SPRITE: u16 = 39;
+const TAG_EXPORT_ASSETS: u16 = 56;
+const TAG_PLACE_OBJECT3: u16 = 70;
+const TAG_SYMBOL_CLASS: u16 = 76;
+const TAG_DEFINE_SHAPE4: u16 = 83;
+
+/// Nested sprites deeper than this are not drawn, in case a file refers to itself.
+const MAX_DEPTH: usize = 32;
+
+/// The most placements and path segments drawn for one character. Sprites that place
+/// the same child many times, at many levels, would otherwise take exponential time.
+/// Whatever is left when it runs out is not drawn.
+const MAX_WORK: usize = 1 << 22;
+
+impl SwfFile {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, SwfError> {
+        Self::parse(&fs::read(path)?)
+    }
+
+    /// Opens the SWF file of a class, found in `dir`.
+    /// The class's file name must be a relative path that stays inside `dir`.
+    pub fn open_for_class<P: AsRef<Path>>(dir: P, class: &AnmClass) -> Result<Self, SwfError> {
+        let path = Path::new(&class.file_name);
+        let inside = path.components().next().is_some()
+            && path
+                .components()
+                .all(|component| matches!(component, Component::Normal(_)));
+        if !inside {
+            return Err(SwfError::InvalidFileNameError {
+                file_name: class.file_name.clone(),
+            });
+        }
+        Self::open(dir.as_ref().join(path))
+    }
+
+    pub fn parse(bytes: &[u8]) -> Result<Self, SwfError> {
+        if bytes.len() < 8 {
+            return Err(SwfError::InvalidSignatureError());
+        }
+        let body = match &bytes[..3] {
+            b"FWS" => bytes[8..].to_vec(),
+            b"CWS" => {
+                // the header's length includes the 8 uncompressed bytes
+                let file_length = u32::from_le_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]);
+                let expected = (file_length as u64).saturating_sub(8);
+                let mut body = Vec::new();
+                // one byte past the end tells a longer body apart
+                ZlibDecoder::new(&bytes[8..])
+                    .take(expected + 1)
+                    .read_to_end(&mut body)?;
+                if body.len() as u64 != expected {
+                    return Err(SwfError::DecompressedSizeError { expected });
+                }
+                body
+            }
+            b"ZWS" => return Err(SwfError::UnsupportedCompressionError()),
+            _ => return Err(SwfError::InvalidSignatureError()),
+        };
+
+        let mut reader = Bits::new(&body, 0);
+        reader.rect()?; // frame size
+        reader.u16()?; // frame rate
+        reader.u16()?; // frame count
+
+        let mut file = Self {
+            shapes: HashMap::new(),
+            sprites: HashMap::new(),
+            symbols: HashMap::new(),
+        };
+        for tag in Tags(reader) {
+            let (code, data) = tag?;
+            let mut reader = Bits::new(data, code);
+            match code {
+                TAG_DEFINE_SHAPE | TAG_DEFINE_SHAPE2 | TAG_DEFINE_SHAPE3 | TAG_DEFINE_SHAPE4 => {
+                    let id = reader.u16()?;
+                    file.shapes.insert(id, read_shape(&mut reader, code)?);
+                }
+                TAG_DEFINE_SPRITE => {
+                    let id = reader.u16()?;
+                    reader.u16()?; // frame count
+                    file.sprites.insert(id, read_timeline(reader)?);
+                }
+                TAG_SYMBOL_CLASS | TAG_EXPORT_ASSETS => {
+                    for _ in 0..reader.u16()? {
+                        let id = reader.u16()?;
+                        let name = reader.string()?;
+                        file.symbols.insert(name, id);
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(file)
+    }
+
+    /// The character id exported under a name.
+    pub fn symbol(&self, name: &str) -> Option<u16> {
+        self.symbols.get(name).copied()
+    }
+
+    pub fn symbol_names(&self) -> impl Iterator<Item = &str> {
+        self.symbols.keys().map(String::as_str)
+    }
+
+    /// The number of frames of a sprite. Shapes have 1 frame.
+    pub fn frame_count(&self, character: u16) -> usize {
+        match self.sprites.get(&character) {
+            Some(frames) => frames.len(),
+            None => usize::from(self.shapes.contains_key(&character)),
+        }
+    }
+
+    /// The fills of a character at a frame, counted from 0, in the character's coordinates.
+    pub fn character(&self, character: u16, frame: usize) -> Vec<FillPath> {
+        let mut drawing = Drawing {
+            fills: Vec::new(),
+            work: MAX_WORK,
+        };
+        self.draw(
+            character,
+            frame,
+            Affine2::IDENTITY,
+            ColorTransform::IDENTITY,
+            0,
+            &mut drawing,
+        );
+        drawing.fills
+    }
+
+    fn draw(
+        &self,
+        character: u16,
+        frame: usize,
+        transform: Affine2,
+        color: ColorTransform,
+        depth: usize,
+        drawing: &mut Drawing,
+    ) {
+        if depth > MAX_DEPTH || drawing.work == 0 {
+            return;
+        }
+        drawing.work -= 1;
+        if let Some(fills) = self.shapes.get(&character) {
+            for fill in fills {
+                let Some(left) = drawing.work.checked_sub(fill.segments.len()) else {
+                    drawing.work = 0;
+                    return;
+                };
+                drawing.work = left;
+                drawing.fills.push(FillPath {
+                    segments: fill
+                        .segments
+                        .iter()
+                        .map(|[a, b]| {
+                            [transform.transform_point(*a), transform.transform_point(*b)]
+                        })
+                        .collect(),
+                    color: color.apply(fill.color),
+                });
+            }
+        } else if let Some(frames) = self.sprites.get(&character) {
+            let Some(placements) = frames.get(frame.min(frames.len().saturating_sub(1))) else {
+                return;
+            };
+            for placement in placements.iter().filter(|p| !p.is_mask) {
+                self.draw(
+                    placement.character,
+                    0,
+                    transform * placement.matrix,
+                    color.then(&placement.color),
+                    depth + 1,
+                    drawing,
+                );
+            }
+        }
+    }
+}
+
+/// The fills drawn so far, and the work left before `MAX_WORK` runs out.
+struct Drawing {
+    fills: Vec<FillPath>,
+    work: usize,
+}
+
+/// Draws bones with the sprites of a SWF file.
+///
+/// The anm file only has bone ids, so the symbol name of each id has to be provided,
+/// typically from the game's bone type list. A bone's sprite `frame` counts from 1.
+pub struct SwfSprites {
+    pub swf: SwfFile,
+    pub bone_names: HashMap<i16, String>,
+}
+
+impl SwfSprites {
+    pub fn new(swf: SwfFile, bone_names: HashMap<i16, String>) -> Self {
+        Self { swf, bone_names }
+    }
+}
+
+impl SpriteSource for SwfSprites {
+    fn sprite(&self, id: i16, frame: i8) -> Vec<FillPath> {
+        let Some(character) = self
+            .bone_names
+            .get(&id)
+            .and_then(|name| self.swf.symbol(name))
+        else {
+            return Vec::new();
+        };
+        let frame = (frame.max(1) - 1) as usize;
+        self.swf.character(character, frame)
+    }
+}
+
+struct Tags<'a>(Bits<'a>);
+
+impl<'a> Iterator for Tags<'a> {
+    type Item = Result<(u16, &'a [u8]), SwfError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.0.is_at_end() {
+            return None;
+        }
+        let result = (|| {
+            let header = self.0.u16()?;
+            let code = header >> 6;
+            let mut length = (header & 0x3f) as usize;
+            if length == 0x3f {
+                length = self.0.u32()? as usize;
+            }
+            self.0.tag = code;
+            Ok((code, self.0.bytes(length)?))
+        })();
+        match result {
+            Ok((TAG_END, _)) => None,
+            Ok(tag) => Some(Ok(tag)),
+            Err(e) => {
+                self.0.data = &[];
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+/// Replays the control tags of a sprite into a display list per frame.
+fn read_timeline(reader: Bits) -> Result<Vec<Vec<Placement>>, SwfError> {
+    let mut frames = Vec::new();
+    let mut display: BTreeMap<u16, Placement> = BTreeMap::new();
+    for tag in Tags(reader) {
+        let (code, data) = tag?;
+        let mut reader = Bits::new(data, code);
+        match code {
+            TAG_SHOW_FRAME => frames.push(display.values().copied().collect()),
+            TAG_PLACE_OBJECT2 | TAG_PLACE_OBJECT3 => {
+                let flags = reader.u8()?;
+                let flags3 = if code == TAG_PLACE_OBJECT3 {
+                    reader.u8()?
+                } else {
+                    0
+                };
+                let is_move = flags & 0x01 != 0;
+                let has_character = flags & 0x02 != 0;
+                let has_matrix = flags & 0x04 != 0;
+                let has_color = flags & 0x08 != 0;
+                let has_ratio = flags & 0x10 != 0;
+                let has_name = flags & 0x20 != 0;
+                let has_clip_depth = flags & 0x40 != 0;
+                let has_class_name = flags3 & 0x08 != 0;
+                let has_image = flags3 & 0x10 != 0;
+
+                let depth = reader.u16()?;
+                if has_class_name || (has_image && has_character) {
+                    reader.string()?;
+                }
+                let character = has_character.then(|| reader.u16()).transpose()?;
+                let matrix = has_matrix.then(|| reader.matrix()).transpose()?;
+                let color = has_color.then(|| reader.color_transform()).transpose()?;
+                if has_ratio {
+                    reader.u16()?;
+                }
+                if has_name {
+                    reader.string()?;
+                }
+                let is_mask = has_clip_depth;
+
+                let existing = display.get(&depth).copied().filter(|_| is_move);
+                let placement = match (existing, character) {
+                    (Some(existing), character) => Placement {
+                        character: character.unwrap_or(existing.character),
+                        matrix: matrix.unwrap_or(existing.matrix),
+                        color: color.unwrap_or(existing.color),
+                        is_mask: existing.is_mask || is_mask,
+                    },
+                    (None, Some(character)) => Placement {
+                        character,
+                        matrix: matrix.unwrap_or(Affine2::IDENTITY),
+                        color: color.unwrap_or(ColorTransform::IDENTITY),
+                        is_mask,
+                    },
+                    (None, None) => continue,
+                };
+                display.insert(depth, placement);
+            }
+            TAG_REMOVE_OBJECT => {
+                reader.u16()?;
+                display.remove(&reader.u16()?);
+            }
+            TAG_REMOVE_OBJECT2 => {
+                display.remove(&reader.u16()?);
+            }
+            _ => {}
+        }
+    }
+    Ok(frames)
+}
+
+struct Styles {
+    fills: Vec<[f32; 4]>,
+    /// Color and width.
+    lines: Vec<([f32; 4], f64)>,
+    fill_bits: u32,
+    line_bits: u32,
+}
+
+/// Reads a shape into fills, in pixels. Edges are collected per fill style,
+/// with the edges that have the style on their left reversed, so every outline winds the same way.
+fn read_shape(reader: &mut Bits, code: u16) -> Result<Vec<FillPath>, SwfError> {
+    reader.rect()?; // bounds
+    if code == TAG_DEFINE_SHAPE4 {
+        reader.rect()?; // edge bounds
+        reader.u8()?; // flags
+    }
+
+    let mut result = Vec::new();
+    let mut styles = read_styles(reader, code)?;
+    let mut fills: Vec<Vec<[(f64, f64); 2]>> = vec![Vec::new(); styles.fills.len()];
+    let mut lines: Vec<Vec<[(f64, f64); 2]>> = vec![Vec::new(); styles.lines.len()];
+    let (mut fill0, mut fill1, mut line) = (0usize, 0usize, 0usize);
+    let (mut x, mut y) = (0i32, 0i32);
+
+    let flush = |styles: &Styles,
+                 fills: &mut Vec<Vec<[(f64, f64); 2]>>,
+                 lines: &mut Vec<Vec<[(f64, f64); 2]>>,
+                 result: &mut Vec<FillPath>| {
+        for (segments, color) in fills.drain(..).zip(&styles.fills) {
+            if !segments.is_empty() {
+                result.push(FillPath {
+                    segments,
+                    color: *color,
+                });
+            }
+        }
+        for (segments, (color, width)) in lines.drain(..).zip(&styles.lines) {
+            if !segments.is_empty() {
+                result.push(FillPath {
+                    segments: stroke(&segments, *width),
+                    color: *color,
+                });
+            }
+        }
+    };
+
+    loop {
+        let is_edge = reader.bits(1)? != 0;
+        let mut edge = |from: (i32, i32), points: &[(f64, f64)]| {
+            let start = twips(from);
+            let mut prev = start;
+            for &point in points {
+                let segment = [prev, point];
+                // style indices count from 1, 0 meaning no style
+                if let Some(fill) = fill1.checked_sub(1).and_then(|i| fills.get_mut(i)) {
+                    fill.push(segment);
+                }
+                if let Some(fill) = fill0.checked_sub(1).and_then(|i| fills.get_mut(i)) {
+                    fill.push([segment[1], segment[0]]);
+                }
+                if let Some(line) = line.checked_sub(1).and_then(|i| lines.get_mut(i)) {
+                    line.push(segment);
+                }
+                prev = point;
+            }
+        };
+
+        if !is_edge {
+            let flags = reader.bits(5)?;
+            if flags == 0 {
+                break;
+            }
+            if flags & 0x01 != 0 {
+                let n = reader.bits(5)?;
+                x = reader.sbits(n)?;
+                y = reader.sbits(n)?;
+            }
+            if flags & 0x02 != 0 {
+                fill0 = reader.bits(styles.fill_bits)? as usize;
+            }
+            if flags & 0x04 != 0 {
+                fill1 = reader.bits(styles.fill_bits)? as usize;
+            }
+            if flags & 0x08 != 0 {
+                line = reader.bits(styles.line_bits)? as usize;
+            }
+            if flags & 0x10 != 0 {
+                flush(&styles, &mut fills, &mut lines, &mut result);
+                styles = read_styles(reader, code)?;
+                fills = vec![Vec::new(); styles.fills.len()];
+                lines = vec![Vec::new(); styles.lines.len()];
+            }
+        } else if reader.bits(1)? != 0 {
+            // straight edge
+            let n = reader.bits(4)? + 2;
+            let (dx, dy) = if reader.bits(1)? != 0 {
+                (reader.sbits(n)?, reader.sbits(n)?)
+            } else if reader.bits(1)? != 0 {
+                (0, reader.sbits(n)?)
+            } else {
+                (reader.sbits(n)?, 0)
+            };
+            let from = (x, y);
+            x += dx;
+            y += dy;
+            edge(from, &[twips((x, y))]);
+        } else {
+            // quadratic curve
+            let n = reader.bits(4)? + 2;
+            let control = (x + reader.sbits(n)?, y + reader.sbits(n)?);
+            let anchor = (control.0 + reader.sbits(n)?, control.1 + reader.sbits(n)?);
+            let from = (x, y);
+            (x, y) = anchor;
+            edge(from, &flatten(twips(from), twips(control), twips(anchor)));
+        }
+    }
+    flush(&styles, &mut fills, &mut lines, &mut result);
+    Ok(result)
+}
+
+fn twips((x, y): (i32, i32)) -> (f64, f64) {
+    (x as f64 / 20., y as f64 / 20.)
+}
+
+/// Splits a quadratic curve into line segments, returning the points after the start.
+fn flatten(p0: (f64, f64), c: (f64, f64), p1: (f64, f64)) -> Vec<(f64, f64)> {
+    let deviation = (p0.0 - 2. * c.0 + p1.0).hypot(p0.1 - 2. * c.1 + p1.1);
+    let count = ((deviation.sqrt() * 2.).ceil() as usize).clamp(2, 16);
+    (1..=count)
+        .map(|i| {
+            let t = i as f64 / count as f64;
+            let u = 1. - t;
+            (
+                u * u * p0.0 + 2. * u * t * c.0 + t * t * p1.0,
+                u * u * p0.1 + 2. * u * t * c.1 + t * t * p1.1,
+            )
+        })
+        .collect()
+}
+
+/// Turns each segment of a stroke into a thin quad.
+fn stroke(segments: &[[(f64, f64); 2]], width: f64) -> Vec<[(f64, f64); 2]> {
+    let half = width.max(0.05) / 2.;
+    let mut result = Vec::with_capacity(segments.len() * 4);
+    for [a, b] in segments {
+        let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+        let length = dx.hypot(dy);
+        if length == 0. {
+            continue;
+        }
+        let (nx, ny) = (-dy / length * half, dx / length * half);
+        let corners = [
+            (a.0 + nx, a.1 + ny),
+            (b.0 + nx, b.1 + ny),
+            (b.0 - nx, b.1 - ny),
+            (a.0 - nx, a.1 - ny),
+        ];
+        for i in 0..4 {
+            result.push([corners[i], corners[(i + 1) % 4]]);
+        }
+    }
+    result
+}
+
+fn read_styles(reader: &mut Bits, code: u16) -> Result<Styles, SwfError> {
+    let has_alpha = code == TAG_DEFINE_SHAPE3 || code == TAG_DEFINE_SHAPE4;
+    let extended_counts = code != TAG_DEFINE_SHAPE;
+    reader.align();
+
+    let mut count = reader.u8()? as usize;
+    if count == 0xff && extended_counts {
+        count = reader.u16()? as usize;
+    }
+    let mut fills = Vec::with_capacity(count);
+    for _ in 0..count {
+        fills.push(read_fill_style(reader, has_alpha)?);
+    }
+
+    let mut count = reader.u8()? as usize;
+    if count == 0xff && extended_counts {
+        count = reader.u16()? as usize;
+    }
+    let mut lines = Vec::with_capacity(count);
+    for _ in 0..count {
+        let width = reader.u16()? as f64 / 20.;
+        let color = if code == TAG_DEFINE_SHAPE4 {
+            let flags = reader.u16()?;
+            let join_style = (flags >> 4) & 0b11;
+            let has_fill = flags & 0x08 != 0;
+            if join_style == 2 {
+                reader.u16()?; // miter limit
+            }
+            if has_fill {
+                read_fill_style(reader, true)?
+            } else {
+                reader.color(true)?
+            }
+        } else {
+            reader.color(has_alpha)?
+        };
+        lines.push((color, width));
+    }
+
+    let fill_bits = reader.bits(4)?;
+    let line_bits = reader.bits(4)?;
+    Ok(Styles {
+        fills,
+        lines,
+        fill_bits,
+        line_bits,
+    })
+}
+
+fn read_fill_style(reader: &mut Bits, has_alpha: bool) -> Result<[f32; 4], SwfError> {
+    match reader.u8()? {
+        0x00 => reader.color(has_alpha),
+        kind @ (0x10 | 0x12 | 0x13) => {
+            reader.matrix()?;
+            let count = (reader.u8()? & 0x0f) as usize;
+            let mut sum = [0.; 4];
+            for _ in 0..count {
+                reader.u8()?; // ratio
+                let color = reader.color(has_alpha)?;
+                for (total, channel) in sum.iter_mut().zip(color) {
+                    *total += channel;
+                }
+            }
+            if kind == 0x13 {
+                reader.u16()?; // focal point
+            }
+            Ok(sum.map(|total| total / count.max(1) as f32))
+        }
+        _ => {
+            // bitmap fill
+            reader.u16()?;
+            reader.matrix()?;
+            Ok([0.; 4])
+        }
+    }
+}
+
+/// Reads bit fields most significant bit first, and byte fields little endian.
+struct Bits<'a> {
+    data: &'a [u8],
+    bit: u32,
+    /// For errors.
+    tag: u16,
+}
+
+impl<'a> Bits<'a> {
+    fn new(data: &'a [u8], tag: u16) -> Self {
+        Self { data, bit: 0, tag }
+    }
+
+    fn is_at_end(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    fn end_error(&self) -> SwfError {
+        SwfError::UnexpectedEndError { tag: self.tag }
+    }
+
+    fn align(&mut self) {
+        if self.bit != 0 {
+            self.bit = 0;
+            self.data = &self.data[1..];
+        }
+    }
+
+    fn bytes(&mut self, length: usize) -> Result<&'a [u8], SwfError> {
+        self.align();
+        if self.data.len() < length {
+            return Err(self.end_error());
+        }
+        let (bytes, rest) = self.data.split_at(length);
+        self.data = rest;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, SwfError> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, SwfError> {
+        let bytes = self.bytes(2)?;
+        Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u32(&mut self) -> Result<u32, SwfError> {
+        let bytes = self.bytes(4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn string(&mut self) -> Result<String, SwfError> {
+        self.align();
+        let length = self
+            .data
+            .iter()
+            .position(|&b| b == 0)
+            .ok_or_else(|| self.end_error())?;
+        let string = String::from_utf8_lossy(&self.data[..length]).into_owned();
+        self.data = &self.data[length + 1..];
+        Ok(string)
+    }
+
+    fn bits(&mut self, count: u32) -> Result<u32, SwfError> {
+        let mut result = 0u32;
+        for _ in 0..count {
+            let byte = *self.data.first().ok_or_else(|| self.end_error())?;
+            let bit = (byte >> (7 - self.bit)) & 1;
+            result = (result << 1) | bit as u32;
+            self.bit += 1;
+            if self.bit == 8 {
+                self.bit = 0;
+                self.data = &self.data[1..];
+            }
+        }
+        Ok(result)
+    }
+
+    fn sbits(&mut self, count: u32) -> Result<i32, SwfError> {
+        let value = self.bits(count)?;
+        if count == 0 {
+            return Ok(0);
+        }
+        let shift = 32 - count;
+        Ok(((value << shift) as i32) >> shift)
+    }
+
+    /// A 16.16 fixed point number.
+    fn fixed_bits(&mut self, count: u32) -> Result<f64, SwfError> {
+        Ok(self.sbits(count)? as f64 / 65536.)
+    }
+
+    fn rect(&mut self) -> Result<(), SwfError> {
+        self.align();
+        let n = self.bits(5)?;
+        self.bits(n * 4)?;
+        self.align();
+        Ok(())
+    }
+
+    fn color(&mut self, has_alpha: bool) -> Result<[f32; 4], SwfError> {
+        let r = self.u8()?;
+        let g = self.u8()?;
+        let b = self.u8()?;
+        let a = if has_alpha { self.u8()? } else { 255 };
+        Ok([r, g, b, a].map(|c| c as f32 / 255.))
+    }
+
+    /// Translation is converted from twips to pixels.
+    fn matrix(&mut self) -> Result<Affine2, SwfError> {
+        self.align();
+        let mut matrix = Affine2::IDENTITY;
+        if self.bits(1)? != 0 {
+            let n = self.bits(5)?;
+            matrix.a = self.fixed_bits(n)?;
+            matrix.d = self.fixed_bits(n)?;
+        }
+        if self.bits(1)? != 0 {
+            let n = self.bits(5)?;
+            matrix.b = self.fixed_bits(n)?;
+            matrix.c = self.fixed_bits(n)?;
+        }
+        let n = self.bits(5)?;
+        matrix.tx = self.sbits(n)? as f64 / 20.;
+        matrix.ty = self.sbits(n)? as f64 / 20.;
+        self.align();
+        Ok(matrix)
+    }
+
+    fn color_transform(&mut self) -> Result<ColorTransform, SwfError> {
+        self.align();
+        let has_add = self.bits(1)? != 0;
+        let has_mult = self.bits(1)? != 0;
+        let n = self.bits(4)?;
+        let mut transform = ColorTransform::IDENTITY;
+        if has_mult {
+            for mult in &mut transform.mult {
+                *mult = self.sbits(n)? as f32 / 256.;
+            }
+        }
+        if has_add {
+            for add in &mut transform.add {
+                *add = self.sbits(n)? as f32 / 255.;
+            }
+        }
+        self.align();
+        Ok(transform)
+    }
+}
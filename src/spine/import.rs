@@ -0,0 +1,708 @@
+use super::{SpineTransform, mirror};
+use crate::{Affine2, AnmAnimation, AnmBone, AnmFrame};
+use serde_json::Value;
+use std::collections::HashMap;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum SpineImportError {
+    #[error(transparent)]
+    JsonError(#[from] serde_json::Error),
+    #[error("Field is missing or has the wrong type: ({field:?})")]
+    InvalidFieldError { field: String },
+    #[error("Bone is not defined before it is used: ({name:?})")]
+    UnknownBoneError { name: String },
+    #[error("Slot is not defined: ({name:?})")]
+    UnknownSlotError { name: String },
+    #[error("Attachment name is not <bone>_<frame> with a known bone: ({name:?})")]
+    UnknownAttachmentError { name: String },
+    #[error("Frame rate is not a positive number: ({fps:?})")]
+    InvalidFpsError { fps: f64 },
+    #[error("Animation is longer than the {MAX_FRAMES} frames frame ids can number: ({name:?})")]
+    TooManyFramesError { name: String, duration: f64 },
+}
+
+/// Frames are numbered by their `i16` id, from 0.
+const MAX_FRAMES: usize = i16::MAX as usize + 1;
+
+/// How to import a skeleton.
+#[derive(Clone, Debug)]
+pub struct SpineImportOptions {
+    /// The frame rate to sample the animations at.
+    pub fps: f64,
+    /// Bone ids for the names in attachment names. Other names must be a bone id.
+    pub bone_ids: HashMap<String, i16>,
+}
+
+impl Default for SpineImportOptions {
+    fn default() -> Self {
+        Self {
+            fps: 24.,
+            bone_ids: HashMap::new(),
+        }
+    }
+}
+
+/// Reads every animation of a Spine skeleton, in file order.
+///
+/// Only the parts of the skeleton that the anm format has are read: bones with their default
+/// inheritance, slots with their alpha, region and mesh attachments with their offsets,
+/// and bone, attachment, alpha and draw order timelines, with their curves from Spine 3 or 4.
+/// Mesh deformation, constraints and events are ignored.
+pub fn from_json(
+    text: &str,
+    options: &SpineImportOptions,
+) -> Result<Vec<AnmAnimation>, SpineImportError> {
+    let root: Value = serde_json::from_str(text)?;
+    if !(options.fps > 0. && options.fps.is_finite()) {
+        return Err(SpineImportError::InvalidFpsError { fps: options.fps });
+    }
+    let skeleton = Skeleton::parse(&root, options)?;
+
+    let mut animations = Vec::new();
+    let Some(entries) = root.get("animations") else {
+        return Ok(animations);
+    };
+    let entries = entries.as_object().ok_or_else(|| invalid("animations"))?;
+    for (name, value) in entries {
+        let timelines = Timelines::parse(value, &skeleton, &format!("animations.{name}"))?;
+        let duration = timelines.duration();
+        // the duration and frame rate are finite, so this is too, or infinite
+        let frame_count = (duration * options.fps).round() + 1.;
+        if frame_count > MAX_FRAMES as f64 {
+            return Err(SpineImportError::TooManyFramesError {
+                name: name.clone(),
+                duration,
+            });
+        }
+        let frame_count = frame_count as usize;
+        let mut frames = Vec::with_capacity(frame_count);
+        for i in 0..frame_count {
+            let bones = skeleton.pose(&timelines, i as f64 / options.fps)?;
+            frames.push(AnmFrame {
+                id: i as i16,
+                bones,
+                fire_socket: None,
+                eb_platform_pos: None,
+            });
+        }
+        animations.push(AnmAnimation::new(name.clone(), frames));
+    }
+    Ok(animations)
+}
+
+fn invalid(field: &str) -> SpineImportError {
+    SpineImportError::InvalidFieldError {
+        field: field.to_string(),
+    }
+}
+
+/// A number field, or `default` if it is missing.
+fn number(value: &Value, owner: &str, key: &str, default: f64) -> Result<f64, SpineImportError> {
+    match value.get(key) {
+        None => Ok(default),
+        Some(v) => v.as_f64().ok_or_else(|| invalid(&format!("{owner}.{key}"))),
+    }
+}
+
+/// A string field, or `None` if it is missing or null.
+fn string<'a>(
+    value: &'a Value,
+    owner: &str,
+    key: &str,
+) -> Result<Option<&'a str>, SpineImportError> {
+    match value.get(key) {
+        None | Some(Value::Null) => Ok(None),
+        Some(v) => v
+            .as_str()
+            .map(Some)
+            .ok_or_else(|| invalid(&format!("{owner}.{key}"))),
+    }
+}
+
+/// The alpha of an `RRGGBBAA` color, which is opaque without the alpha digits.
+fn color_alpha(color: &str, field: &str) -> Result<f64, SpineImportError> {
+    match color.get(6..8) {
+        None if color.len() == 6 => Ok(1.),
+        Some(alpha) if color.len() == 8 => u8::from_str_radix(alpha, 16)
+            .map(|alpha| alpha as f64 / 255.)
+            .map_err(|_| invalid(field)),
+        _ => Err(invalid(field)),
+    }
+}
+
+struct SetupBone {
+    parent: Option<usize>,
+    local: SpineTransform,
+}
+
+struct SetupSlot {
+    bone: usize,
+    attachment: Option<String>,
+    alpha: f64,
+}
+
+/// What an attachment draws: the name of its sprite, placed by `transform` in its bone.
+/// `None` for attachments that don't draw, like points and bounding boxes.
+type Attachment = Option<(String, Affine2)>;
+
+struct Skeleton<'a> {
+    options: &'a SpineImportOptions,
+    bones: Vec<SetupBone>,
+    bone_indices: HashMap<String, usize>,
+    slots: Vec<SetupSlot>,
+    slot_indices: HashMap<String, usize>,
+    attachments: HashMap<(usize, String), Attachment>,
+    /// The major version of Spine that wrote the file, which changes how curves are written.
+    version: u32,
+}
+
+impl<'a> Skeleton<'a> {
+    fn parse(root: &Value, options: &'a SpineImportOptions) -> Result<Self, SpineImportError> {
+        let mut skeleton = Skeleton {
+            options,
+            bones: Vec::new(),
+            bone_indices: HashMap::new(),
+            slots: Vec::new(),
+            slot_indices: HashMap::new(),
+            attachments: HashMap::new(),
+            version: version(root),
+        };
+
+        let bones = root.get("bones").and_then(Value::as_array);
+        for bone in bones.into_iter().flatten() {
+            let name = string(bone, "bones", "name")?.ok_or_else(|| invalid("bones.name"))?;
+            let owner = format!("bones.{name}");
+            let parent = match string(bone, &owner, "parent")? {
+                Some(parent) => Some(skeleton.bone_index(parent)?),
+                None => None,
+            };
+            let local = SpineTransform {
+                x: number(bone, &owner, "x", 0.)?,
+                y: number(bone, &owner, "y", 0.)?,
+                rotation: number(bone, &owner, "rotation", 0.)?,
+                scale_x: number(bone, &owner, "scaleX", 1.)?,
+                scale_y: number(bone, &owner, "scaleY", 1.)?,
+                shear_x: number(bone, &owner, "shearX", 0.)?,
+                shear_y: number(bone, &owner, "shearY", 0.)?,
+            };
+            skeleton
+                .bone_indices
+                .insert(name.to_string(), skeleton.bones.len());
+            skeleton.bones.push(SetupBone { parent, local });
+        }
+
+        let slots = root.get("slots").and_then(Value::as_array);
+        for slot in slots.into_iter().flatten() {
+            let name = string(slot, "slots", "name")?.ok_or_else(|| invalid("slots.name"))?;
+            let owner = format!("slots.{name}");
+            let bone =
+                string(slot, &owner, "bone")?.ok_or_else(|| invalid(&format!("{owner}.bone")))?;
+            let alpha = match string(slot, &owner, "color")? {
+                Some(color) => color_alpha(color, &format!("{owner}.color"))?,
+                None => 1.,
+            };
+            let setup = SetupSlot {
+                bone: skeleton.bone_index(bone)?,
+                attachment: string(slot, &owner, "attachment")?.map(str::to_string),
+                alpha,
+            };
+            skeleton
+                .slot_indices
+                .insert(name.to_string(), skeleton.slots.len());
+            skeleton.slots.push(setup);
+        }
+
+        // Spine 4 has an array of skins, and older versions an object keyed by skin name
+        let skin = match root.get("skins") {
+            Some(Value::Array(skins)) => skins
+                .iter()
+                .find(|skin| skin.get("name").and_then(Value::as_str) == Some("default"))
+                .or(skins.first())
+                .and_then(|skin| skin.get("attachments")),
+            Some(skins @ Value::Object(_)) => skins.get("default"),
+            _ => None,
+        };
+        if let Some(skin) = skin {
+            let skin = skin
+                .as_object()
+                .ok_or_else(|| invalid("skins.attachments"))?;
+            for (slot_name, attachments) in skin {
+                let slot = skeleton.slot_index(slot_name)?;
+                let attachments = attachments
+                    .as_object()
+                    .ok_or_else(|| invalid(&format!("skins.{slot_name}")))?;
+                for (name, attachment) in attachments {
+                    let owner = format!("skins.{slot_name}.{name}");
+                    let drawn = match string(attachment, &owner, "type")?.unwrap_or("region") {
+                        "region" => {
+                            let offset = SpineTransform {
+                                x: number(attachment, &owner, "x", 0.)?,
+                                y: number(attachment, &owner, "y", 0.)?,
+                                rotation: number(attachment, &owner, "rotation", 0.)?,
+                                scale_x: number(attachment, &owner, "scaleX", 1.)?,
+                                scale_y: number(attachment, &owner, "scaleY", 1.)?,
+                                ..Default::default()
+                            };
+                            Some(offset.matrix())
+                        }
+                        // mesh vertices are already in the bone's coordinates
+                        "mesh" | "linkedmesh" => Some(Affine2::IDENTITY),
+                        _ => None,
+                    };
+                    let sprite = match string(attachment, &owner, "path")? {
+                        Some(path) => path,
+                        None => string(attachment, &owner, "name")?.unwrap_or(name),
+                    };
+                    skeleton.attachments.insert(
+                        (slot, name.clone()),
+                        drawn.map(|offset| (sprite.to_string(), offset)),
+                    );
+                }
+            }
+        }
+        Ok(skeleton)
+    }
+
+    fn bone_index(&self, name: &str) -> Result<usize, SpineImportError> {
+        self.bone_indices
+            .get(name)
+            .copied()
+            .ok_or_else(|| SpineImportError::UnknownBoneError {
+                name: name.to_string(),
+            })
+    }
+
+    fn slot_index(&self, name: &str) -> Result<usize, SpineImportError> {
+        self.slot_indices
+            .get(name)
+            .copied()
+            .ok_or_else(|| SpineImportError::UnknownSlotError {
+                name: name.to_string(),
+            })
+    }
+
+    /// The bone id and sprite frame of an attachment named `<bone>_<frame>`.
+    fn sprite_of(&self, name: &str) -> Result<(i16, i8), SpineImportError> {
+        let parsed = name.rsplit_once('_').and_then(|(bone, frame)| {
+            let id = match self.options.bone_ids.get(bone) {
+                Some(&id) => id,
+                None => bone.parse().ok()?,
+            };
+            Some((id, frame.parse().ok()?))
+        });
+        parsed.ok_or_else(|| SpineImportError::UnknownAttachmentError {
+            name: name.to_string(),
+        })
+    }
+
+    /// The visible attachments at a time, as bones in drawing order.
+    fn pose(&self, timelines: &Timelines, time: f64) -> Result<Vec<AnmBone>, SpineImportError> {
+        let mut locals: Vec<SpineTransform> = self.bones.iter().map(|bone| bone.local).collect();
+        for timeline in &timelines.bones {
+            let Some(values) = sample(&timeline.keys, time) else {
+                continue;
+            };
+            let setup = &self.bones[timeline.bone].local;
+            let local = &mut locals[timeline.bone];
+            for (channel, value) in timeline.channels.iter().zip(values) {
+                match channel {
+                    Channel::Rotate => local.rotation = setup.rotation + value,
+                    Channel::X => local.x = setup.x + value,
+                    Channel::Y => local.y = setup.y + value,
+                    Channel::ScaleX => local.scale_x = setup.scale_x * value,
+                    Channel::ScaleY => local.scale_y = setup.scale_y * value,
+                    Channel::ShearX => local.shear_x = setup.shear_x + value,
+                    Channel::ShearY => local.shear_y = setup.shear_y + value,
+                }
+            }
+        }
+        // parents come before their children, so their world transforms are ready
+        let mut world: Vec<Affine2> = Vec::with_capacity(self.bones.len());
+        for (bone, local) in self.bones.iter().zip(&locals) {
+            let parent = bone
+                .parent
+                .map_or(Affine2::IDENTITY, |parent| world[parent]);
+            world.push(parent * local.matrix());
+        }
+
+        let order = timelines
+            .draw_order
+            .iter()
+            .rev()
+            .find(|(key_time, _)| *key_time <= time)
+            .map(|(_, order)| order.clone())
+            .unwrap_or_else(|| (0..self.slots.len()).collect());
+
+        let mut bones = Vec::new();
+        for slot in order {
+            let setup = &self.slots[slot];
+            let slot_timelines = &timelines.slots[slot];
+            let attachment = match slot_timelines
+                .attachment
+                .iter()
+                .rev()
+                .find(|(key_time, _)| *key_time <= time)
+            {
+                Some((_, attachment)) => attachment.as_deref(),
+                None => setup.attachment.as_deref(),
+            };
+            let Some(attachment) = attachment else {
+                continue;
+            };
+            let (sprite, offset) = match self.attachments.get(&(slot, attachment.to_string())) {
+                Some(Some((sprite, offset))) => (sprite.as_str(), *offset),
+                Some(None) => continue,
+                // not in the skin, so take the timeline's name as is
+                None => (attachment, Affine2::IDENTITY),
+            };
+            let (id, frame) = self.sprite_of(sprite)?;
+            let alpha = match sample(&slot_timelines.alpha, time) {
+                Some(values) => values[0],
+                None => setup.alpha,
+            };
+            let mut bone = AnmBone {
+                id,
+                scale_x: 1.,
+                rotate_skew0: 0.,
+                rotate_skew1: 0.,
+                scale_y: 1.,
+                x: 0.,
+                y: 0.,
+                opacity: alpha.clamp(0., 1.),
+                frame,
+                encoding: None,
+            };
+            bone.set_transform(mirror(world[setup.bone] * offset));
+            bones.push(bone);
+        }
+        Ok(bones)
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Channel {
+    Rotate,
+    X,
+    Y,
+    ScaleX,
+    ScaleY,
+    ShearX,
+    ShearY,
+}
+
+enum Curve {
+    Linear,
+    Stepped,
+    /// Two control points, `(time, value)`, per channel.
+    Bezier(Vec<f64>),
+    /// Two control points for all the channels, with the time and value going from 0 to 1
+    /// between the keys.
+    Normalized([f64; 4]),
+}
+
+struct Key {
+    time: f64,
+    values: Vec<f64>,
+    /// How to get to the next key.
+    curve: Curve,
+}
+
+struct BoneTimeline {
+    bone: usize,
+    channels: Vec<Channel>,
+    keys: Vec<Key>,
+}
+
+#[derive(Default)]
+struct SlotTimelines {
+    attachment: Vec<(f64, Option<String>)>,
+    alpha: Vec<Key>,
+}
+
+struct Timelines {
+    bones: Vec<BoneTimeline>,
+    slots: Vec<SlotTimelines>,
+    /// The slot indices in drawing order, from each key on.
+    draw_order: Vec<(f64, Vec<usize>)>,
+}
+
+impl Timelines {
+    fn parse(
+        animation: &Value,
+        skeleton: &Skeleton,
+        owner: &str,
+    ) -> Result<Self, SpineImportError> {
+        let mut timelines = Timelines {
+            bones: Vec::new(),
+            slots: (0..skeleton.slots.len())
+                .map(|_| SlotTimelines::default())
+                .collect(),
+            draw_order: Vec::new(),
+        };
+
+        let bones = animation.get("bones").and_then(Value::as_object);
+        for (bone_name, bone_timelines) in bones.into_iter().flatten() {
+            let bone = skeleton.bone_index(bone_name)?;
+            for (kind, keys) in bone_timelines.as_object().into_iter().flatten() {
+                let field = format!("{owner}.bones.{bone_name}.{kind}");
+                let (channels, fields): (&[Channel], &[(&str, f64)]) = match kind.as_str() {
+                    "rotate" => (&[Channel::Rotate], &[("value", 0.)]),
+                    "translate" => (&[Channel::X, Channel::Y], &[("x", 0.), ("y", 0.)]),
+                    "translatex" => (&[Channel::X], &[("value", 0.)]),
+                    "translatey" => (&[Channel::Y], &[("value", 0.)]),
+                    "scale" => (&[Channel::ScaleX, Channel::ScaleY], &[("x", 1.), ("y", 1.)]),
+                    "scalex" => (&[Channel::ScaleX], &[("value", 1.)]),
+                    "scaley" => (&[Channel::ScaleY], &[("value", 1.)]),
+                    "shear" => (&[Channel::ShearX, Channel::ShearY], &[("x", 0.), ("y", 0.)]),
+                    "shearx" => (&[Channel::ShearX], &[("value", 0.)]),
+                    "sheary" => (&[Channel::ShearY], &[("value", 0.)]),
+                    _ => continue,
+                };
+                let keys = parse_keys(keys, &field, skeleton.version, |key| {
+                    fields
+                        .iter()
+                        .map(|&(name, default)| match name {
+                            // older versions call the rotation an angle
+                            "value" if key.get("value").is_none() => {
+                                number(key, &field, "angle", default)
+                            }
+                            _ => number(key, &field, name, default),
+                        })
+                        .collect()
+                })?;
+                timelines.bones.push(BoneTimeline {
+                    bone,
+                    channels: channels.to_vec(),
+                    keys,
+                });
+            }
+        }
+
+        let slots = animation.get("slots").and_then(Value::as_object);
+        for (slot_name, slot_timelines) in slots.into_iter().flatten() {
+            let slot = skeleton.slot_index(slot_name)?;
+            let tracks = &mut timelines.slots[slot];
+            for (kind, keys) in slot_timelines.as_object().into_iter().flatten() {
+                let field = format!("{owner}.slots.{slot_name}.{kind}");
+                match kind.as_str() {
+                    "attachment" => {
+                        for key in keys.as_array().ok_or_else(|| invalid(&field))? {
+                            let time = number(key, &field, "time", 0.)?;
+                            let name = string(key, &field, "name")?.map(str::to_string);
+                            tracks.attachment.push((time, name));
+                        }
+                    }
+                    // the alpha is the fourth channel of the color
+                    "rgba" | "color" => {
+                        let mut keys = parse_keys(keys, &field, skeleton.version, |key| {
+                            let color = string(key, &field, "color")?
+                                .ok_or_else(|| invalid(&format!("{field}.color")))?;
+                            Ok(vec![color_alpha(color, &format!("{field}.color"))?])
+                        })?;
+                        for key in &mut keys {
+                            if let Curve::Bezier(points) = &mut key.curve {
+                                points.drain(..points.len().min(12));
+                            }
+                        }
+                        tracks.alpha = keys;
+                    }
+                    "alpha" => {
+                        tracks.alpha = parse_keys(keys, &field, skeleton.version, |key| {
+                            Ok(vec![number(key, &field, "value", 1.)?])
+                        })?;
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        let draw_order = animation
+            .get("drawOrder")
+            .or_else(|| animation.get("draworder"));
+        if let Some(keys) = draw_order {
+            let field = format!("{owner}.drawOrder");
+            for key in keys.as_array().ok_or_else(|| invalid(&field))? {
+                let time = number(key, &field, "time", 0.)?;
+                let offsets = key.get("offsets").and_then(Value::as_array);
+                let offsets = offsets.map(Vec::as_slice).unwrap_or_default();
+                let order = apply_offsets(skeleton, offsets, &field)?;
+                timelines.draw_order.push((time, order));
+            }
+        }
+        Ok(timelines)
+    }
+
+    /// The time of the last key.
+    fn duration(&self) -> f64 {
+        let bone_keys = self.bones.iter().flat_map(|t| &t.keys).map(|k| k.time);
+        let slot_keys = self.slots.iter().flat_map(|t| {
+            let attachments = t.attachment.iter().map(|&(time, _)| time);
+            attachments.chain(t.alpha.iter().map(|k| k.time))
+        });
+        let draw_order_keys = self.draw_order.iter().map(|&(time, _)| time);
+        bone_keys
+            .chain(slot_keys)
+            .chain(draw_order_keys)
+            .filter(|time| time.is_finite())
+            .fold(0., f64::max)
+    }
+}
+
+/// Reads keys with their time and curve, and their values with `values`.
+fn parse_keys(
+    keys: &Value,
+    field: &str,
+    version: u32,
+    values: impl Fn(&Value) -> Result<Vec<f64>, SpineImportError>,
+) -> Result<Vec<Key>, SpineImportError> {
+    let keys = keys.as_array().ok_or_else(|| invalid(field))?;
+    keys.iter()
+        .map(|key| {
+            Ok(Key {
+                time: number(key, field, "time", 0.)?,
+                values: values(key)?,
+                curve: parse_curve(key, field, version)?,
+            })
+        })
+        .collect()
+}
+
+/// The major version in the skeleton's `spine` field, or 4 if there is none.
+fn version(root: &Value) -> u32 {
+    let version = root.get("skeleton").and_then(|s| s.get("spine"));
+    let major = version
+        .and_then(Value::as_str)
+        .and_then(|v| v.split('.').next())
+        .and_then(|major| major.parse().ok());
+    major.unwrap_or(4)
+}
+
+/// Spine 4 writes a curve per channel, as an array of times and values.
+/// Spine 3 writes one curve for all the channels, going from 0 to 1 between the keys:
+/// up to 3.7 as an array of four numbers, and in 3.8 as the number `curve` followed by `c2`, `c3` and `c4`.
+fn parse_curve(key: &Value, field: &str, version: u32) -> Result<Curve, SpineImportError> {
+    let field = format!("{field}.curve");
+    let points = match key.get("curve") {
+        None => return Ok(Curve::Linear),
+        Some(Value::String(s)) if s == "stepped" => return Ok(Curve::Stepped),
+        Some(Value::Number(cx1)) => {
+            let cx1 = cx1.as_f64().ok_or_else(|| invalid(&field))?;
+            return Ok(Curve::Normalized([
+                cx1,
+                number(key, &field, "c2", 0.)?,
+                number(key, &field, "c3", 1.)?,
+                number(key, &field, "c4", 1.)?,
+            ]));
+        }
+        Some(Value::Array(points)) => points,
+        Some(_) => return Err(invalid(&field)),
+    };
+    let points = points
+        .iter()
+        .map(|p| p.as_f64().ok_or_else(|| invalid(&field)))
+        .collect::<Result<Vec<_>, _>>()?;
+    if version >= 4 {
+        return Ok(Curve::Bezier(points));
+    }
+    match points[..] {
+        [cx1, cy1, cx2, cy2] => Ok(Curve::Normalized([cx1, cy1, cx2, cy2])),
+        _ => Err(invalid(&field)),
+    }
+}
+
+/// The values at a time, or `None` before the first key, where the setup pose applies.
+fn sample(keys: &[Key], time: f64) -> Option<Vec<f64>> {
+    let index = keys.iter().rposition(|key| key.time <= time)?;
+    let key = &keys[index];
+    let Some(next) = keys.get(index + 1) else {
+        return Some(key.values.clone());
+    };
+    let span = next.time - key.time;
+    if span <= 0. {
+        return Some(next.values.clone());
+    }
+    let amount = (time - key.time) / span;
+    let values = key
+        .values
+        .iter()
+        .zip(&next.values)
+        .enumerate()
+        .map(|(channel, (&from, &to))| match &key.curve {
+            Curve::Stepped => from,
+            Curve::Bezier(points) if points.len() >= channel * 4 + 4 => {
+                let p = &points[channel * 4..channel * 4 + 4];
+                bezier(
+                    (key.time, from),
+                    (p[0], p[1]),
+                    (p[2], p[3]),
+                    (next.time, to),
+                    time,
+                )
+            }
+            Curve::Normalized(p) => {
+                let eased = bezier((0., 0.), (p[0], p[1]), (p[2], p[3]), (1., 1.), amount);
+                from + (to - from) * eased
+            }
+            _ => from + (to - from) * amount,
+        })
+        .collect();
+    Some(values)
+}
+
+/// The value of a cubic Bézier curve of `(time, value)` points at a time,
+/// found by bisecting, since the time along the curve is monotonic in Spine.
+fn bezier(p0: (f64, f64), p1: (f64, f64), p2: (f64, f64), p3: (f64, f64), time: f64) -> f64 {
+    let at = |t: f64, a: f64, b: f64, c: f64, d: f64| {
+        let u = 1. - t;
+        u * u * u * a + 3. * u * u * t * b + 3. * u * t * t * c + t * t * t * d
+    };
+    let (mut low, mut high) = (0., 1.);
+    for _ in 0..40 {
+        let mid = 0.5 * (low + high);
+        if at(mid, p0.0, p1.0, p2.0, p3.0) < time {
+            low = mid;
+        } else {
+            high = mid;
+        }
+    }
+    at(0.5 * (low + high), p0.1, p1.1, p2.1, p3.1)
+}
+
+/// Spine's draw order keys move some slots by an offset from their setup position,
+/// and keep the others in their setup order in the positions that are left.
+fn apply_offsets(
+    skeleton: &Skeleton,
+    offsets: &[Value],
+    field: &str,
+) -> Result<Vec<usize>, SpineImportError> {
+    let count = skeleton.slots.len();
+    let mut order: Vec<Option<usize>> = vec![None; count];
+    let mut unchanged = Vec::new();
+    let mut original = 0;
+    for offset in offsets {
+        let name = string(offset, field, "slot")?.ok_or_else(|| invalid(field))?;
+        let slot = skeleton.slot_index(name)?;
+        if slot < original {
+            // offsets are sorted by slot
+            return Err(invalid(field));
+        }
+        unchanged.extend(original..slot);
+        original = slot + 1;
+        let target = slot as f64 + number(offset, field, "offset", 0.)?;
+        if !(0. ..count as f64).contains(&target) || target.fract() != 0. {
+            return Err(invalid(field));
+        }
+        match &mut order[target as usize] {
+            position @ None => *position = Some(slot),
+            Some(_) => return Err(invalid(field)),
+        }
+    }
+    unchanged.extend(original..count);
+    for position in order.iter_mut().rev() {
+        if position.is_none() {
+            *position = unchanged.pop();
+        }
+    }
+    Ok(order.into_iter().flatten().collect())
+}
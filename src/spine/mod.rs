@@ -0,0 +1,467 @@
+//! Spine skeleton JSON export, for studying and remixing animations in Spine,
+//! and import, for authoring animations there.
+//!
+//! Each bone id becomes a Spine bone with a slot of the same name, attached to the root.
+//! A bone id that is on a frame more than once gets a slot per copy, named with `#2`, `#3`...
+//! Names that are taken, by another id or by the root bone, get a `~2`, `~3`... suffix.
+//! The sprite frames of a bone become region attachments named `<bone>_<frame>`,
+//! sized like the placeholders of `svg` and `render`, so the images can be swapped in.
+//!
+//! Spine's y axis points up, so positions and matrices are mirrored vertically.
+//!
+//! Importing, with the `spine-import` feature, samples each animation at a fixed frame rate.
+//! Every visible attachment becomes an `AnmBone` with the world transform of its bone,
+//! so the bone hierarchy is flattened. Attachment names (or paths) are read back as `<bone>_<frame>`.
+
+use crate::{Affine2, AnmBone, AnmClass, AnmFrame, AnmReadingError, json};
+use std::collections::{HashMap, HashSet};
+
+#[cfg(feature = "spine-import")]
+mod import;
+
+#[cfg(feature = "spine-import")]
+pub use import::{SpineImportError, SpineImportOptions, from_json};
+
+const SPINE_VERSION: &str = "4.1";
+
+/// How to export a class.
+#[derive(Clone, Debug)]
+pub struct SpineOptions {
+    /// The frame rate the animations are authored at, to turn frames into seconds.
+    pub fps: f64,
+    /// Names for bone ids, such as the sprite names from the class's SWF file.
+    /// Other bones are named by their id.
+    pub bone_names: HashMap<i16, String>,
+    /// The width and height of the attachments of each bone id.
+    pub sizes: HashMap<i16, (f64, f64)>,
+    /// The size of bones that are not in `sizes`.
+    pub default_size: (f64, f64),
+    /// Whether keys hold until the next one, like the game's frames,
+    /// rather than being interpolated.
+    pub stepped: bool,
+}
+
+impl Default for SpineOptions {
+    fn default() -> Self {
+        Self {
+            fps: 24.,
+            bone_names: HashMap::new(),
+            sizes: HashMap::new(),
+            default_size: (40., 40.),
+            stepped: true,
+        }
+    }
+}
+
+impl SpineOptions {
+    fn name_of(&self, id: i16) -> String {
+        self.bone_names
+            .get(&id)
+            .cloned()
+            .unwrap_or_else(|| id.to_string())
+    }
+}
+
+/// The transform of a bone in Spine's terms, in degrees, with y up.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SpineTransform {
+    pub x: f64,
+    pub y: f64,
+    pub rotation: f64,
+    pub scale_x: f64,
+    pub scale_y: f64,
+    pub shear_x: f64,
+    pub shear_y: f64,
+}
+
+impl SpineTransform {
+    /// Spine builds a bone's matrix from its rotation and shear angles, with the y axis at
+    /// `rotation + 90 + shear_y` degrees, so the skew of the decomposition becomes a shear
+    /// of the y axis, and a flip becomes a negative `scale_y`.
+    pub fn from_bone(bone: &AnmBone) -> Self {
+        let d = mirror(bone.transform()).decompose();
+        let sy = if d.flip { -d.scale.1 } else { d.scale.1 };
+        Self {
+            x: d.translation.0,
+            y: d.translation.1,
+            rotation: d.rotation.to_degrees(),
+            scale_x: d.scale.0,
+            scale_y: sy / d.skew.cos(),
+            shear_x: 0.,
+            shear_y: -d.skew.to_degrees(),
+        }
+    }
+
+    /// The matrix Spine builds from the transform, with y up.
+    pub fn matrix(&self) -> Affine2 {
+        let x_axis = (self.rotation + self.shear_x).to_radians();
+        let y_axis = (self.rotation + 90. + self.shear_y).to_radians();
+        Affine2::new(
+            x_axis.cos() * self.scale_x,
+            x_axis.sin() * self.scale_x,
+            y_axis.cos() * self.scale_y,
+            y_axis.sin() * self.scale_y,
+            self.x,
+            self.y,
+        )
+    }
+}
+
+impl Default for SpineTransform {
+    fn default() -> Self {
+        Self {
+            x: 0.,
+            y: 0.,
+            rotation: 0.,
+            scale_x: 1.,
+            scale_y: 1.,
+            shear_x: 0.,
+            shear_y: 0.,
+        }
+    }
+}
+
+/// Between y up and y down.
+fn mirror(m: Affine2) -> Affine2 {
+    Affine2::new(m.a, -m.b, -m.c, m.d, m.tx, -m.ty)
+}
+
+/// A slot for a copy of a bone id on a frame: the id, and which copy it is.
+type SlotKey = (i16, usize);
+
+struct Slot {
+    name: String,
+    id: i16,
+    /// Every sprite frame shown in the slot, in the order they appear.
+    frames: Vec<i8>,
+}
+
+/// Converts all the animations of a class into one Spine skeleton.
+pub fn to_json(class: &AnmClass, options: &SpineOptions) -> Result<String, AnmReadingError> {
+    // the slots, in the order they first appear, which is also the setup drawing order
+    let mut slots: Vec<Slot> = Vec::new();
+    let mut slot_indices: HashMap<SlotKey, usize> = HashMap::new();
+    let mut names = UniqueNames::default();
+    for animation in class.animations.iter() {
+        for frame in animation.frames()? {
+            for (bone, key) in frame.bones.iter().zip(frame.bone_copies()) {
+                let index = *slot_indices.entry(key).or_insert_with(|| {
+                    let name = AnmFrame::copy_name(options.name_of(key.0), key.1);
+                    slots.push(Slot {
+                        name: names.pick(name),
+                        id: key.0,
+                        frames: Vec::new(),
+                    });
+                    slots.len() - 1
+                });
+                let slot = &mut slots[index];
+                if !slot.frames.contains(&bone.frame) {
+                    slot.frames.push(bone.frame);
+                }
+            }
+        }
+    }
+
+    let mut out = String::from("{\n\"skeleton\":{\"spine\":");
+    json::write_string(&mut out, SPINE_VERSION);
+    out.push_str(",\"fps\":");
+    write_number(&mut out, options.fps);
+    out.push_str("},\n\"bones\":[\n  {\"name\":\"root\"}");
+    for slot in &slots {
+        out.push_str(",\n  {\"name\":");
+        json::write_string(&mut out, &slot.name);
+        out.push_str(",\"parent\":\"root\"}");
+    }
+    out.push_str("\n],\n\"slots\":[");
+    for (i, slot) in slots.iter().enumerate() {
+        out.push_str(if i == 0 {
+            "\n  {\"name\":"
+        } else {
+            ",\n  {\"name\":"
+        });
+        json::write_string(&mut out, &slot.name);
+        out.push_str(",\"bone\":");
+        json::write_string(&mut out, &slot.name);
+        out.push('}');
+    }
+    out.push_str("\n],\n\"skins\":[{\"name\":\"default\",\"attachments\":{");
+    for (i, slot) in slots.iter().enumerate() {
+        out.push_str(if i == 0 { "\n  " } else { ",\n  " });
+        json::write_string(&mut out, &slot.name);
+        out.push_str(":{");
+        let (width, height) = options
+            .sizes
+            .get(&slot.id)
+            .copied()
+            .unwrap_or(options.default_size);
+        for (j, frame) in slot.frames.iter().enumerate() {
+            if j > 0 {
+                out.push(',');
+            }
+            json::write_string(&mut out, &attachment_name(options, slot.id, *frame));
+            out.push_str(":{\"width\":");
+            write_number(&mut out, width);
+            out.push_str(",\"height\":");
+            write_number(&mut out, height);
+            out.push('}');
+        }
+        out.push('}');
+    }
+    out.push_str("\n}}],\n\"animations\":{");
+
+    for (i, animation) in class.animations.iter().enumerate() {
+        out.push_str(if i == 0 { "\n  " } else { ",\n  " });
+        json::write_string(&mut out, &animation.name);
+        out.push(':');
+        let mut tracks = AnimationTracks::new(slots.len());
+        let frames = animation.frames()?;
+        for (i, frame) in frames.iter().enumerate() {
+            let time = i as f64 / options.fps;
+            let is_last = i + 1 == frames.len();
+            tracks.add_frame(time, frame, is_last, &slot_indices, options);
+        }
+        tracks.write(&mut out, &slots, options.stepped);
+    }
+    out.push_str("\n}\n}\n");
+    Ok(out)
+}
+
+/// Spine finds bones and slots by name, so each slot, and the bone of the same name,
+/// needs a name of its own.
+struct UniqueNames {
+    taken: HashSet<String>,
+}
+
+impl Default for UniqueNames {
+    fn default() -> Self {
+        Self {
+            taken: HashSet::from(["root".to_string()]),
+        }
+    }
+}
+
+impl UniqueNames {
+    fn pick(&mut self, name: String) -> String {
+        let mut candidate = name.clone();
+        let mut counter = 2;
+        while !self.taken.insert(candidate.clone()) {
+            candidate = format!("{name}~{counter}");
+            counter += 1;
+        }
+        candidate
+    }
+}
+
+fn attachment_name(options: &SpineOptions, id: i16, frame: i8) -> String {
+    format!("{}_{frame}", options.name_of(id))
+}
+
+/// The keys of one animation, as `(time, value)` pairs, with a key only where the value changes.
+#[derive(Default)]
+struct SlotTracks {
+    attachment: Vec<(f64, Option<String>)>,
+    alpha: Vec<(f64, f64)>,
+    translate: Vec<(f64, [f64; 2])>,
+    rotate: Vec<(f64, f64)>,
+    scale: Vec<(f64, [f64; 2])>,
+    shear: Vec<(f64, [f64; 2])>,
+}
+
+struct AnimationTracks {
+    slots: Vec<SlotTracks>,
+    /// The slot indices in drawing order, with an offset per slot from the setup order.
+    draw_order: Vec<(f64, Vec<(usize, isize)>)>,
+    last_order: Option<Vec<usize>>,
+}
+
+/// Adds a key if the value is different from the last key's.
+fn push_key<T: PartialEq>(keys: &mut Vec<(f64, T)>, time: f64, value: T) {
+    if keys.last().is_none_or(|(_, last)| *last != value) {
+        keys.push((time, value));
+    }
+}
+
+impl AnimationTracks {
+    fn new(slot_count: usize) -> Self {
+        Self {
+            slots: (0..slot_count).map(|_| SlotTracks::default()).collect(),
+            draw_order: Vec::new(),
+            last_order: None,
+        }
+    }
+
+    fn add_frame(
+        &mut self,
+        time: f64,
+        frame: &AnmFrame,
+        is_last: bool,
+        slot_indices: &HashMap<SlotKey, usize>,
+        options: &SpineOptions,
+    ) {
+        let visible: Vec<usize> = frame
+            .bone_copies()
+            .iter()
+            .map(|key| slot_indices[key])
+            .collect();
+
+        for (index, tracks) in self.slots.iter_mut().enumerate() {
+            let position = visible.iter().position(|&i| i == index);
+            let attachment = position.map(|position| {
+                let bone = &frame.bones[position];
+                attachment_name(options, bone.id, bone.frame)
+            });
+            push_key(&mut tracks.attachment, time, attachment.clone());
+            // the animation ends on its last key, so the last frame is always keyed
+            if is_last && tracks.attachment.last().is_some_and(|&(t, _)| t != time) {
+                tracks.attachment.push((time, attachment));
+            }
+            let Some(position) = position else {
+                continue;
+            };
+            let bone = &frame.bones[position];
+            push_key(&mut tracks.alpha, time, bone.opacity);
+
+            let t = SpineTransform::from_bone(bone);
+            push_key(&mut tracks.translate, time, [t.x, t.y]);
+            // keep rotations continuous, so interpolating them doesn't spin the long way around
+            let rotation = match tracks.rotate.last() {
+                Some(&(_, last)) => last + (t.rotation - last + 180.).rem_euclid(360.) - 180.,
+                None => t.rotation,
+            };
+            push_key(&mut tracks.rotate, time, rotation);
+            push_key(&mut tracks.scale, time, [t.scale_x, t.scale_y]);
+            push_key(&mut tracks.shear, time, [t.shear_x, t.shear_y]);
+        }
+
+        // the visible slots in the frame's order, then the hidden ones in the setup order
+        let mut order = visible.clone();
+        order.extend((0..self.slots.len()).filter(|i| !visible.contains(i)));
+        let unchanged = match &self.last_order {
+            Some(last) => *last == order,
+            None => order.iter().enumerate().all(|(i, &slot)| i == slot),
+        };
+        if !unchanged {
+            // Spine stores how far each slot moves from its setup position
+            let mut offsets: Vec<(usize, isize)> = order
+                .iter()
+                .enumerate()
+                .filter(|&(position, &slot)| position != slot)
+                .map(|(position, &slot)| (slot, position as isize - slot as isize))
+                .collect();
+            offsets.sort_by_key(|&(slot, _)| slot);
+            self.draw_order.push((time, offsets));
+        }
+        self.last_order = Some(order);
+    }
+
+    fn write(&self, out: &mut String, slots: &[Slot], stepped: bool) {
+        out.push_str("{\"slots\":{");
+        let mut first = true;
+        for (slot, tracks) in slots.iter().zip(&self.slots) {
+            if tracks.attachment.is_empty() {
+                continue;
+            }
+            out.push_str(if first { "\n    " } else { ",\n    " });
+            first = false;
+            json::write_string(out, &slot.name);
+            out.push_str(":{\"attachment\":[");
+            for (i, (time, name)) in tracks.attachment.iter().enumerate() {
+                out.push_str(if i == 0 { "{" } else { ",{" });
+                write_time(out, *time);
+                out.push_str(",\"name\":");
+                match name {
+                    Some(name) => json::write_string(out, name),
+                    None => out.push_str("null"),
+                }
+                out.push('}');
+            }
+            out.push(']');
+            if !tracks.alpha.is_empty() {
+                out.push_str(",\"rgba\":[");
+                for (i, (time, alpha)) in tracks.alpha.iter().enumerate() {
+                    out.push_str(if i == 0 { "{" } else { ",{" });
+                    write_time(out, *time);
+                    let alpha = (alpha.clamp(0., 1.) * 255.).round() as u8;
+                    w!(out, ",\"color\":\"ffffff{alpha:02x}\"");
+                    write_curve(out, stepped);
+                    out.push('}');
+                }
+                out.push(']');
+            }
+            out.push('}');
+        }
+
+        out.push_str("},\"bones\":{");
+        let mut first = true;
+        for (slot, tracks) in slots.iter().zip(&self.slots) {
+            if tracks.rotate.is_empty() {
+                continue;
+            }
+            out.push_str(if first { "\n    " } else { ",\n    " });
+            first = false;
+            json::write_string(out, &slot.name);
+            out.push_str(":{\"rotate\":[");
+            for (i, (time, value)) in tracks.rotate.iter().enumerate() {
+                out.push_str(if i == 0 { "{" } else { ",{" });
+                write_time(out, *time);
+                out.push_str(",\"value\":");
+                write_number(out, *value);
+                write_curve(out, stepped);
+                out.push('}');
+            }
+            for (name, keys) in [
+                ("translate", &tracks.translate),
+                ("scale", &tracks.scale),
+                ("shear", &tracks.shear),
+            ] {
+                w!(out, "],\"{name}\":[");
+                for (i, (time, [x, y])) in keys.iter().enumerate() {
+                    out.push_str(if i == 0 { "{" } else { ",{" });
+                    write_time(out, *time);
+                    out.push_str(",\"x\":");
+                    write_number(out, *x);
+                    out.push_str(",\"y\":");
+                    write_number(out, *y);
+                    write_curve(out, stepped);
+                    out.push('}');
+                }
+            }
+            out.push_str("]}");
+        }
+        out.push('}');
+
+        if !self.draw_order.is_empty() {
+            out.push_str(",\"drawOrder\":[");
+            for (i, (time, offsets)) in self.draw_order.iter().enumerate() {
+                out.push_str(if i == 0 { "\n    {" } else { ",\n    {" });
+                write_time(out, *time);
+                out.push_str(",\"offsets\":[");
+                for (j, (slot, offset)) in offsets.iter().enumerate() {
+                    out.push_str(if j == 0 { "{\"slot\":" } else { ",{\"slot\":" });
+                    json::write_string(out, &slots[*slot].name);
+                    w!(out, ",\"offset\":{offset}}}");
+                }
+                out.push_str("]}");
+            }
+            out.push(']');
+        }
+        out.push('}');
+    }
+}
+
+fn write_time(out: &mut String, time: f64) {
+    out.push_str("\"time\":");
+    write_number(out, time);
+}
+
+fn write_curve(out: &mut String, stepped: bool) {
+    if stepped {
+        out.push_str(",\"curve\":\"stepped\"");
+    }
+}
+
+/// JSON has no NaN or infinity, so those are written as 0.
+fn write_number(out: &mut String, value: f64) {
+    let value = if value.is_finite() { value } else { 0. };
+    w!(out, "{value}");
+}
@@ -0,0 +1,649 @@
+//! Wireframe SVG rendering of frames, for looking at poses without the game's sprites,
+//! and looping previews of whole animations, as animated SVG or HTML with CSS,
+//! for pages that can't run scripts.
+
+use crate::{AnmAnimation, AnmBone, AnmFrame, AnmReadingError};
+use std::collections::HashMap;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum SvgError {
+    #[error(transparent)]
+    ReadingError(#[from] AnmReadingError),
+    #[error("frame rates must be finite and positive: ({fps:?})")]
+    InvalidFrameRateError { fps: f64 },
+}
+
+/// An image to draw for a sprite instead of its placeholder.
+#[derive(Clone, Debug)]
+pub struct SvgImage {
+    /// A URL, or a `data:` URL to embed the image.
+    pub href: String,
+    /// The top left corner, in the bone's coordinates.
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+/// How to draw a frame.
+#[derive(Clone, Debug)]
+pub struct SvgOptions {
+    /// The width and height of the placeholder drawn for each bone id.
+    /// The placeholder is centered on the bone's origin.
+    pub sizes: HashMap<i16, (f64, f64)>,
+    /// The size of bones that are not in `sizes`.
+    pub default_size: (f64, f64),
+    /// Images for bone ids and sprite frames, drawn instead of placeholders.
+    pub images: HashMap<(i16, i8), SvgImage>,
+    /// Space around the drawing.
+    pub padding: f64,
+    /// Whether to label each bone with its index, id and sprite frame.
+    /// Only frames are labeled, not animations.
+    pub labels: bool,
+    /// The frame rate animations play at. Must be finite and positive.
+    pub fps: f64,
+}
+
+impl Default for SvgOptions {
+    fn default() -> Self {
+        Self {
+            sizes: HashMap::new(),
+            default_size: (40., 40.),
+            images: HashMap::new(),
+            padding: 20.,
+            labels: true,
+            fps: 24.,
+        }
+    }
+}
+
+impl SvgOptions {
+    pub fn size_of(&self, id: i16) -> (f64, f64) {
+        self.sizes.get(&id).copied().unwrap_or(self.default_size)
+    }
+
+    fn check_fps(&self) -> Result<(), SvgError> {
+        if !(self.fps.is_finite() && self.fps > 0.) {
+            return Err(SvgError::InvalidFrameRateError { fps: self.fps });
+        }
+        Ok(())
+    }
+
+    /// The rectangle `(x, y, width, height)` drawn for a sprite, in the bone's coordinates.
+    fn rect_of(&self, id: i16, frame: i8) -> (f64, f64, f64, f64) {
+        match self.images.get(&(id, frame)) {
+            Some(image) => (image.x, image.y, image.width, image.height),
+            None => {
+                let (w, h) = self.size_of(id);
+                (-w / 2., -h / 2., w, h)
+            }
+        }
+    }
+}
+
+const MARKER_SIZE: f64 = 6.;
+
+/// Draws the bones in order, so later bones are on top, like in the game.
+/// Each bone is a quad transformed by its matrix, with a line from its origin along its x axis,
+/// and faded by its opacity.
+/// The `fire_socket` is drawn as a red cross, and the `eb_platform_pos` as a blue line.
+pub fn render_frame(frame: &AnmFrame, options: &SvgOptions) -> String {
+    let mut bounds = Bounds::default();
+    bounds.add_frame(frame, options);
+    let (min_x, min_y, max_x, max_y) = bounds.padded(options.padding);
+
+    let mut out = String::new();
+    write_svg_start(&mut out, (min_x, min_y, max_x, max_y));
+    wln!(
+        out,
+        "<g font-family=\"sans-serif\" font-size=\"10\" stroke-width=\"1\">"
+    );
+
+    for (i, bone) in frame.bones.iter().enumerate() {
+        let color = color_of(bone.id);
+        wln!(
+            out,
+            "<g transform=\"matrix({} {} {} {} {} {})\" opacity=\"{}\">",
+            num(bone.scale_x as f64),
+            num(bone.rotate_skew0 as f64),
+            num(bone.rotate_skew1 as f64),
+            num(bone.scale_y as f64),
+            num(bone.x as f64),
+            num(bone.y as f64),
+            num(bone.opacity),
+        );
+        write_sprite(&mut out, bone.id, bone.frame, options, "");
+        wln!(out, "</g>");
+
+        if options.labels {
+            // labels are not transformed, so they stay readable
+            wln!(
+                out,
+                "<text x=\"{}\" y=\"{}\" fill=\"{color}\" text-anchor=\"middle\">{i}: {}/{}</text>",
+                num(bone.x as f64),
+                num(bone.y as f64),
+                bone.id,
+                bone.frame,
+            );
+        }
+    }
+
+    if let Some((x, y)) = frame.fire_socket {
+        write_fire_socket(&mut out, (x, y), "");
+    }
+    if let Some((x, y)) = frame.eb_platform_pos {
+        let s = MARKER_SIZE;
+        wln!(
+            out,
+            "<path d=\"M{} {}L{} {}M{} {}L{} {}\" stroke=\"blue\" stroke-width=\"2\"><title>eb_platform_pos</title></path>",
+            num(x - s),
+            num(y),
+            num(x + s),
+            num(y),
+            num(x),
+            num(y),
+            num(x),
+            num(y + s),
+        );
+    }
+
+    wln!(out, "</g>");
+    wln!(out, "</svg>");
+    out
+}
+
+fn write_svg_start(out: &mut String, (min_x, min_y, max_x, max_y): (f64, f64, f64, f64)) {
+    wln!(
+        out,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"{} {} {} {}\" width=\"{}\" height=\"{}\">",
+        num(min_x),
+        num(min_y),
+        num(max_x - min_x),
+        num(max_y - min_y),
+        num(max_x - min_x),
+        num(max_y - min_y),
+    );
+}
+
+/// The image of a sprite, or its placeholder, in the bone's coordinates.
+/// `attributes` go on the outermost element.
+fn write_sprite(out: &mut String, id: i16, frame: i8, options: &SvgOptions, attributes: &str) {
+    if let Some(image) = options.images.get(&(id, frame)) {
+        wln!(
+            out,
+            "<image href=\"{}\" x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\"{attributes}/>",
+            escape(&image.href),
+            num(image.x),
+            num(image.y),
+            num(image.width),
+            num(image.height),
+        );
+        return;
+    }
+    let (x, y, w, h) = options.rect_of(id, frame);
+    let color = color_of(id);
+    if !attributes.is_empty() {
+        wln!(out, "<g{attributes}>");
+    }
+    wln!(
+        out,
+        "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{color}\" fill-opacity=\"0.3\" stroke=\"{color}\" vector-effect=\"non-scaling-stroke\"/>",
+        num(x),
+        num(y),
+        num(w),
+        num(h),
+    );
+    wln!(
+        out,
+        "<line x1=\"0\" y1=\"0\" x2=\"{}\" y2=\"0\" stroke=\"{color}\" vector-effect=\"non-scaling-stroke\"/>",
+        num(x + w),
+    );
+    if !attributes.is_empty() {
+        wln!(out, "</g>");
+    }
+}
+
+fn write_fire_socket(out: &mut String, (x, y): (f64, f64), attributes: &str) {
+    let s = MARKER_SIZE;
+    wln!(
+        out,
+        "<path d=\"M{} {}L{} {}M{} {}L{} {}\" stroke=\"red\" stroke-width=\"2\"{attributes}><title>fire_socket</title></path>",
+        num(x - s),
+        num(y - s),
+        num(x + s),
+        num(y + s),
+        num(x - s),
+        num(y + s),
+        num(x + s),
+        num(y - s),
+    );
+}
+
+/// Escapes text for XML, inside elements or quoted attributes.
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// A color per bone id, so the same sprite is recognizable across frames.
+fn color_of(id: i16) -> String {
+    let hue = (id as i32 * 47).rem_euclid(360);
+    format!("hsl({hue},70%,45%)")
+}
+
+/// Rounds away float noise from the computed coordinates.
+/// SVG and CSS have no NaN or infinities, so those are written as 0.
+fn num(value: f64) -> f32 {
+    if value.is_finite() { value as f32 } else { 0. }
+}
+
+#[derive(Default)]
+struct Bounds(Option<(f64, f64, f64, f64)>);
+
+impl Bounds {
+    fn add(&mut self, (x, y): (f64, f64)) {
+        if !x.is_finite() || !y.is_finite() {
+            return;
+        }
+        self.0 = Some(match self.0 {
+            None => (x, y, x, y),
+            Some((min_x, min_y, max_x, max_y)) => {
+                (min_x.min(x), min_y.min(y), max_x.max(x), max_y.max(y))
+            }
+        });
+    }
+
+    fn add_frame(&mut self, frame: &AnmFrame, options: &SvgOptions) {
+        for bone in &frame.bones {
+            let transform = bone.transform();
+            let (x, y, w, h) = options.rect_of(bone.id, bone.frame);
+            for corner in [(x, y), (x + w, y), (x + w, y + h), (x, y + h)] {
+                self.add(transform.transform_point(corner));
+            }
+        }
+        for (x, y) in frame.fire_socket.into_iter().chain(frame.eb_platform_pos) {
+            self.add((x - MARKER_SIZE, y - MARKER_SIZE));
+            self.add((x + MARKER_SIZE, y + MARKER_SIZE));
+        }
+    }
+
+    fn padded(&self, padding: f64) -> (f64, f64, f64, f64) {
+        let (min_x, min_y, max_x, max_y) = self.0.unwrap_or_default();
+        (
+            min_x - padding,
+            min_y - padding,
+            max_x + padding,
+            max_y + padding,
+        )
+    }
+}
+
+/// A bone followed through an animation: a copy of a bone id, as in `AnmFrame::bone_copies`.
+struct Track<'a> {
+    id: i16,
+    /// The bone on each frame, if it is there.
+    bones: Vec<Option<&'a AnmBone>>,
+    /// The sprite frames shown, in the order they first appear.
+    sprite_frames: Vec<i8>,
+}
+
+/// Follows each bone through the frames, in the order the bones first appear.
+/// That is also the order they are drawn in, since SVG can't reorder elements over time.
+fn tracks(frames: &[AnmFrame]) -> Vec<Track<'_>> {
+    let mut tracks: Vec<Track> = Vec::new();
+    let mut indices = HashMap::new();
+    for (i, frame) in frames.iter().enumerate() {
+        for (bone, key) in frame.bones.iter().zip(frame.bone_copies()) {
+            let index = *indices.entry(key).or_insert_with(|| {
+                tracks.push(Track {
+                    id: key.0,
+                    bones: vec![None; frames.len()],
+                    sprite_frames: Vec::new(),
+                });
+                tracks.len() - 1
+            });
+            let track = &mut tracks[index];
+            track.bones[i] = Some(bone);
+            if !track.sprite_frames.contains(&bone.frame) {
+                track.sprite_frames.push(bone.frame);
+            }
+        }
+    }
+    tracks
+}
+
+impl Track<'_> {
+    /// A value per frame. Frames without the bone repeat the value before, or the first one,
+    /// since the bone is hidden there anyway, and that keeps the values from changing
+    /// more often than needed.
+    fn values(&self, value: impl Fn(&AnmBone) -> String) -> Vec<String> {
+        let first = self.bones.iter().flatten().next().map(|bone| value(bone));
+        let mut last = first.unwrap_or_default();
+        self.bones
+            .iter()
+            .map(|bone| {
+                if let Some(bone) = bone {
+                    last = value(bone);
+                }
+                last.clone()
+            })
+            .collect()
+    }
+
+    fn opacities(&self) -> Vec<String> {
+        let opacity = |bone: &Option<&AnmBone>| match bone {
+            Some(bone) => num(bone.opacity.clamp(0., 1.)).to_string(),
+            None => "0".into(),
+        };
+        self.bones.iter().map(opacity).collect()
+    }
+
+    /// Whether a sprite frame is shown, as an opacity per frame.
+    fn sprite_opacities(&self, sprite_frame: i8) -> Vec<String> {
+        self.values(|bone| if bone.frame == sprite_frame { "1" } else { "0" }.into())
+    }
+
+    /// The transform of each frame, split the way SVG and CSS can animate it:
+    /// translate, rotate, skewX and scale, from the outside in.
+    fn transforms(&self) -> [Vec<String>; 4] {
+        let parts: Vec<Option<[String; 4]>> = self
+            .bones
+            .iter()
+            .map(|bone| {
+                let d = (*bone)?.decompose();
+                let (sx, sy) = d.scale;
+                let sy = if d.flip { -sy } else { sy };
+                Some([
+                    format!("{} {}", num(d.translation.0), num(d.translation.1)),
+                    num(d.rotation.to_degrees()).to_string(),
+                    num(d.skew.to_degrees()).to_string(),
+                    format!("{} {}", num(sx), num(sy)),
+                ])
+            })
+            .collect();
+        std::array::from_fn(|part| {
+            let first = parts.iter().flatten().next();
+            let mut last = first.map(|parts| parts[part].clone()).unwrap_or_default();
+            parts
+                .iter()
+                .map(|parts| {
+                    if let Some(parts) = parts {
+                        last = parts[part].clone();
+                    }
+                    last.clone()
+                })
+                .collect()
+        })
+    }
+}
+
+/// The frames where a value changes, as `(frame, value)`, always starting with the first.
+fn changes(values: &[String]) -> Vec<(usize, &str)> {
+    let mut result: Vec<(usize, &str)> = Vec::new();
+    for (i, value) in values.iter().enumerate() {
+        if result.last().is_none_or(|(_, last)| *last != value) {
+            result.push((i, value));
+        }
+    }
+    result
+}
+
+fn fire_socket_values(frames: &[AnmFrame]) -> Option<(Vec<String>, Vec<String>)> {
+    let first = frames.iter().find_map(|frame| frame.fire_socket)?;
+    let mut last = first;
+    let mut positions = Vec::new();
+    let mut opacities = Vec::new();
+    for frame in frames {
+        if let Some(position) = frame.fire_socket {
+            last = position;
+        }
+        positions.push(format!("{} {}", num(last.0), num(last.1)));
+        let shown = frame.fire_socket.is_some();
+        opacities.push(if shown { "1" } else { "0" }.to_string());
+    }
+    Some((positions, opacities))
+}
+
+/// Discrete SMIL timing for the frames of an animation.
+struct Smil {
+    frame_count: usize,
+    duration: f64,
+}
+
+impl Smil {
+    /// An attribute's value on the first frame, and, if it changes,
+    /// an element that animates it through the frames.
+    fn animate(
+        &self,
+        element: &str,
+        attribute: &str,
+        kind: Option<&str>,
+        values: &[String],
+    ) -> Option<String> {
+        let changes = changes(values);
+        if changes.len() < 2 {
+            return None;
+        }
+        let key_times: Vec<String> = changes
+            .iter()
+            .map(|(i, _)| num(*i as f64 / self.frame_count as f64).to_string())
+            .collect();
+        let values: Vec<&str> = changes.iter().map(|(_, value)| *value).collect();
+        let kind = kind
+            .map(|kind| format!(" type=\"{kind}\""))
+            .unwrap_or_default();
+        Some(format!(
+            "<{element} attributeName=\"{attribute}\"{kind} values=\"{}\" keyTimes=\"{}\" dur=\"{}s\" calcMode=\"discrete\" repeatCount=\"indefinite\"/>",
+            values.join(";"),
+            key_times.join(";"),
+            num(self.duration),
+        ))
+    }
+}
+
+/// Plays the animation on a loop, with SMIL `<animate>` and `<animateTransform>` elements
+/// holding each frame for `1 / fps` seconds.
+///
+/// A bone's matrix can't be animated as a whole, so each bone is a group per part of its
+/// decomposition: translate, rotate, skewX and scale. Bones that are not on a frame are
+/// hidden with an opacity of 0, and sprite frame changes swap the sprite drawn.
+pub fn render_animation(
+    animation: &AnmAnimation,
+    options: &SvgOptions,
+) -> Result<String, SvgError> {
+    options.check_fps()?;
+    let frames = animation.frames()?;
+    let mut bounds = Bounds::default();
+    for frame in frames {
+        bounds.add_frame(frame, options);
+    }
+    let smil = Smil {
+        frame_count: frames.len(),
+        duration: frames.len() as f64 / options.fps,
+    };
+
+    let mut out = String::new();
+    write_svg_start(&mut out, bounds.padded(options.padding));
+    wln!(out, "<g stroke-width=\"1\">");
+    for track in tracks(frames) {
+        let opacities = track.opacities();
+        wln!(out, "<g opacity=\"{}\">", opacities[0]);
+        if let Some(animate) = smil.animate("animate", "opacity", None, &opacities) {
+            wln!(out, "{animate}");
+        }
+        let kinds = ["translate", "rotate", "skewX", "scale"];
+        for (kind, values) in kinds.into_iter().zip(track.transforms()) {
+            wln!(out, "<g transform=\"{kind}({})\">", values[0]);
+            let animate = smil.animate("animateTransform", "transform", Some(kind), &values);
+            if let Some(animate) = animate {
+                wln!(out, "{animate}");
+            }
+        }
+        for &sprite_frame in &track.sprite_frames {
+            let opacities = track.sprite_opacities(sprite_frame);
+            match smil.animate("animate", "opacity", None, &opacities) {
+                Some(animate) => {
+                    let attributes = format!(" opacity=\"{}\"", opacities[0]);
+                    // the animation goes inside the sprite's outermost element
+                    let mut sprite = String::new();
+                    write_sprite(&mut sprite, track.id, sprite_frame, options, &attributes);
+                    out.push_str(&insert_child(&sprite, &animate));
+                }
+                None => write_sprite(&mut out, track.id, sprite_frame, options, ""),
+            }
+        }
+        wln!(out, "</g></g></g></g>");
+        wln!(out, "</g>");
+    }
+
+    if let Some((positions, opacities)) = fire_socket_values(frames) {
+        wln!(
+            out,
+            "<g transform=\"translate({})\" opacity=\"{}\">",
+            positions[0],
+            opacities[0]
+        );
+        let animations = [
+            smil.animate(
+                "animateTransform",
+                "transform",
+                Some("translate"),
+                &positions,
+            ),
+            smil.animate("animate", "opacity", None, &opacities),
+        ];
+        for animate in animations.into_iter().flatten() {
+            wln!(out, "{animate}");
+        }
+        write_fire_socket(&mut out, (0., 0.), "");
+        wln!(out, "</g>");
+    }
+
+    wln!(out, "</g>");
+    wln!(out, "</svg>");
+    Ok(out)
+}
+
+/// Puts `child` inside the first element of `element`, which is either empty (`<image .../>`)
+/// or a group (`<g ...>`).
+fn insert_child(element: &str, child: &str) -> String {
+    let line_end = element.find('\n').unwrap_or(element.len());
+    let first_line = &element[..line_end];
+    match first_line.strip_suffix("/>") {
+        Some(start) => {
+            let name = start[1..].split(' ').next().unwrap_or_default();
+            format!("{start}>\n{child}\n</{name}>{}", &element[line_end..])
+        }
+        None => format!("{first_line}\n{child}{}", &element[line_end..]),
+    }
+}
+
+/// Plays the animation on a loop in an HTML page, with an inline SVG animated by
+/// CSS `@keyframes` that hold each frame for `1 / fps` seconds.
+///
+/// Each bone is a group whose `transform` is its matrix, hidden with an opacity of 0
+/// on frames it is not on. Sprite frame changes swap the sprite drawn.
+pub fn render_animation_html(
+    animation: &AnmAnimation,
+    options: &SvgOptions,
+) -> Result<String, SvgError> {
+    options.check_fps()?;
+    let frames = animation.frames()?;
+    let mut bounds = Bounds::default();
+    for frame in frames {
+        bounds.add_frame(frame, options);
+    }
+    let duration = num(frames.len() as f64 / options.fps);
+    let frame_count = frames.len();
+    let tracks = tracks(frames);
+
+    let mut style = String::new();
+    let mut keyframes = |class: &str, properties: &[(&str, Vec<String>)]| {
+        wln!(
+            style,
+            ".{class} {{ animation: {class} {duration}s step-end infinite; }}"
+        );
+        wln!(style, "@keyframes {class} {{");
+        let mut last = String::new();
+        for i in 0..frame_count {
+            let declarations: Vec<String> = properties
+                .iter()
+                .map(|(property, values)| format!("{property}: {};", values[i]))
+                .collect();
+            let declarations = declarations.join(" ");
+            if declarations != last {
+                let percent = num(i as f64 / frame_count as f64 * 100.);
+                wln!(style, "  {percent}% {{ {declarations} }}");
+            }
+            last = declarations;
+        }
+        // without a last keyframe, the end of the loop would fall back to no animation
+        wln!(style, "  100% {{ {last} }}\n}}");
+    };
+
+    let mut body = String::new();
+    write_svg_start(&mut body, bounds.padded(options.padding));
+    wln!(body, "<g stroke-width=\"1\">");
+    for (i, track) in tracks.iter().enumerate() {
+        let matrices = track.values(|bone| {
+            format!(
+                "matrix({}, {}, {}, {}, {}, {})",
+                num(bone.scale_x as f64),
+                num(bone.rotate_skew0 as f64),
+                num(bone.rotate_skew1 as f64),
+                num(bone.scale_y as f64),
+                num(bone.x as f64),
+                num(bone.y as f64),
+            )
+        });
+        let class = format!("b{i}");
+        keyframes(
+            &class,
+            &[("transform", matrices), ("opacity", track.opacities())],
+        );
+        wln!(body, "<g class=\"{class}\">");
+        for (j, &sprite_frame) in track.sprite_frames.iter().enumerate() {
+            if track.sprite_frames.len() == 1 {
+                write_sprite(&mut body, track.id, sprite_frame, options, "");
+                continue;
+            }
+            let class = format!("b{i}f{j}");
+            keyframes(&class, &[("opacity", track.sprite_opacities(sprite_frame))]);
+            let attributes = format!(" class=\"{class}\"");
+            write_sprite(&mut body, track.id, sprite_frame, options, &attributes);
+        }
+        wln!(body, "</g>");
+    }
+    if let Some((positions, opacities)) = fire_socket_values(frames) {
+        let positions = positions
+            .iter()
+            .map(|position| format!("translate({})", position.replace(' ', "px, ") + "px"))
+            .collect();
+        keyframes(
+            "fire-socket",
+            &[("transform", positions), ("opacity", opacities)],
+        );
+        wln!(body, "<g class=\"fire-socket\">");
+        write_fire_socket(&mut body, (0., 0.), "");
+        wln!(body, "</g>");
+    }
+    wln!(body, "</g>");
+    wln!(body, "</svg>");
+
+    let mut out = String::new();
+    wln!(out, "<!DOCTYPE html>");
+    wln!(out, "<html>");
+    wln!(out, "<head>");
+    wln!(out, "<meta charset=\"utf-8\">");
+    wln!(out, "<title>{}</title>", escape(&animation.name));
+    wln!(out, "<style>\n{style}</style>");
+    wln!(out, "</head>");
+    wln!(out, "<body>");
+    out.push_str(&body);
+    wln!(out, "</body>");
+    wln!(out, "</html>");
+    Ok(out)
+}
@@ -0,0 +1,548 @@
+//! A line-oriented text format for animations, and for whole files.
+//!
+//! ```text
+//! header 1
+//!
+//! class "a__AnimationBow"
+//! index "Bow"
+//! file_name "Animation_Bow.swf"
+//!
+//! animation "Idle"
+//! loop_start 0
+//! recovery_start 0
+//! free_start 0
+//! preview_frame 0
+//! base_start 0
+//! data 1 2
+//!
+//! frame 0
+//! fire_socket 10 -20
+//! bone 12 1 0 0 1 5.5 -3 1 1 | full store-frame
+//! ```
+//!
+//! Each bone line holds `id`, the matrix (`scale_x rotate_skew0 rotate_skew1 scale_y`),
+//! `x`, `y`, `opacity` and the sprite `frame`, optionally followed by `|` and the
+//! encoding the bone was read with (see `AnmBone::encoding`).
+//! Numbers are printed so that they parse back to the exact same value.
+//! NaNs other than the usual one are printed as `NaN:` followed by their bits in hex.
+//! Lines starting with `#` are comments.
+//!
+//! A file starts with its `header`, and each class with its key, `index` and `file_name`,
+//! followed by its animations. Since the encodings and exact values are kept,
+//! writing a parsed file gives the same bytes as writing the printed one, so the format
+//! can stand in for anm files in reviews, or as a git `textconv` driver.
+
+use crate::{
+    AnimationCollection, AnmAnimation, AnmBone, AnmClass, AnmFile, AnmFrame, AnmReadingError,
+    BoneEncoding, ClassCollection, StoredBoneEncoding, TransformEncoding,
+};
+use std::{fmt, str::FromStr};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+#[error("line {line}: {message}")]
+pub struct TextParseError {
+    /// 1-based.
+    pub line: usize,
+    pub message: String,
+}
+
+pub fn print_file(file: &AnmFile) -> Result<String, AnmReadingError> {
+    let mut out = String::new();
+    wln!(out, "header {}", file.header);
+    for (key, class) in file.classes.iter() {
+        wln!(out);
+        wln!(out, "class {}", quote(key));
+        wln!(out, "index {}", quote(&class.index));
+        wln!(out, "file_name {}", quote(&class.file_name));
+        for animation in class.animations.iter() {
+            wln!(out);
+            write_animation(&mut out, animation).map_err(|e| e.in_class(key))?;
+        }
+    }
+    Ok(out)
+}
+
+/// Parses a file printed by `print_file`. Duplicate class keys and animation names are kept.
+pub fn parse_file(text: &str) -> Result<AnmFile, TextParseError> {
+    let mut lines = Lines::new(text);
+    let mut line = lines.expect("header")?;
+    let header = line.number()?;
+    line.end()?;
+
+    let mut classes = ClassCollection::new();
+    while let Some(mut line) = lines.next_if("class")? {
+        let key = line.string()?;
+        line.end()?;
+        let mut string_field = |name: &str| -> Result<String, TextParseError> {
+            let mut line = lines.expect(name)?;
+            let value = line.string()?;
+            line.end()?;
+            Ok(value)
+        };
+        let index = string_field("index")?;
+        let file_name = string_field("file_name")?;
+
+        let mut animations = AnimationCollection::new();
+        while lines.peek_is("animation") {
+            animations.push(parse_animation_lines(&mut lines)?);
+        }
+        classes.push(
+            key,
+            AnmClass {
+                index,
+                file_name,
+                animations,
+            },
+        );
+    }
+
+    match lines.next()? {
+        Some(line) => Err(line.error("expected a class or the end of the file")),
+        None => Ok(AnmFile::new(header, classes)),
+    }
+}
+
+pub fn print_animation(animation: &AnmAnimation) -> Result<String, AnmReadingError> {
+    let mut out = String::new();
+    write_animation(&mut out, animation)?;
+    Ok(out)
+}
+
+pub(crate) fn write_animation(
+    out: &mut String,
+    animation: &AnmAnimation,
+) -> Result<(), AnmReadingError> {
+    let frames = animation.frames()?;
+    wln!(out, "animation {}", quote(&animation.name));
+    wln!(out, "loop_start {}", animation.loop_start);
+    wln!(out, "recovery_start {}", animation.recovery_start);
+    wln!(out, "free_start {}", animation.free_start);
+    wln!(out, "preview_frame {}", animation.preview_frame);
+    wln!(out, "base_start {}", animation.base_start);
+    w!(out, "data");
+    for datum in &animation.data {
+        w!(out, " {datum}");
+    }
+    wln!(out);
+
+    for frame in frames {
+        wln!(out);
+        wln!(out, "frame {}", frame.id);
+        if let Some((x, y)) = frame.fire_socket {
+            wln!(out, "fire_socket {} {}", Exact(x), Exact(y));
+        }
+        if let Some((x, y)) = frame.eb_platform_pos {
+            wln!(out, "eb_platform_pos {} {}", Exact(x), Exact(y));
+        }
+        for bone in &frame.bones {
+            write_bone(out, bone);
+        }
+    }
+    Ok(())
+}
+
+fn write_bone(out: &mut String, bone: &AnmBone) {
+    w!(
+        out,
+        "bone {} {} {} {} {} {} {} {} {}",
+        bone.id,
+        Exact(bone.scale_x),
+        Exact(bone.rotate_skew0),
+        Exact(bone.rotate_skew1),
+        Exact(bone.scale_y),
+        Exact(bone.x),
+        Exact(bone.y),
+        Exact(bone.opacity),
+        bone.frame
+    );
+    match bone.encoding {
+        None => {}
+        Some(BoneEncoding::ClonePrevFrame { keep_frame }) => {
+            w!(out, " | clone");
+            if !keep_frame {
+                w!(out, " store-frame");
+            }
+        }
+        Some(BoneEncoding::Stored(encoding)) => {
+            let transform = match encoding.transform {
+                TransformEncoding::CopyPrev => "copy-transform",
+                TransformEncoding::Identity => "identity",
+                TransformEncoding::Symmetric => "symmetric",
+                TransformEncoding::Full => "full",
+            };
+            w!(out, " | {transform}");
+            if encoding.copy_position {
+                w!(out, " copy-position");
+            }
+            if encoding.has_frame {
+                w!(out, " store-frame");
+            }
+            if !encoding.opaque {
+                w!(out, " store-opacity");
+            }
+        }
+    }
+    wln!(out);
+}
+
+pub fn parse_animation(text: &str) -> Result<AnmAnimation, TextParseError> {
+    let mut lines = Lines::new(text);
+    let animation = parse_animation_lines(&mut lines)?;
+    match lines.next()? {
+        Some(line) => Err(line.error("expected the end of the animation")),
+        None => Ok(animation),
+    }
+}
+
+/// Parses an animation, stopping before the first line that doesn't belong to it.
+pub(crate) fn parse_animation_lines(lines: &mut Lines) -> Result<AnmAnimation, TextParseError> {
+    let mut line = lines.expect("animation")?;
+    let mut animation = AnmAnimation::new(line.string()?, Vec::new());
+    line.end()?;
+
+    for (key, field) in [
+        ("loop_start", &mut animation.loop_start),
+        ("recovery_start", &mut animation.recovery_start),
+        ("free_start", &mut animation.free_start),
+        ("preview_frame", &mut animation.preview_frame),
+        ("base_start", &mut animation.base_start),
+    ] {
+        let mut line = lines.expect(key)?;
+        *field = line.number()?;
+        line.end()?;
+    }
+
+    let mut line = lines.expect("data")?;
+    while !line.is_empty() {
+        animation.data.push(line.number()?);
+    }
+
+    let mut frames: Vec<AnmFrame> = Vec::new();
+    while let Some(mut line) = lines.next_if("frame")? {
+        let mut frame = AnmFrame {
+            id: line.number()?,
+            bones: Vec::new(),
+            fire_socket: None,
+            eb_platform_pos: None,
+        };
+        line.end()?;
+
+        if let Some(mut line) = lines.next_if("fire_socket")? {
+            frame.fire_socket = Some((line.float()?, line.float()?));
+            line.end()?;
+        }
+        if let Some(mut line) = lines.next_if("eb_platform_pos")? {
+            frame.eb_platform_pos = Some((line.float()?, line.float()?));
+            line.end()?;
+        }
+        while let Some(mut line) = lines.next_if("bone")? {
+            frame.bones.push(parse_bone(&mut line)?);
+        }
+        frames.push(frame);
+    }
+    animation.set_frames(frames);
+
+    Ok(animation)
+}
+
+fn parse_bone(line: &mut Line) -> Result<AnmBone, TextParseError> {
+    let mut bone = AnmBone {
+        id: line.number()?,
+        scale_x: line.float()?,
+        rotate_skew0: line.float()?,
+        rotate_skew1: line.float()?,
+        scale_y: line.float()?,
+        x: line.float()?,
+        y: line.float()?,
+        opacity: line.float()?,
+        frame: line.number()?,
+        encoding: None,
+    };
+    if line.is_empty() {
+        return Ok(bone);
+    }
+    line.keyword("|")?;
+
+    let kind = line.word()?;
+    let mut flags = Vec::new();
+    while !line.is_empty() {
+        flags.push(line.word()?);
+    }
+    let mut flag = |name: &str| match flags.iter().position(|f| f == name) {
+        Some(i) => {
+            flags.remove(i);
+            true
+        }
+        None => false,
+    };
+
+    let encoding = if kind == "clone" {
+        BoneEncoding::ClonePrevFrame {
+            keep_frame: !flag("store-frame"),
+        }
+    } else {
+        let transform = match kind.as_str() {
+            "copy-transform" => TransformEncoding::CopyPrev,
+            "identity" => TransformEncoding::Identity,
+            "symmetric" => TransformEncoding::Symmetric,
+            "full" => TransformEncoding::Full,
+            _ => return Err(line.error(&format!("unknown bone encoding {kind:?}"))),
+        };
+        BoneEncoding::Stored(StoredBoneEncoding {
+            transform,
+            copy_position: flag("copy-position"),
+            has_frame: flag("store-frame"),
+            opaque: !flag("store-opacity"),
+        })
+    };
+    if let Some(unknown) = flags.first() {
+        return Err(line.error(&format!("unknown bone encoding flag {unknown:?}")));
+    }
+    bone.encoding = Some(encoding);
+
+    Ok(bone)
+}
+
+/// Prints a float so that `Line::float` parses it back to the same bits.
+struct Exact<T>(T);
+
+/// Floats that keep their NaN payloads through `Exact` and `Line::float`.
+pub(crate) trait ExactFloat: Sized {
+    fn parse_exact(word: &str) -> Option<Self>;
+}
+
+macro_rules! exact_float {
+    ($float:ty, $bits:ty) => {
+        impl fmt::Display for Exact<$float> {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                let value = self.0;
+                if value.is_nan() && value.to_bits() != <$float>::NAN.to_bits() {
+                    write!(f, "NaN:{:x}", value.to_bits())
+                } else {
+                    write!(f, "{value}")
+                }
+            }
+        }
+
+        impl ExactFloat for $float {
+            fn parse_exact(word: &str) -> Option<Self> {
+                match word.strip_prefix("NaN:") {
+                    Some(bits) => <$bits>::from_str_radix(bits, 16)
+                        .ok()
+                        .map(<$float>::from_bits)
+                        .filter(|value| value.is_nan()),
+                    None => word.parse().ok(),
+                }
+            }
+        }
+    };
+}
+
+exact_float!(f32, u32);
+exact_float!(f64, u64);
+
+/// Quotes a string the way `Line::string` expects it.
+pub(crate) fn quote(s: &str) -> String {
+    format!("{s:?}")
+}
+
+/// The non-empty, non-comment lines of a text, split into tokens.
+pub(crate) struct Lines<'a> {
+    lines: std::iter::Peekable<std::iter::Enumerate<std::str::Lines<'a>>>,
+}
+
+impl<'a> Lines<'a> {
+    pub(crate) fn new(text: &'a str) -> Self {
+        Self {
+            lines: text.lines().enumerate().peekable(),
+        }
+    }
+
+    fn skip_blank(&mut self) {
+        while let Some((_, line)) = self.lines.peek() {
+            let line = line.trim();
+            if !line.is_empty() && !line.starts_with('#') {
+                break;
+            }
+            self.lines.next();
+        }
+    }
+
+    pub(crate) fn next(&mut self) -> Result<Option<Line>, TextParseError> {
+        self.skip_blank();
+        match self.lines.next() {
+            Some((i, line)) => Line::new(i + 1, line).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Whether the next line starts with `keyword`.
+    pub(crate) fn peek_is(&mut self, keyword: &str) -> bool {
+        self.skip_blank();
+        self.lines
+            .peek()
+            .is_some_and(|(_, line)| line.split_whitespace().next() == Some(keyword))
+    }
+
+    /// Takes the next line if it starts with `keyword`, skipping the keyword.
+    pub(crate) fn next_if(&mut self, keyword: &str) -> Result<Option<Line>, TextParseError> {
+        if !self.peek_is(keyword) {
+            return Ok(None);
+        }
+        let mut line = self.next()?.unwrap();
+        line.word()?;
+        Ok(Some(line))
+    }
+
+    /// Takes the next line, which must start with `keyword`, skipping the keyword.
+    pub(crate) fn expect(&mut self, keyword: &str) -> Result<Line, TextParseError> {
+        match self.next()? {
+            Some(mut line) => {
+                line.keyword(keyword)?;
+                Ok(line)
+            }
+            None => Err(TextParseError {
+                line: 0,
+                message: format!("expected {keyword:?}, found the end of the text"),
+            }),
+        }
+    }
+}
+
+pub(crate) struct Line {
+    number: usize,
+    tokens: std::collections::VecDeque<String>,
+}
+
+impl Line {
+    fn new(number: usize, text: &str) -> Result<Self, TextParseError> {
+        let mut tokens = std::collections::VecDeque::new();
+        let mut chars = text.chars().peekable();
+        loop {
+            while chars.next_if(|c| c.is_whitespace()).is_some() {}
+            let Some(&first) = chars.peek() else {
+                break;
+            };
+            let mut token = String::new();
+            if first == '"' {
+                // keep the quotes, so that `string` can tell quoted tokens apart
+                token.push(chars.next().unwrap());
+                let mut escaped = false;
+                loop {
+                    let Some(c) = chars.next() else {
+                        return Err(TextParseError {
+                            line: number,
+                            message: "unterminated string".into(),
+                        });
+                    };
+                    token.push(c);
+                    if escaped {
+                        escaped = false;
+                    } else if c == '\\' {
+                        escaped = true;
+                    } else if c == '"' {
+                        break;
+                    }
+                }
+            } else {
+                while let Some(c) = chars.next_if(|c| !c.is_whitespace()) {
+                    token.push(c);
+                }
+            }
+            tokens.push_back(token);
+        }
+        Ok(Self { number, tokens })
+    }
+
+    pub(crate) fn error(&self, message: &str) -> TextParseError {
+        TextParseError {
+            line: self.number,
+            message: message.into(),
+        }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.tokens.is_empty()
+    }
+
+    pub(crate) fn word(&mut self) -> Result<String, TextParseError> {
+        self.tokens
+            .pop_front()
+            .ok_or_else(|| self.error("unexpected end of line"))
+    }
+
+    pub(crate) fn keyword(&mut self, keyword: &str) -> Result<(), TextParseError> {
+        let word = self.word()?;
+        if word != keyword {
+            return Err(self.error(&format!("expected {keyword:?}, found {word:?}")));
+        }
+        Ok(())
+    }
+
+    pub(crate) fn number<T: FromStr>(&mut self) -> Result<T, TextParseError> {
+        let word = self.word()?;
+        word.parse()
+            .map_err(|_| self.error(&format!("invalid number {word:?}")))
+    }
+
+    pub(crate) fn float<T: ExactFloat>(&mut self) -> Result<T, TextParseError> {
+        let word = self.word()?;
+        T::parse_exact(&word).ok_or_else(|| self.error(&format!("invalid number {word:?}")))
+    }
+
+    pub(crate) fn string(&mut self) -> Result<String, TextParseError> {
+        let word = self.word()?;
+        let Some(inner) = word
+            .strip_prefix('"')
+            .and_then(|word| word.strip_suffix('"'))
+        else {
+            return Err(self.error(&format!("expected a quoted string, found {word:?}")));
+        };
+        unescape(inner).ok_or_else(|| self.error(&format!("invalid escape in {word}")))
+    }
+
+    pub(crate) fn end(&self) -> Result<(), TextParseError> {
+        match self.tokens.front() {
+            Some(token) => Err(self.error(&format!("unexpected {token:?}"))),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Reverses the escaping done by `quote`.
+fn unescape(s: &str) -> Option<String> {
+    let mut result = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        let c = match chars.next()? {
+            'n' => '\n',
+            'r' => '\r',
+            't' => '\t',
+            '0' => '\0',
+            '\\' => '\\',
+            '"' => '"',
+            '\'' => '\'',
+            'u' => {
+                if chars.next()? != '{' {
+                    return None;
+                }
+                let mut hex = String::new();
+                loop {
+                    match chars.next()? {
+                        '}' => break,
+                        c => hex.push(c),
+                    }
+                }
+                char::from_u32(u32::from_str_radix(&hex, 16).ok()?)?
+            }
+            _ => return None,
+        };
+        result.push(c);
+    }
+    Some(result)
+}
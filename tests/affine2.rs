@@ -0,0 +1,101 @@
+mod common;
+
+use bhanm::{Affine2, Decomposition};
+use common::bone;
+use proptest::prelude::*;
+use std::f64::consts::FRAC_PI_2;
+
+fn close(a: Affine2, b: Affine2) -> bool {
+    [
+        a.a - b.a,
+        a.b - b.b,
+        a.c - b.c,
+        a.d - b.d,
+        a.tx - b.tx,
+        a.ty - b.ty,
+    ]
+    .iter()
+    .all(|d| d.abs() < 1e-6)
+}
+
+fn component() -> impl Strategy<Value = f64> {
+    prop_oneof![Just(0.), Just(1.), Just(-1.), -100f64..100f64]
+}
+
+fn affine() -> impl Strategy<Value = Affine2> {
+    (
+        component(),
+        component(),
+        component(),
+        component(),
+        component(),
+        component(),
+    )
+        .prop_map(|(a, b, c, d, tx, ty)| Affine2::new(a, b, c, d, tx, ty))
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(256))]
+
+    #[test]
+    fn decomposition_recomposes(m in affine()) {
+        prop_assume!(m.determinant().abs() > 1e-3);
+        let parts = m.decompose();
+        prop_assert!(parts.scale.0 >= 0. && parts.scale.1 >= 0.);
+        prop_assert_eq!(parts.flip, m.determinant() < 0.);
+        prop_assert!(close(parts.recompose(), m), "{:?} -> {:?}", m, parts);
+    }
+
+    #[test]
+    fn inverse_undoes_the_transform(m in affine(), p in (component(), component())) {
+        prop_assume!(m.determinant().abs() > 1e-3);
+        let inverse = m.inverse().unwrap();
+        prop_assert!(close(inverse * m, Affine2::IDENTITY));
+        let (x, y) = inverse.transform_point(m.transform_point(p));
+        prop_assert!((x - p.0).abs() < 1e-6 && (y - p.1).abs() < 1e-6);
+    }
+
+    #[test]
+    fn symmetric_matrices_are_flipped_without_skew(a in component(), b in component()) {
+        prop_assume!(a != 0. || b != 0.);
+        let parts = Affine2::new(a, b, b, -a, 0., 0.).decompose();
+        prop_assert!(parts.flip);
+        prop_assert!(parts.skew.abs() < 1e-9);
+        prop_assert!((parts.scale.0 - parts.scale.1).abs() < 1e-9);
+    }
+}
+
+#[test]
+fn parts_act_in_order() {
+    let parts = Decomposition {
+        translation: (10., 20.),
+        rotation: FRAC_PI_2,
+        scale: (2., 3.),
+        skew: 0.,
+        flip: true,
+    };
+    // (1, 1) -> flip (1, -1) -> scale (2, -3) -> rotate a quarter turn (3, 2) -> translate
+    let (x, y) = parts.recompose().transform_point((1., 1.));
+    assert!((x - 13.).abs() < 1e-9 && (y - 22.).abs() < 1e-9);
+    assert!(Affine2::new(1., 0., 0., 1., 0., 0.).inverse().is_some());
+    assert!(Affine2::scale(0., 1.).inverse().is_none());
+}
+
+#[test]
+fn bone_setters_keep_the_other_parts() {
+    let mut bone = bone(5.);
+    bone.scale_x = 2.;
+    bone.rotate_skew0 = 0.;
+    bone.rotate_skew1 = 0.;
+    bone.scale_y = 3.;
+
+    bone.set_rotation(FRAC_PI_2);
+    assert!((bone.rotation() - FRAC_PI_2).abs() < 1e-6);
+    let (sx, sy) = bone.scale();
+    assert!((sx - 2.).abs() < 1e-6 && (sy - 3.).abs() < 1e-6);
+    assert_eq!(bone.x, 5.);
+
+    bone.set_flipped(true);
+    assert!(bone.is_flipped());
+    assert!(bone.transform().determinant() < 0.);
+}
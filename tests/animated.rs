@@ -0,0 +1,156 @@
+mod common;
+
+use bhanm::{
+    AnmAnimation,
+    render::{
+        Placeholders, RenderOptions,
+        animated::{AnimationExportError, AnimationOptions, render_animation},
+    },
+};
+use common::frame;
+
+fn animation() -> AnmAnimation {
+    let frames = (0..6).map(|i| frame(&[i as f32 * 10.])).collect();
+    let mut animation = AnmAnimation::new("Anim".into(), frames);
+    animation.loop_start = 2;
+    animation.recovery_start = 5;
+    animation
+}
+
+fn options() -> AnimationOptions {
+    AnimationOptions {
+        render: RenderOptions {
+            width: 64,
+            height: 32,
+            origin: (0., 16.),
+            background: [0, 0, 0, 255],
+            ..Default::default()
+        },
+        ..Default::default()
+    }
+}
+
+#[test]
+fn range_defaults_to_the_loop() {
+    let animation = animation();
+    assert_eq!(options().frame_range(&animation), 2..5);
+
+    let mut no_loop = AnmAnimation::new("Anim".into(), vec![frame(&[]), frame(&[])]);
+    no_loop.recovery_start = 9;
+    assert_eq!(options().frame_range(&no_loop), 0..2);
+
+    let custom = AnimationOptions {
+        range: Some(1..100),
+        ..options()
+    };
+    assert_eq!(custom.frame_range(&animation), 1..6);
+}
+
+#[test]
+fn frame_rate_changes_the_frame_count() {
+    let animation = animation();
+    let images = render_animation(&animation, &Placeholders::default(), &options()).unwrap();
+    assert_eq!(images.len(), 3);
+    assert!(images.iter().all(|i| (i.width, i.height) == (64, 32)));
+    assert_ne!(images[0], images[1]);
+
+    let double = AnimationOptions {
+        fps: 48.,
+        ..options()
+    };
+    let images = render_animation(&animation, &Placeholders::default(), &double).unwrap();
+    assert_eq!(images.len(), 6);
+
+    let empty = AnimationOptions {
+        range: Some(3..3),
+        ..options()
+    };
+    assert!(matches!(
+        render_animation(&animation, &Placeholders::default(), &empty),
+        Err(AnimationExportError::EmptyRangeError { .. })
+    ));
+}
+
+#[test]
+fn bad_frame_rates_and_sizes_fail() {
+    let animation = animation();
+    let placeholders = Placeholders::default();
+    for (fps, animation_fps) in [
+        (0., 24.),
+        (-24., 24.),
+        (f64::NAN, 24.),
+        (24., f64::INFINITY),
+    ] {
+        let options = AnimationOptions {
+            fps,
+            animation_fps,
+            ..options()
+        };
+        assert!(matches!(
+            render_animation(&animation, &placeholders, &options),
+            Err(AnimationExportError::InvalidFrameRateError { .. })
+        ));
+    }
+
+    let too_fast = AnimationOptions {
+        fps: 1e9,
+        ..options()
+    };
+    assert!(matches!(
+        render_animation(&animation, &placeholders, &too_fast),
+        Err(AnimationExportError::TooManyFramesError { .. })
+    ));
+
+    let too_large = AnimationOptions {
+        render: RenderOptions {
+            width: 20000,
+            ..options().render
+        },
+        ..options()
+    };
+    assert!(matches!(
+        render_animation(&animation, &placeholders, &too_large),
+        Err(AnimationExportError::TooLargeImageError {
+            width: 20000,
+            height: 32
+        })
+    ));
+}
+
+#[cfg(feature = "gif")]
+#[test]
+fn writes_a_gif() {
+    let mut bytes = Vec::new();
+    bhanm::render::animated::write_gif(
+        &animation(),
+        &Placeholders::default(),
+        &options(),
+        &mut bytes,
+    )
+    .unwrap();
+    let mut decoder = gif::DecodeOptions::new().read_info(&bytes[..]).unwrap();
+    let mut frames = 0;
+    while let Some(frame) = decoder.read_next_frame().unwrap() {
+        assert_eq!(frame.delay, 4);
+        frames += 1;
+    }
+    assert_eq!(frames, 3);
+}
+
+#[cfg(feature = "png")]
+#[test]
+fn writes_an_apng() {
+    let mut bytes = Vec::new();
+    bhanm::render::animated::write_apng(
+        &animation(),
+        &Placeholders::default(),
+        &options(),
+        &mut bytes,
+    )
+    .unwrap();
+    let reader = png::Decoder::new(std::io::Cursor::new(bytes))
+        .read_info()
+        .unwrap();
+    let control = reader.info().animation_control().unwrap();
+    assert_eq!((control.num_frames, control.num_plays), (3, 0));
+}
@@ -0,0 +1,68 @@
+mod common;
+
+use bhanm::{AnmAnimation, AnmFile, ReadOptions};
+use common::write;
+use std::{env, fs, process};
+
+#[test]
+fn pack_restores_unpacked_file() {
+    let mut file = common::file();
+    let class = file.classes.get_mut("Key").unwrap();
+    // names that are awkward as file names, and a duplicate kept by lenient reading
+    for name in ["a/b", "A/B", "quote \"\\ \u{7f}", "", "Anim"] {
+        let mut animation = AnmAnimation::new(name.into(), vec![common::frame(&[5.])]);
+        animation.data = vec![1, 2, 3];
+        class.animations.push(animation);
+    }
+    let bytes = write(&file);
+    let options = ReadOptions {
+        lazy_frames: true,
+        ..Default::default()
+    };
+    let (file, warnings) = AnmFile::read_with_options(&bytes[..], &options).unwrap();
+    assert_eq!(warnings.len(), 1);
+
+    let dir = env::temp_dir().join(format!("bhanm-anm-dir-{}", process::id()));
+    file.unpack_to_dir(&dir).unwrap();
+    let packed = AnmFile::pack_from_dir(&dir);
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(write(&packed.unwrap()), bytes);
+}
+
+#[test]
+fn file_names_avoid_reserved_and_long_names() {
+    let mut file = common::file();
+    let long = "x".repeat(1000);
+    let class = file.classes.remove("Key").unwrap();
+    file.classes.push("con".into(), class);
+    let class = file.classes.get_mut("con").unwrap();
+    for name in ["NUL", "Com1", "trailing. ", long.as_str()] {
+        class
+            .animations
+            .push(AnmAnimation::new(name.into(), vec![common::frame(&[5.])]));
+    }
+
+    let dir = env::temp_dir().join(format!("bhanm-anm-dir-names-{}", process::id()));
+    file.unpack_to_dir(&dir).unwrap();
+    let mut names: Vec<String> = fs::read_dir(dir.join("con_"))
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .collect();
+    let packed = AnmFile::pack_from_dir(&dir);
+    fs::remove_dir_all(&dir).unwrap();
+
+    names.sort();
+    let long_name = format!("{}.txt", "x".repeat(200));
+    assert_eq!(
+        names,
+        [
+            "Anim.txt",
+            "Com1_.txt",
+            "NUL_.txt",
+            "trailing__.txt",
+            &long_name
+        ]
+    );
+    assert_eq!(write(&packed.unwrap()), write(&file));
+}
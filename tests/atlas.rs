@@ -0,0 +1,171 @@
+mod common;
+
+use bhanm::{
+    Affine2, AnmAnimation, AnmFrame,
+    render::{
+        FillPath, SpriteSource,
+        animated::AnimationExportError,
+        atlas::{AtlasOptions, build_atlas},
+    },
+};
+use common::{bone, frame};
+
+/// A 10x10 white square with its corner on the bone's origin.
+struct Square;
+
+impl SpriteSource for Square {
+    fn sprite(&self, _id: i16, _frame: i8) -> Vec<FillPath> {
+        vec![FillPath::rect(0., 0., 10., 10., [1., 1., 1., 1.])]
+    }
+}
+
+fn squares(positions: &[(f32, f32)]) -> AnmFrame {
+    let mut frame = frame(&[]);
+    for &(x, y) in positions {
+        let mut bone = bone(x);
+        bone.y = y;
+        bone.set_transform(Affine2::new(1., 0., 0., 1., x as f64, y as f64));
+        frame.bones.push(bone);
+    }
+    frame
+}
+
+fn animation() -> AnmAnimation {
+    let mut wide = squares(&[(-20., 0.), (10., 0.)]);
+    wide.fire_socket = Some((15., -5.));
+    AnmAnimation::new(
+        "Anim".into(),
+        vec![
+            squares(&[(0., 0.)]),
+            wide,
+            squares(&[]),
+            squares(&[(0., 0.), (0., 20.)]),
+        ],
+    )
+}
+
+#[test]
+fn frames_are_cropped_and_packed() {
+    let atlas = build_atlas(&animation(), &Square, &AtlasOptions::default()).unwrap();
+    let sizes: Vec<_> = atlas.frames.iter().map(|f| (f.width, f.height)).collect();
+    assert_eq!(sizes, [(12, 12), (42, 12), (0, 0), (12, 32)]);
+    assert_eq!(atlas.frames[1].pivot, (21., 1.));
+    assert_eq!(atlas.frames[1].fire_socket, Some((15., -5.)));
+    assert_eq!(atlas.frames[0].fire_socket, None);
+
+    // rectangles don't overlap, and the tallest comes first
+    let placed: Vec<_> = atlas.frames.iter().filter(|f| f.width > 0).collect();
+    for (i, a) in placed.iter().enumerate() {
+        assert!(a.x + a.width <= atlas.image.width && a.y + a.height <= atlas.image.height);
+        for b in &placed[i + 1..] {
+            let apart = a.x + a.width <= b.x
+                || b.x + b.width <= a.x
+                || a.y + a.height <= b.y
+                || b.y + b.height <= a.y;
+            assert!(apart, "{a:?} overlaps {b:?}");
+        }
+    }
+    assert_eq!((atlas.frames[3].x, atlas.frames[3].y), (0, 0));
+
+    // the square of the first frame is drawn right of and below its pivot
+    let first = &atlas.frames[0];
+    let (x, y) = (
+        first.x + first.pivot.0 as u32,
+        first.y + first.pivot.1 as u32,
+    );
+    assert_eq!(atlas.image.pixel(x + 5, y + 5), [255, 255, 255, 255]);
+    assert_eq!(atlas.image.pixel(first.x, first.y)[3], 0);
+}
+
+#[test]
+fn narrow_atlases_wrap_into_rows() {
+    let options = AtlasOptions {
+        max_width: 50,
+        scale: 0.5,
+        ..Default::default()
+    };
+    let atlas = build_atlas(&animation(), &Square, &options).unwrap();
+    assert!(atlas.image.width <= 50);
+    assert_eq!(atlas.frames[1].fire_socket, Some((7.5, -2.5)));
+
+    let json: serde_json::Value = serde_json::from_str(&atlas.to_json("anim.png")).unwrap();
+    assert_eq!(json["image"], "anim.png");
+    assert_eq!(json["width"], atlas.image.width);
+    assert_eq!(json["frames"].as_array().unwrap().len(), 4);
+    assert_eq!(json["frames"][1]["fire_socket"]["x"], 7.5);
+    assert_eq!(json["frames"][0]["fire_socket"], serde_json::Value::Null);
+    assert_eq!(json["frames"][2]["width"], 0);
+}
+
+#[cfg(feature = "png")]
+#[test]
+fn writes_a_png() {
+    let atlas = build_atlas(&animation(), &Square, &AtlasOptions::default()).unwrap();
+    let mut bytes = Vec::new();
+    atlas.write_png(&mut bytes).unwrap();
+    let mut reader = png::Decoder::new(std::io::Cursor::new(bytes))
+        .read_info()
+        .unwrap();
+    let mut pixels = vec![0; reader.output_buffer_size().unwrap()];
+    reader.next_frame(&mut pixels).unwrap();
+    assert_eq!(pixels, atlas.image.pixels);
+}
+
+#[test]
+fn too_large_atlases_are_rejected() {
+    // a single bone far away still gets a small rectangle
+    let far = AnmAnimation::new("Far".into(), vec![squares(&[(1e9, 0.)])]);
+    let atlas = build_atlas(&far, &Square, &AtlasOptions::default()).unwrap();
+    assert_eq!((atlas.image.width, atlas.image.height), (12, 12));
+
+    let spread = AnmAnimation::new("Spread".into(), vec![squares(&[(0., 0.), (1e9, 0.)])]);
+    let error = build_atlas(&spread, &Square, &AtlasOptions::default())
+        .err()
+        .unwrap();
+    assert!(matches!(
+        error,
+        AnimationExportError::TooLargeImageError { height: 12, .. }
+    ));
+
+    // within `MAX_SIZE` on both sides, but too many pixels
+    let square = AnmAnimation::new("Square".into(), vec![squares(&[(0., 0.), (4990., 4990.)])]);
+    let error = build_atlas(&square, &Square, &AtlasOptions::default())
+        .err()
+        .unwrap();
+    assert!(matches!(
+        error,
+        AnimationExportError::TooLargeImageError {
+            width: 5002,
+            height: 5002,
+        }
+    ));
+
+    // every frame on its own row, with huge gaps in between
+    let options = AtlasOptions {
+        spacing: u32::MAX,
+        max_width: 0,
+        ..Default::default()
+    };
+    let error = build_atlas(&animation(), &Square, &options).err().unwrap();
+    assert!(matches!(
+        error,
+        AnimationExportError::TooLargeImageError {
+            width: 12,
+            height: u32::MAX,
+        }
+    ));
+}
+
+#[test]
+fn scales_must_be_finite_and_positive() {
+    for scale in [0., -1., f64::NAN, f64::INFINITY] {
+        let options = AtlasOptions {
+            scale,
+            ..Default::default()
+        };
+        assert!(matches!(
+            build_atlas(&animation(), &Square, &options),
+            Err(AnimationExportError::InvalidScaleError { .. })
+        ));
+    }
+}
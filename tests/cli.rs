@@ -0,0 +1,130 @@
+mod common;
+
+use common::write;
+use std::{
+    env, fs,
+    path::PathBuf,
+    process::{self, Command, Output},
+};
+
+fn bhanm(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_bhanm"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+/// A fresh directory for one test.
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("bhanm-cli-{name}-{}", process::id()));
+    _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[test]
+fn bad_arguments_print_the_usage() {
+    for args in [
+        &[][..],
+        &["nope"],
+        &["list"],
+        &["list", "a.anm", "b.anm"],
+        &["patch-notes", "old.anm", "new.anm", "--xml"],
+    ] {
+        let output = bhanm(args);
+        assert!(!output.status.success(), "{args:?}");
+        assert!(stderr(&output).starts_with("Usage:"), "{args:?}");
+        assert!(output.stdout.is_empty(), "{args:?}");
+    }
+
+    let output = bhanm(&["--help"]);
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).starts_with("Usage:"));
+}
+
+#[test]
+fn errors_are_reported() {
+    let dir = temp_dir("errors");
+    let missing = dir.join("missing.anm");
+    let output = bhanm(&["list", missing.to_str().unwrap()]);
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert!(!output.status.success());
+    assert!(stderr(&output).starts_with("error: "));
+}
+
+#[test]
+fn text_round_trips_through_from_text() {
+    let dir = temp_dir("text");
+    let anm = dir.join("in.anm");
+    let bytes = write(&common::file());
+    fs::write(&anm, &bytes).unwrap();
+
+    let output = bhanm(&["text", anm.to_str().unwrap()]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    let text = dir.join("in.txt");
+    fs::write(&text, &output.stdout).unwrap();
+    let out = dir.join("out.anm");
+    let output = bhanm(&["from-text", text.to_str().unwrap(), out.to_str().unwrap()]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    let written = fs::read(&out).unwrap();
+
+    let output = bhanm(&["list", out.to_str().unwrap()]);
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(written, bytes);
+    assert!(output.status.success());
+    let list = String::from_utf8(output.stdout).unwrap();
+    assert!(list.starts_with("Key (index \"Index\", file \"File.swf\", 1 animations)\n  Anim"));
+}
+
+#[test]
+fn dump_and_stats_print_the_file() {
+    let dir = temp_dir("dump");
+    let anm = dir.join("in.anm");
+    fs::write(&anm, write(&common::file())).unwrap();
+    let anm = anm.to_str().unwrap();
+
+    let dump = bhanm(&["dump", anm, "Key", "Anim"]);
+    let stats = bhanm(&["stats", anm, anm]);
+    let missing = bhanm(&["dump", anm, "Key", "Missing"]);
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert!(dump.status.success(), "{}", stderr(&dump));
+    assert_eq!(
+        String::from_utf8(dump.stdout).unwrap(),
+        "\
+Anim: frames 2, loop_start 0, recovery_start 0, free_start 0, base_start 0, preview_frame 0
+data: []
+frame 0 (id 0, 1 bones)
+  bone 0: id 1, matrix [2, 0.5, 0.25, 3], position (1, 0), opacity 1, frame 1
+frame 1 (id 0, 3 bones)
+  bone 0: id 1, matrix [2, 0.5, 0.25, 3], position (2, 0), opacity 1, frame 1
+  bone 1: id 1, matrix [2, 0.5, 0.25, 3], position (3, 0), opacity 1, frame 1
+  bone 2: id 1, matrix [2, 0.5, 0.25, 3], position (4, 0), opacity 1, frame 1
+"
+    );
+
+    assert!(stats.status.success(), "{}", stderr(&stats));
+    assert_eq!(
+        String::from_utf8(stats.stdout).unwrap(),
+        "\
+files: 2
+classes: 2
+animations: 2
+frames: 4
+bones: 8
+average frames per animation: 2.00
+average bones per frame: 2.00
+longest animation: Key/Anim (2 frames)
+"
+    );
+
+    assert!(!missing.status.success());
+    assert!(stderr(&missing).starts_with("error: "));
+    assert!(missing.stdout.is_empty());
+}
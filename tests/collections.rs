@@ -0,0 +1,147 @@
+mod common;
+
+use bhanm::{AnimationCollection, AnmAnimation, AnmClass, AnmFile, ClassCollection};
+use common::{frame, write};
+
+fn animation(name: &str) -> AnmAnimation {
+    AnmAnimation::new(name.into(), vec![frame(&[1.])])
+}
+
+fn animations(names: &[&str]) -> AnimationCollection {
+    let mut animations = AnimationCollection::new();
+    for name in names {
+        animations.push(animation(name));
+    }
+    animations
+}
+
+fn class(names: &[&str]) -> AnmClass {
+    AnmClass {
+        index: "Index".into(),
+        file_name: "File.swf".into(),
+        animations: animations(names),
+    }
+}
+
+fn names(animations: &AnimationCollection) -> Vec<&str> {
+    animations.iter().map(|a| a.name.as_str()).collect()
+}
+
+#[test]
+fn moving_and_sorting_keeps_lookups_right() {
+    let mut animations = animations(&["A", "B", "C", "D"]);
+
+    animations.move_index(0, 2);
+    assert_eq!(names(&animations), ["B", "C", "A", "D"]);
+    animations.move_index(3, 0);
+    assert_eq!(names(&animations), ["D", "B", "C", "A"]);
+    for (i, name) in ["D", "B", "C", "A"].into_iter().enumerate() {
+        assert_eq!(animations.index_of(name), Some(i));
+    }
+
+    animations.sort_by(|a, b| a.name.cmp(&b.name));
+    assert_eq!(names(&animations), ["A", "B", "C", "D"]);
+    assert_eq!(animations.index_of("D"), Some(3));
+
+    animations.swap_indices(0, 3);
+    assert_eq!(animations.get_index(0).unwrap().name, "D");
+    assert_eq!(animations.index_of("A"), Some(3));
+
+    assert_eq!(animations.remove("B").unwrap().name, "B");
+    assert_eq!(names(&animations), ["D", "C", "A"]);
+    assert_eq!(animations.index_of("A"), Some(2));
+    assert_eq!(animations.index_of("B"), None);
+}
+
+#[test]
+fn sorting_is_stable() {
+    let mut animations = AnimationCollection::new();
+    for (name, loop_start) in [("A", 2), ("B", 1), ("C", 2), ("D", 1)] {
+        let mut animation = animation(name);
+        animation.loop_start = loop_start;
+        animations.push(animation);
+    }
+    animations.sort_by(|a, b| a.loop_start.cmp(&b.loop_start));
+    assert_eq!(names(&animations), ["B", "D", "A", "C"]);
+}
+
+#[test]
+fn duplicates_are_found_in_order() {
+    let mut animations = animations(&["A", "B", "A"]);
+    assert_eq!(animations.index_of("A"), Some(0));
+
+    animations.remove_index(0);
+    assert_eq!(animations.index_of("A"), Some(1));
+
+    // insert replaces the first one
+    animations.push(animation("B"));
+    let mut replacement = animation("B");
+    replacement.loop_start = 7;
+    assert_eq!(animations.insert(replacement).unwrap().loop_start, 0);
+    assert_eq!(animations.get_index(0).unwrap().loop_start, 7);
+    assert_eq!(animations.get_index(2).unwrap().loop_start, 0);
+}
+
+#[test]
+fn renamed_animations_are_found() {
+    let mut animations = animations(&["A", "B", "C"]);
+
+    animations.get_mut("A").unwrap().name = "Z".into();
+    assert_eq!(animations.index_of("A"), None);
+    assert_eq!(animations.index_of("Z"), Some(0));
+    assert_eq!(animations.get_mut("Z").unwrap().name, "Z");
+
+    animations.get_index_mut(1).unwrap().name = "C".into();
+    assert_eq!(animations.index_of("B"), None);
+    assert_eq!(animations.index_of("C"), Some(1));
+    animations.push(animation("B"));
+    assert_eq!(animations.index_of("B"), Some(3));
+
+    for animation in animations.iter_mut() {
+        animation.name.make_ascii_lowercase();
+    }
+    assert_eq!(animations.index_of("c"), Some(1));
+    assert!(animations.get("Z").is_none());
+    assert_eq!(animations.remove("b").unwrap().name, "b");
+    assert_eq!(names(&animations), ["z", "c", "c"]);
+}
+
+#[test]
+fn classes_keep_their_order() {
+    let mut classes = ClassCollection::new();
+    for key in ["C", "A", "B"] {
+        classes.push(key.into(), class(&[]));
+    }
+
+    classes.move_index(2, 0);
+    assert_eq!(classes.keys().collect::<Vec<_>>(), ["B", "C", "A"]);
+    assert_eq!(classes.index_of("A"), Some(2));
+
+    classes.sort_by(|k1, _, k2, _| k1.cmp(k2));
+    assert_eq!(classes.keys().collect::<Vec<_>>(), ["A", "B", "C"]);
+    assert_eq!(classes.index_of("C"), Some(2));
+
+    classes.remove("A");
+    assert_eq!(classes.index_of("B"), Some(0));
+    assert!(classes.get("A").is_none());
+}
+
+#[test]
+fn file_order_survives_a_round_trip() {
+    let mut classes = ClassCollection::new();
+    classes.push("Zeta".into(), class(&["Walk", "Attack", "Idle"]));
+    classes.push("Alpha".into(), class(&["B", "A"]));
+    classes.push("Mid".into(), class(&[]));
+    let file = AnmFile::new(3, classes);
+
+    let read = AnmFile::read(&write(&file)[..]).unwrap();
+    assert_eq!(
+        read.classes.keys().collect::<Vec<_>>(),
+        ["Zeta", "Alpha", "Mid"]
+    );
+    let zeta = &read.classes.get("Zeta").unwrap().animations;
+    assert_eq!(names(zeta), ["Walk", "Attack", "Idle"]);
+    assert_eq!(zeta.index_of("Idle"), Some(2));
+    let alpha = &read.classes.get("Alpha").unwrap().animations;
+    assert_eq!(names(alpha), ["B", "A"]);
+}
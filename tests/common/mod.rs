@@ -0,0 +1,50 @@
+//! Helpers shared by the integration tests.
+#![allow(dead_code)]
+
+use bhanm::{
+    AnimationCollection, AnmAnimation, AnmBone, AnmClass, AnmFile, AnmFrame, ClassCollection,
+};
+
+pub fn bone(x: f32) -> AnmBone {
+    let mut bone = AnmBone::new(1);
+    bone.scale_x = 2.;
+    bone.rotate_skew0 = 0.5;
+    bone.rotate_skew1 = 0.25;
+    bone.scale_y = 3.;
+    bone.x = x;
+    bone
+}
+
+pub fn frame(xs: &[f32]) -> AnmFrame {
+    AnmFrame {
+        id: 0,
+        bones: xs.iter().copied().map(bone).collect(),
+        fire_socket: None,
+        eb_platform_pos: None,
+    }
+}
+
+pub fn file() -> AnmFile {
+    let mut animations = AnimationCollection::new();
+    animations.insert(AnmAnimation::new(
+        "Anim".into(),
+        vec![frame(&[1.]), frame(&[2., 3., 4.])],
+    ));
+    let mut classes = ClassCollection::new();
+    classes.insert(
+        "Key".into(),
+        AnmClass {
+            index: "Index".into(),
+            file_name: "File.swf".into(),
+            animations,
+        },
+    );
+    AnmFile::new(0, classes)
+}
+
+/// The bytes `AnmFile::write` writes.
+pub fn write(file: &AnmFile) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    file.write(&mut bytes).unwrap();
+    bytes
+}
@@ -0,0 +1,124 @@
+mod common;
+
+use bhanm::{
+    AnmAnimation, AnmPath,
+    diff::{ChangeKind, TimingField, diff},
+};
+use common::{file, frame};
+
+fn path(frame: Option<usize>, bone: Option<usize>) -> AnmPath {
+    AnmPath {
+        class_key: Some("Key".into()),
+        animation: Some("Anim".into()),
+        frame,
+        bone,
+    }
+}
+
+#[test]
+fn identical_files_have_no_changes() {
+    assert!(diff(&file(), &file()).unwrap().changes.is_empty());
+}
+
+#[test]
+fn changes_are_reported_with_their_path() {
+    let old = file();
+    let mut new = file();
+    {
+        let class = new.classes.get_mut("Key").unwrap();
+        class
+            .animations
+            .push(AnmAnimation::new("Added".into(), vec![frame(&[])]));
+        let animation = class.animations.get_mut("Anim").unwrap();
+        animation.recovery_start = 5;
+        let frames = animation.frames_mut().unwrap();
+        frames[0].fire_socket = Some((1., 2.));
+        frames[1].bones[1].opacity = 0.5;
+        frames[1].bones.pop();
+        frames.push(frame(&[]));
+    }
+
+    let changes: Vec<_> = diff(&old, &new)
+        .unwrap()
+        .changes
+        .into_iter()
+        .map(|c| (c.path, c.kind))
+        .collect();
+    assert_eq!(
+        changes,
+        vec![
+            (
+                path(None, None),
+                ChangeKind::Timing {
+                    field: TimingField::RecoveryStart,
+                    old: 0,
+                    new: 5
+                }
+            ),
+            (path(None, None), ChangeKind::FrameCount { old: 2, new: 3 }),
+            (
+                path(Some(0), None),
+                ChangeKind::FireSocket {
+                    old: None,
+                    new: Some((1., 2.))
+                }
+            ),
+            (
+                path(Some(1), Some(1)),
+                ChangeKind::Opacity { old: 1., new: 0.5 }
+            ),
+            (path(Some(1), Some(2)), ChangeKind::BoneRemoved),
+            (
+                AnmPath {
+                    animation: Some("Added".into()),
+                    ..path(None, None)
+                },
+                ChangeKind::AnimationAdded
+            ),
+        ]
+    );
+}
+
+#[test]
+fn duplicate_names_are_matched_in_order() {
+    let old = file();
+    let mut new = file();
+    new.classes
+        .get_mut("Key")
+        .unwrap()
+        .animations
+        .push(AnmAnimation::new("Anim".into(), vec![frame(&[])]));
+
+    let changes: Vec<_> = diff(&old, &new)
+        .unwrap()
+        .changes
+        .into_iter()
+        .map(|c| (c.path, c.kind))
+        .collect();
+    assert_eq!(
+        changes,
+        vec![(path(None, None), ChangeKind::AnimationAdded)]
+    );
+}
+
+#[test]
+fn renamed_classes_are_removed_and_added() {
+    let old = file();
+    let mut new = file();
+    let class = new.classes.remove("Key").unwrap();
+    new.classes.push("Other".into(), class);
+
+    let changes: Vec<_> = diff(&old, &new)
+        .unwrap()
+        .changes
+        .into_iter()
+        .map(|c| (c.path.class_key, c.kind))
+        .collect();
+    assert_eq!(
+        changes,
+        vec![
+            (Some("Key".into()), ChangeKind::ClassRemoved),
+            (Some("Other".into()), ChangeKind::ClassAdded),
+        ]
+    );
+}
@@ -0,0 +1,102 @@
+mod common;
+
+use bhanm::{
+    AnmAnimation, AnmClass, AnmFile, AnmPath, AnmReadingError, AnmReadingWarning, DuplicateMode,
+    ReadOptions,
+};
+use common::{file, frame, write};
+
+fn read(bytes: &[u8], duplicates: DuplicateMode) -> Result<AnmFile, AnmReadingError> {
+    let options = ReadOptions {
+        duplicates,
+        ..Default::default()
+    };
+    AnmFile::read_with_options(bytes, &options).map(|(file, warnings)| {
+        assert!(warnings.is_empty(), "{warnings:?}");
+        file
+    })
+}
+
+fn second_class() -> AnmClass {
+    file().classes.remove("Key").unwrap()
+}
+
+/// `file()` with a second animation named `Anim`, then a second class keyed `Key`.
+fn with_duplicates() -> Vec<u8> {
+    let mut file = file();
+    let mut class = second_class();
+    let mut animation = AnmAnimation::new("Anim".into(), vec![frame(&[9.])]);
+    animation.loop_start = 7;
+    file.classes
+        .get_mut("Key")
+        .unwrap()
+        .animations
+        .push(animation);
+    class.index = "Second".into();
+    file.classes.push("Key".into(), class);
+    write(&file)
+}
+
+#[test]
+fn strict_mode_fails_on_duplicates() {
+    let error = read(&with_duplicates(), DuplicateMode::Strict)
+        .err()
+        .unwrap();
+    let AnmReadingError::ContextError { path, .. } = &error else {
+        panic!("expected a context error, got {error}");
+    };
+    assert_eq!(
+        *path,
+        AnmPath {
+            class_key: Some("Key".into()),
+            animation: Some("Anim".into()),
+            ..Default::default()
+        }
+    );
+    assert!(matches!(
+        error.root_cause(),
+        AnmReadingError::DuplicateAnimationNameError { class_key, name }
+            if class_key == "Key" && name == "Anim"
+    ));
+
+    // only the class key is duplicated
+    let mut file = file();
+    file.classes.push("Key".into(), second_class());
+    let error = read(&write(&file), DuplicateMode::Strict).err().unwrap();
+    assert!(matches!(
+        error.root_cause(),
+        AnmReadingError::DuplicateClassKeyError { key } if key == "Key"
+    ));
+
+    assert!(read(&write(&common::file()), DuplicateMode::Strict).is_ok());
+}
+
+#[test]
+fn lenient_mode_keeps_duplicates_and_warns() {
+    let bytes = with_duplicates();
+    let options = ReadOptions::default();
+    assert_eq!(options.duplicates, DuplicateMode::Lenient);
+    let (file, warnings) = AnmFile::read_with_options(&bytes[..], &options).unwrap();
+
+    assert_eq!(
+        warnings,
+        [
+            AnmReadingWarning::DuplicateAnimationName {
+                class_key: "Key".into(),
+                name: "Anim".into(),
+            },
+            AnmReadingWarning::DuplicateClassKey { key: "Key".into() },
+        ]
+    );
+
+    // lookups find the first entry, and every entry is kept in order
+    assert_eq!(file.classes.len(), 2);
+    let first = file.classes.get("Key").unwrap();
+    assert_eq!(first.index, "Index");
+    assert_eq!(first.animations.len(), 2);
+    assert_eq!(first.animations.get("Anim").unwrap().loop_start, 0);
+    let (_, second) = file.classes.get_index(1).unwrap();
+    assert_eq!(second.index, "Second");
+
+    assert_eq!(write(&file), bytes);
+}
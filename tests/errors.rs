@@ -0,0 +1,278 @@
+mod common;
+
+use bhanm::{AnmFile, AnmPath, AnmReadingError, ReadLimit, ReadLimits, ReadOptions};
+use common::file;
+use flate2::{Compression, read::ZlibDecoder, write::ZlibEncoder};
+use std::io::{Read, Write};
+
+/// Writes `file`, then cuts `cut` bytes off the end of its decompressed data.
+fn truncated(cut: usize) -> Vec<u8> {
+    let bytes = common::write(&file());
+
+    let mut decompressed = Vec::new();
+    ZlibDecoder::new(&bytes[4..])
+        .read_to_end(&mut decompressed)
+        .unwrap();
+    decompressed.truncate(decompressed.len() - cut);
+
+    let mut result = bytes[..4].to_vec();
+    let mut zlib = ZlibEncoder::new(&mut result, Compression::default());
+    zlib.write_all(&decompressed).unwrap();
+    zlib.finish().unwrap();
+    result
+}
+
+#[test]
+fn truncated_bone_reports_its_path() {
+    // drop the class list terminator and the last bone's position
+    let bytes = truncated(1 + 4);
+    let error = AnmFile::read(&bytes[..]).err().unwrap();
+
+    let AnmReadingError::ContextError { offset, path, .. } = &error else {
+        panic!("expected a context error, got {error}");
+    };
+    assert_eq!(
+        *path,
+        AnmPath {
+            class_key: Some("Key".into()),
+            animation: Some("Anim".into()),
+            frame: Some(1),
+            bone: Some(2),
+        }
+    );
+    let full = common::write(&file());
+    let mut decompressed = Vec::new();
+    ZlibDecoder::new(&full[4..])
+        .read_to_end(&mut decompressed)
+        .unwrap();
+    assert_eq!(*offset, (decompressed.len() - 1 - 4) as u64);
+    assert!(matches!(error.root_cause(), AnmReadingError::IOError(_)));
+}
+
+#[test]
+fn wrong_frames_byte_size_fails_eagerly_and_lazily() {
+    // one byte of padding after the frames, counted in the stored size
+    let bytes = common::write(&file());
+    let mut decompressed = Vec::new();
+    ZlibDecoder::new(&bytes[4..])
+        .read_to_end(&mut decompressed)
+        .unwrap();
+    let size_offset = 1 + (2 + 3) + (2 + 5) + (2 + 8) + 4 + (2 + 4) + 4 * 6 + 4;
+    let size_field = &mut decompressed[size_offset..size_offset + 4];
+    let size = u32::from_le_bytes(size_field.try_into().unwrap());
+    size_field.copy_from_slice(&(size + 1).to_le_bytes());
+    decompressed.insert(decompressed.len() - 1, 0);
+
+    let mut corrupt = bytes[..4].to_vec();
+    let mut zlib = ZlibEncoder::new(&mut corrupt, Compression::default());
+    zlib.write_all(&decompressed).unwrap();
+    zlib.finish().unwrap();
+
+    let expected = |error: &AnmReadingError| {
+        let size = size as usize;
+        assert!(
+            matches!(
+                error.root_cause(),
+                AnmReadingError::FramesByteSizeMismatchError { byte_count, used }
+                    if *byte_count == size + 1 && *used == size
+            ),
+            "{error}"
+        );
+    };
+    expected(&AnmFile::read(&corrupt[..]).err().unwrap());
+
+    let options = ReadOptions {
+        lazy_frames: true,
+        ..Default::default()
+    };
+    let (lazy, _) = AnmFile::read_with_options(&corrupt[..], &options).unwrap();
+    let animation = lazy
+        .classes
+        .get("Key")
+        .unwrap()
+        .animations
+        .get("Anim")
+        .unwrap();
+    expected(&animation.frames().err().unwrap());
+}
+
+/// `file()`, with a data array of 2 entries.
+fn read_with_limits(limits: ReadLimits) -> Result<AnmFile, AnmReadingError> {
+    let mut file = file();
+    let class = file.classes.get_mut("Key").unwrap();
+    class.animations.get_mut("Anim").unwrap().data = vec![1, 2];
+    let bytes = common::write(&file);
+    let options = ReadOptions {
+        limits,
+        ..Default::default()
+    };
+    AnmFile::read_with_options(&bytes[..], &options).map(|(file, _)| file)
+}
+
+#[test]
+fn limits_are_enforced() {
+    let cases = [
+        (
+            ReadLimit::FrameCount,
+            ReadLimits {
+                max_frames: 1,
+                ..ReadLimits::UNLIMITED
+            },
+        ),
+        (
+            ReadLimit::BoneCount,
+            ReadLimits {
+                max_bones: 2,
+                ..ReadLimits::UNLIMITED
+            },
+        ),
+        (
+            ReadLimit::NameLength,
+            ReadLimits {
+                max_name_length: 4,
+                ..ReadLimits::UNLIMITED
+            },
+        ),
+        (
+            ReadLimit::AnimationCount,
+            ReadLimits {
+                max_animations: 0,
+                ..ReadLimits::UNLIMITED
+            },
+        ),
+        (
+            ReadLimit::DataLength,
+            ReadLimits {
+                max_data_length: 1,
+                ..ReadLimits::UNLIMITED
+            },
+        ),
+        (
+            // 1 and 3 bones
+            ReadLimit::TotalBoneCount,
+            ReadLimits {
+                max_total_bones: 3,
+                ..ReadLimits::UNLIMITED
+            },
+        ),
+        (
+            ReadLimit::DecompressedSize,
+            ReadLimits {
+                max_decompressed_size: 16,
+                ..ReadLimits::UNLIMITED
+            },
+        ),
+    ];
+    for (expected, limits) in cases {
+        let error = read_with_limits(limits).err().unwrap();
+        match error.root_cause() {
+            AnmReadingError::LimitExceededError { limit, .. } => assert_eq!(*limit, expected),
+            other => panic!("expected {expected} to be exceeded, got {other}"),
+        }
+    }
+
+    assert!(read_with_limits(ReadLimits::default()).is_ok());
+    let exact = ReadLimits {
+        max_animations: 1,
+        max_data_length: 2,
+        max_total_bones: 4,
+        ..ReadLimits::UNLIMITED
+    };
+    assert!(read_with_limits(exact).is_ok());
+}
+
+#[test]
+fn cloned_bones_count_towards_the_total() {
+    // every bone after the first frame is cloned, at 2 bytes each
+    let mut file = file();
+    let class = file.classes.get_mut("Key").unwrap();
+    for i in 0..3 {
+        let animation = bhanm::AnmAnimation::new(
+            format!("Clones{i}"),
+            (0..10).map(|_| common::frame(&[1.; 20])).collect(),
+        );
+        class.animations.push(animation);
+    }
+    let bytes = common::write(&file);
+    let limits = ReadLimits {
+        max_total_bones: 400,
+        ..ReadLimits::default()
+    };
+
+    let options = ReadOptions {
+        limits,
+        ..Default::default()
+    };
+    let error = AnmFile::read_with_options(&bytes[..], &options)
+        .err()
+        .unwrap();
+    assert!(matches!(
+        error.root_cause(),
+        AnmReadingError::LimitExceededError {
+            limit: ReadLimit::TotalBoneCount,
+            value: 404,
+            max: 400,
+        }
+    ));
+
+    // lazily, the animations decoded later share the budget of the file
+    let options = ReadOptions {
+        lazy_frames: true,
+        limits,
+        ..Default::default()
+    };
+    let (file, _) = AnmFile::read_with_options(&bytes[..], &options).unwrap();
+    let animations = &file.classes.get("Key").unwrap().animations;
+    for animation in animations.iter().take(2) {
+        assert!(animation.frames().is_ok());
+    }
+    let error = animations.get("Clones1").unwrap().frames().err().unwrap();
+    assert!(matches!(
+        error.root_cause(),
+        AnmReadingError::LimitExceededError {
+            limit: ReadLimit::TotalBoneCount,
+            value: 404,
+            max: 400,
+        }
+    ));
+}
+
+/// Takes everything written, and fails on flush, or once `capacity` bytes are written.
+struct FailingWriter {
+    written: Vec<u8>,
+    capacity: usize,
+}
+
+impl Write for FailingWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if self.written.len() + buf.len() > self.capacity {
+            return Err(std::io::Error::other("disk full"));
+        }
+        self.written.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Err(std::io::Error::other("flush failed"))
+    }
+}
+
+#[test]
+fn write_reports_errors_at_the_end_of_the_file() {
+    let bytes = common::write(&file());
+
+    let writer = FailingWriter {
+        written: Vec::new(),
+        capacity: usize::MAX,
+    };
+    let error = file().write(writer).err().unwrap();
+    assert_eq!(error.to_string(), "flush failed");
+
+    // only the last zlib block doesn't fit
+    let writer = FailingWriter {
+        written: Vec::new(),
+        capacity: bytes.len() - 1,
+    };
+    let error = file().write(writer).err().unwrap();
+    assert_eq!(error.to_string(), "disk full");
+}
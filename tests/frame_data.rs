@@ -0,0 +1,51 @@
+mod common;
+
+use bhanm::frame_data::{frame_data, to_csv, to_json};
+use common::file;
+
+#[test]
+fn rows_have_timing_and_phases() {
+    let mut file = file();
+    let animation = file
+        .classes
+        .get_mut("Key")
+        .unwrap()
+        .animations
+        .get_mut("Anim")
+        .unwrap();
+    animation.name = "Anim, \"quoted\"".into();
+    animation.loop_start = 1;
+    animation.recovery_start = 1;
+    animation.free_start = 2;
+    animation.frames_mut().unwrap()[1].fire_socket = Some((0., 0.));
+
+    let rows = frame_data(&file).unwrap();
+    assert_eq!(rows.len(), 1);
+    let row = &rows[0];
+    assert_eq!(row.fire_socket_frames, vec![1]);
+    assert_eq!(
+        (row.startup, row.looping, row.recovery, row.free),
+        (1, 0, 1, 0)
+    );
+
+    let csv = to_csv(&rows);
+    let mut lines = csv.lines();
+    assert!(
+        lines
+            .next()
+            .unwrap()
+            .starts_with("class_key,animation,frames,")
+    );
+    assert_eq!(
+        lines.next().unwrap(),
+        "Key,\"Anim, \"\"quoted\"\"\",2,1,1,2,0,0,1,1,0,1,0"
+    );
+
+    assert_eq!(
+        to_json(&rows),
+        "[\n  {\"class_key\":\"Key\",\"animation\":\"Anim, \\\"quoted\\\"\",\"frames\":2,\
+         \"loop_start\":1,\"recovery_start\":1,\"free_start\":2,\"base_start\":0,\
+         \"preview_frame\":0,\"fire_socket_frames\":[1],\
+         \"startup\":1,\"looping\":0,\"recovery\":1,\"free\":0}\n]\n"
+    );
+}
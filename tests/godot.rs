@@ -0,0 +1,97 @@
+mod common;
+
+use bhanm::{
+    AnmAnimation,
+    godot::{GodotOptions, animation_tres, scene_tscn},
+};
+use common::{bone, frame};
+
+fn animation() -> AnmAnimation {
+    let mut first = frame(&[1.]);
+    first.fire_socket = Some((4., -2.));
+    let mut second = frame(&[1., 2.]);
+    second.bones[0].frame = 3;
+    second.bones.push(bone(5.));
+    second.bones[2].id = 9;
+    second.bones[2].opacity = 0.5;
+    let mut animation = AnmAnimation::new("Attack".into(), vec![first, second, frame(&[1.])]);
+    animation.loop_start = 1;
+    animation.recovery_start = 2;
+    animation
+}
+
+/// The keys of the track with a path, as the text between its braces.
+fn track_keys<'a>(tres: &'a str, path: &str) -> &'a str {
+    let path_line = format!("/path = NodePath(\"{path}\")");
+    let start = tres
+        .find(&path_line)
+        .unwrap_or_else(|| panic!("no track {path}"));
+    let keys = &tres[start..];
+    let keys = &keys[keys.find("keys = {").unwrap()..];
+    &keys[..keys.find('}').unwrap()]
+}
+
+#[test]
+fn tracks_follow_the_bones() {
+    let tres = animation_tres(&animation(), &GodotOptions::default()).unwrap();
+    assert!(tres.starts_with("[gd_resource type=\"Animation\" format=3]"));
+    assert!(tres.contains("resource_name = \"Attack\"\nlength = 0.125\nloop_mode = 0\n"));
+    assert!(tres.contains("metadata/loop_start = 0.041666668\nmetadata/loop_end = 0.083333336\n"));
+
+    let transform = track_keys(&tres, "Bone1:transform");
+    assert!(transform.contains("\"times\": PackedFloat32Array(0)"));
+    assert!(transform.contains("\"update\": 1"));
+    assert!(transform.contains("Transform2D(2, 0.5, 0.25, 3, 1, 0)"));
+    let transform = track_keys(&tres, "Bone1#2:transform");
+    assert!(transform.contains("\"times\": PackedFloat32Array(0.041666668)"));
+    assert!(transform.contains("Transform2D(2, 0.5, 0.25, 3, 2, 0)"));
+
+    // sprite frames count from 0, and only changes are keyed
+    let frames = track_keys(&tres, "Bone1:frame");
+    assert!(frames.contains("\"values\": [0, 2, 0]"));
+
+    let copy = track_keys(&tres, "Bone1#2:visible");
+    assert!(copy.contains("\"values\": [false, true, false]"));
+    let z_index = track_keys(&tres, "Bone9:z_index");
+    assert!(z_index.contains("\"times\": PackedFloat32Array(0.041666668)"));
+    assert!(z_index.contains("\"values\": [2]"));
+    let alpha = track_keys(&tres, "Bone9:modulate:a");
+    assert!(alpha.contains("\"values\": [0.5]"));
+
+    let socket = track_keys(&tres, "FireSocket:position");
+    assert!(socket.contains("\"values\": [Vector2(4, -2)]"));
+}
+
+#[test]
+fn whole_loops_use_the_loop_mode() {
+    let mut animation = animation();
+    animation.loop_start = 0;
+    animation.recovery_start = 10;
+    let options = GodotOptions {
+        interpolate: true,
+        ..Default::default()
+    };
+    let tres = animation_tres(&animation, &options).unwrap();
+    assert!(tres.contains("loop_mode = 1\n"));
+    assert!(!tres.contains("metadata/"));
+    assert!(track_keys(&tres, "Bone1:transform").contains("\"update\": 0"));
+    assert!(track_keys(&tres, "Bone1:frame").contains("\"update\": 1"));
+}
+
+#[test]
+fn scene_has_a_sprite_per_bone() {
+    let scene = scene_tscn(&animation(), "res://Attack.tres").unwrap();
+    assert!(
+        scene.contains("[ext_resource type=\"Animation\" path=\"res://Attack.tres\" id=\"1\"]")
+    );
+    assert!(scene.contains("[node name=\"Attack\" type=\"Node2D\"]"));
+    assert!(scene.contains(
+        "[node name=\"Bone1\" type=\"Sprite2D\" parent=\".\"]\nvisible = false\nhframes = 3\n"
+    ));
+    assert!(scene.contains(
+        "[node name=\"Bone1#2\" type=\"Sprite2D\" parent=\".\"]\nvisible = false\nhframes = 1\n"
+    ));
+    assert!(scene.contains("[node name=\"Bone9\""));
+    assert!(scene.contains("[node name=\"FireSocket\" type=\"Marker2D\""));
+    assert!(scene.contains("autoplay = \"Attack\""));
+}
@@ -0,0 +1,18 @@
+//! The crate's JSON writing, through the exporters that use it.
+
+mod common;
+
+use bhanm::frame_data::{frame_data, to_json};
+
+#[test]
+fn written_strings_are_escaped() {
+    let mut file = common::file();
+    let class = file.classes.get_mut("Key").unwrap();
+    let name = "\"quotes\" \\ \n\r\t \u{1} é 😀";
+    class.animations.get_mut("Anim").unwrap().name = name.into();
+
+    let json = to_json(&frame_data(&file).unwrap());
+    assert!(json.contains(r#""\"quotes\" \\ \n\r\t \u0001 é 😀""#));
+    let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(value[0]["animation"], name);
+}
@@ -0,0 +1,118 @@
+mod common;
+
+use bhanm::{
+    AnmAnimation,
+    diff::diff,
+    patch_notes::{PatchNotes, Status},
+};
+use common::{file, frame};
+
+#[test]
+fn only_gameplay_changes_are_reported() {
+    let old = file();
+    let mut new = file();
+    {
+        let class = new.classes.get_mut("Key").unwrap();
+        class
+            .animations
+            .push(AnmAnimation::new("<New>_*`x`*".into(), vec![frame(&[])]));
+        let animation = class.animations.get_mut("Anim").unwrap();
+        animation.free_start = 3;
+        animation.preview_frame = 1;
+        let frames = animation.frames_mut().unwrap();
+        frames[1].fire_socket = Some((1., -2.));
+        frames[1].eb_platform_pos = Some((0., 0.));
+        frames[0].bones[0].x = 10.;
+        frames.push(frame(&[]));
+    }
+
+    let notes = PatchNotes::new(&diff(&old, &new).unwrap());
+    assert_eq!(
+        notes.to_markdown(),
+        "\
+## Key
+
+* **Anim**
+  * free_start: 0 -> 3 (+3)
+  * frames: 2 -> 3 (+1)
+  * fire_socket on frame 1: added at (1, -2)
+* **\\<New\\>\\_\\*\\`x\\`\\*** (added)
+
+"
+    );
+    let html = notes.to_html();
+    assert!(html.contains("<strong>&lt;New&gt;_*`x`*</strong> (added)"));
+    assert!(html.contains("<li>free_start: 0 -&gt; 3 (+3)</li>"));
+}
+
+#[test]
+fn cosmetic_changes_are_ignored() {
+    let old = file();
+    let mut new = file();
+    let animation = new
+        .classes
+        .get_mut("Key")
+        .unwrap()
+        .animations
+        .get_mut("Anim")
+        .unwrap();
+    animation.frames_mut().unwrap()[1].bones[2].opacity = 0.;
+
+    let notes = PatchNotes::new(&diff(&old, &new).unwrap());
+    assert!(notes.is_empty());
+    assert_eq!(notes.to_markdown(), "No gameplay changes.\n");
+}
+
+#[test]
+fn duplicate_entries_get_their_own_notes() {
+    // two classes with the same key, the second with two animations with the same name
+    let twice = || {
+        let mut file = file();
+        let mut again = common::file().classes.remove("Key").unwrap();
+        again
+            .animations
+            .push(AnmAnimation::new("Anim".into(), vec![frame(&[])]));
+        file.classes.push("Key".into(), again);
+        file
+    };
+    let old = twice();
+    let mut new = twice();
+    for i in 0..2 {
+        let (_, class) = new.classes.get_index_mut(i).unwrap();
+        class.animations.get_index_mut(0).unwrap().free_start = 3;
+    }
+    new.classes
+        .get_index_mut(1)
+        .unwrap()
+        .1
+        .animations
+        .get_index_mut(1)
+        .unwrap()
+        .free_start = 4;
+
+    let notes = PatchNotes::new(&diff(&old, &new).unwrap());
+    assert_eq!(
+        notes.to_markdown(),
+        "\
+## Key
+
+* **Anim**
+  * free_start: 0 -> 3 (+3)
+
+## Key
+
+* **Anim**
+  * free_start: 0 -> 3 (+3)
+* **Anim**
+  * free_start: 0 -> 4 (+4)
+
+"
+    );
+
+    // the second class is removed, not the one with the changes
+    new.classes.remove_index(1);
+    let notes = PatchNotes::new(&diff(&old, &new).unwrap());
+    let statuses: Vec<_> = notes.classes.iter().map(|c| c.status).collect();
+    assert_eq!(statuses, [Status::Changed, Status::Removed]);
+    assert_eq!(notes.classes[0].animations.len(), 1);
+}
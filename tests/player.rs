@@ -0,0 +1,134 @@
+mod common;
+
+use bhanm::{AnmAnimation, Phase};
+use common::frame;
+
+fn animation(frame_count: usize) -> AnmAnimation {
+    let mut animation = AnmAnimation::new(
+        "Anim".into(),
+        (0..frame_count).map(|_| frame(&[])).collect(),
+    );
+    animation.base_start = 1;
+    animation.loop_start = 2;
+    animation.recovery_start = 4;
+    animation.free_start = 5;
+    animation
+}
+
+#[test]
+fn loops_until_told_to_exit() {
+    let animation = animation(7);
+    let mut player = animation.player();
+    let frames: Vec<_> = (0..6).map(|_| player.tick().frame).collect();
+    assert_eq!(frames, [1, 2, 3, 2, 3, 2]);
+
+    player.exit_loop();
+    let ticks: Vec<_> = (0..5).map(|_| player.tick()).collect();
+    let frames: Vec<_> = ticks.iter().map(|t| t.frame).collect();
+    assert_eq!(frames, [3, 4, 5, 6, 6]);
+    let phases: Vec<_> = ticks.iter().map(|t| t.phase).collect();
+    assert_eq!(
+        phases,
+        [
+            Phase::Loop,
+            Phase::Recovery,
+            Phase::Free,
+            Phase::Free,
+            Phase::Free
+        ]
+    );
+    let became_free: Vec<_> = ticks.iter().map(|t| t.became_free).collect();
+    assert_eq!(became_free, [false, false, true, false, false]);
+    assert!(!ticks[3].finished && ticks[4].finished);
+
+    player.restart();
+    assert_eq!(player.tick().frame, 1);
+    assert_eq!(player.phase(), Phase::Startup);
+}
+
+#[test]
+fn reports_loop_jumps() {
+    let animation = animation(7);
+    let mut player = animation.player();
+    let looped: Vec<_> = (0..4).map(|_| player.tick().looped).collect();
+    assert_eq!(looped, [false, false, false, true]);
+}
+
+#[test]
+fn short_and_empty_animations_finish() {
+    // recovery_start is past the end, so the loop is cut short at the last frame
+    let mut player = animation(3).player();
+    let frames: Vec<_> = (0..4).map(|_| player.tick().frame).collect();
+    assert_eq!(frames, [1, 2, 2, 2]);
+    assert!(!player.is_finished());
+    player.exit_loop();
+    assert!(player.tick().finished);
+
+    let mut player = animation(0).player();
+    assert!(player.tick().finished);
+}
+
+#[test]
+fn zero_timing_fields_are_unset() {
+    let mut zeros = animation(3);
+    zeros.base_start = 0;
+    zeros.loop_start = 0;
+    zeros.recovery_start = 0;
+    zeros.free_start = 0;
+    let mut player = zeros.player();
+    let ticks: Vec<_> = (0..4).map(|_| player.tick()).collect();
+    let frames: Vec<_> = ticks.iter().map(|t| (t.frame, t.phase)).collect();
+    assert_eq!(
+        frames,
+        [
+            (0, Phase::Startup),
+            (1, Phase::Startup),
+            (2, Phase::Startup),
+            (2, Phase::Free)
+        ]
+    );
+    assert!(ticks.iter().all(|t| !t.looped));
+    assert!(ticks[3].finished && ticks[3].became_free);
+    assert!(!player.is_looping());
+
+    // a loop and recovery, but no free_start: free only once finished
+    let mut no_free = animation(6);
+    no_free.free_start = 0;
+    let mut player = no_free.player();
+    player.exit_loop();
+    let phases: Vec<_> = (0..6).map(|_| player.tick().phase).collect();
+    assert_eq!(
+        phases,
+        [
+            Phase::Startup,
+            Phase::Loop,
+            Phase::Loop,
+            Phase::Recovery,
+            Phase::Recovery,
+            Phase::Free
+        ]
+    );
+}
+
+#[test]
+fn phases_stay_in_order() {
+    // recovery_start before loop_start, and free_start before recovery_start
+    let mut backwards = animation(7);
+    backwards.base_start = 0;
+    backwards.loop_start = 3;
+    backwards.recovery_start = 1;
+    backwards.free_start = 2;
+    let mut player = backwards.player();
+    let phases: Vec<_> = (0..5).map(|_| player.tick().phase).collect();
+    assert_eq!(
+        phases,
+        [
+            Phase::Startup,
+            Phase::Startup,
+            Phase::Startup,
+            Phase::Free,
+            Phase::Free
+        ]
+    );
+    assert!(!player.is_looping());
+}
@@ -0,0 +1,105 @@
+mod common;
+
+use bhanm::{
+    Affine2, AnmBone,
+    render::{
+        FillPath, Placeholders, RenderOptions, SpriteSource, TooLargeImageError, bounds,
+        render_bones, render_frame,
+    },
+};
+use common::bone;
+
+/// A 10x10 white square with its corner on the bone's origin.
+struct Square;
+
+impl SpriteSource for Square {
+    fn sprite(&self, _id: i16, _frame: i8) -> Vec<FillPath> {
+        vec![FillPath::rect(0., 0., 10., 10., [1., 1., 1., 1.])]
+    }
+}
+
+fn options() -> RenderOptions {
+    RenderOptions {
+        width: 20,
+        height: 20,
+        origin: (0., 0.),
+        ..Default::default()
+    }
+}
+
+fn square_at(x: f32, y: f32) -> AnmBone {
+    let mut bone = bone(x);
+    bone.y = y;
+    bone.set_transform(Affine2::new(1., 0., 0., 1., x as f64, y as f64));
+    bone
+}
+
+#[test]
+fn fills_are_anti_aliased_and_clipped() {
+    // half a pixel into the canvas on the left, hanging off the bottom
+    let image = render_bones(&[square_at(-9.5, 15.)], &Square, &options()).unwrap();
+    assert_eq!(image.pixel(0, 17), [255, 255, 255, 128]);
+    assert_eq!(image.pixel(1, 17), [0, 0, 0, 0]);
+    assert_eq!(image.pixel(0, 19)[3], 128);
+    assert_eq!(image.pixel(0, 14), [0, 0, 0, 0]);
+
+    let image = render_bones(&[square_at(15., 5.)], &Square, &options()).unwrap();
+    assert_eq!(image.pixel(19, 10), [255, 255, 255, 255]);
+    assert_eq!(image.pixel(14, 10), [0, 0, 0, 0]);
+}
+
+#[test]
+fn bones_are_faded_and_stacked_in_order() {
+    let mut below = square_at(0., 0.);
+    below.opacity = 0.5;
+    let image = render_bones(&[below], &Square, &options()).unwrap();
+    assert_eq!(image.pixel(5, 5), [255, 255, 255, 128]);
+
+    let placeholders = Placeholders::default();
+    let options = RenderOptions {
+        background: [0, 0, 0, 255],
+        ..Default::default()
+    };
+    let mut frame = common::frame(&[0., 0.]);
+    frame.bones[1].id = 2;
+    let both = render_frame(&frame, &placeholders, &options).unwrap();
+    frame.bones.truncate(1);
+    let first = render_frame(&frame, &placeholders, &options).unwrap();
+    frame.bones[0].id = 2;
+    let second = render_frame(&frame, &placeholders, &options).unwrap();
+
+    // placeholders are translucent, so both show, with the second on top
+    let center = both.pixel(128, 128);
+    assert_eq!(center[3], 255);
+    assert_ne!(center, first.pixel(128, 128));
+    assert_ne!(center, second.pixel(128, 128));
+    assert_eq!(both.pixel(0, 0), [0, 0, 0, 255]);
+}
+
+#[test]
+fn bounds_cover_the_sprites() {
+    let (x0, y0, x1, y1) = bounds(&[square_at(-5., 2.), square_at(20., 3.)], &Square).unwrap();
+    assert_eq!((x0, y0, x1, y1), (-5., 2., 30., 13.));
+    assert!(bounds(&[], &Square).is_none());
+}
+
+#[test]
+fn huge_canvases_are_refused() {
+    let options = RenderOptions {
+        width: 1 << 15,
+        ..options()
+    };
+    assert_eq!(
+        render_bones(&[], &Square, &options),
+        Err(TooLargeImageError {
+            width: 1 << 15,
+            height: 20
+        })
+    );
+    let options = RenderOptions {
+        width: 8192,
+        height: 8192,
+        ..options
+    };
+    assert!(render_bones(&[], &Square, &options).is_err());
+}
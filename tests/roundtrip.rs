@@ -0,0 +1,437 @@
+mod common;
+
+use bhanm::{
+    Affine2, AnimationCollection, AnmAnimation, AnmBone, AnmClass, AnmFile, AnmFrame, BoneEncoding,
+    ClassCollection, ReadOptions, StoredBoneEncoding, TransformEncoding,
+};
+use byteorder::{LittleEndian as LE, ReadBytesExt};
+use common::write;
+use flate2::{Compression, read::ZlibDecoder, write::ZlibEncoder};
+use proptest::prelude::*;
+use std::io::{Read, Write};
+
+/// Describes a bone in terms of which compression path it should be able to take.
+#[derive(Clone, Debug)]
+struct BoneRecipe {
+    clone_prev_frame: bool,
+    new_sprite_frame: Option<i8>,
+    id: i16,
+    transform: TransformRecipe,
+    copy_position: bool,
+    position: (f32, f32),
+    frame: i8,
+    opacity: u8,
+    encoding: Option<BoneEncoding>,
+}
+
+/// A frame's fire socket and bones.
+type FrameRecipe = (Option<(f64, f64)>, Vec<BoneRecipe>);
+
+#[derive(Clone, Debug)]
+enum TransformRecipe {
+    CopyPrev,
+    Identity,
+    Symmetric(f32, f32),
+    Full(f32, f32, f32, f32),
+}
+
+fn coord() -> impl Strategy<Value = f32> {
+    prop_oneof![Just(0.), Just(1.), Just(-1.), -1000f32..1000f32]
+}
+
+fn transform_recipe() -> impl Strategy<Value = TransformRecipe> {
+    prop_oneof![
+        Just(TransformRecipe::CopyPrev),
+        Just(TransformRecipe::Identity),
+        (coord(), coord()).prop_map(|(a, b)| TransformRecipe::Symmetric(a, b)),
+        (coord(), coord(), coord(), coord())
+            .prop_map(|(a, b, c, d)| TransformRecipe::Full(a, b, c, d)),
+    ]
+}
+
+fn transform_encoding() -> impl Strategy<Value = TransformEncoding> {
+    prop_oneof![
+        Just(TransformEncoding::CopyPrev),
+        Just(TransformEncoding::Identity),
+        Just(TransformEncoding::Symmetric),
+        Just(TransformEncoding::Full),
+    ]
+}
+
+/// Arbitrary recorded encodings, valid or not for the bone they end up on.
+fn bone_encoding() -> impl Strategy<Value = BoneEncoding> {
+    prop_oneof![
+        any::<bool>().prop_map(|keep_frame| BoneEncoding::ClonePrevFrame { keep_frame }),
+        (
+            transform_encoding(),
+            any::<bool>(),
+            any::<bool>(),
+            any::<bool>()
+        )
+            .prop_map(|(transform, copy_position, has_frame, opaque)| {
+                BoneEncoding::Stored(StoredBoneEncoding {
+                    transform,
+                    copy_position,
+                    has_frame,
+                    opaque,
+                })
+            }),
+    ]
+}
+
+fn bone_recipe() -> impl Strategy<Value = BoneRecipe> {
+    (
+        any::<bool>(),
+        prop::option::of(any::<i8>()),
+        0i16..8,
+        transform_recipe(),
+        any::<bool>(),
+        (coord(), coord()),
+        prop_oneof![Just(1i8), any::<i8>()],
+        prop_oneof![Just(255u8), any::<u8>()],
+        prop::option::of(bone_encoding()),
+    )
+        .prop_map(
+            |(
+                clone_prev_frame,
+                new_sprite_frame,
+                id,
+                transform,
+                copy_position,
+                position,
+                frame,
+                opacity,
+                encoding,
+            )| BoneRecipe {
+                clone_prev_frame,
+                new_sprite_frame,
+                id,
+                transform,
+                copy_position,
+                position,
+                frame,
+                opacity,
+                encoding,
+            },
+        )
+}
+
+fn build_bone(
+    recipe: &BoneRecipe,
+    prev_frame_bone: Option<&AnmBone>,
+    prev: Option<&AnmBone>,
+) -> AnmBone {
+    if let Some(prev_frame_bone) = prev_frame_bone.filter(|_| recipe.clone_prev_frame) {
+        let mut bone = prev_frame_bone.clone();
+        bone.frame = recipe.new_sprite_frame.unwrap_or(prev_frame_bone.frame);
+        bone.set_encoding(recipe.encoding);
+        return bone;
+    }
+
+    let (scale_x, rotate_skew0, rotate_skew1, scale_y) = match (&recipe.transform, prev) {
+        (TransformRecipe::CopyPrev, Some(prev)) => (
+            prev.scale_x,
+            prev.rotate_skew0,
+            prev.rotate_skew1,
+            prev.scale_y,
+        ),
+        (TransformRecipe::CopyPrev, None) | (TransformRecipe::Identity, _) => (1., 0., 0., 1.),
+        (TransformRecipe::Symmetric(a, b), _) => (*a, *b, *b, -*a),
+        (TransformRecipe::Full(a, b, c, d), _) => (*a, *b, *c, *d),
+    };
+    let (x, y) = match prev {
+        Some(prev) if recipe.copy_position => (prev.x, prev.y),
+        _ => recipe.position,
+    };
+
+    let mut bone = AnmBone::new(recipe.id);
+    bone.scale_x = scale_x;
+    bone.rotate_skew0 = rotate_skew0;
+    bone.rotate_skew1 = rotate_skew1;
+    bone.scale_y = scale_y;
+    bone.x = x;
+    bone.y = y;
+    bone.opacity = recipe.opacity as f64 / 255.;
+    bone.frame = recipe.frame;
+    bone.set_encoding(recipe.encoding);
+    bone
+}
+
+fn build_frames(recipes: &[FrameRecipe]) -> Vec<AnmFrame> {
+    let mut frames: Vec<AnmFrame> = Vec::new();
+    for (i, (fire_socket, bone_recipes)) in recipes.iter().enumerate() {
+        let mut bones: Vec<AnmBone> = Vec::new();
+        for (j, recipe) in bone_recipes.iter().enumerate() {
+            let prev_frame_bone = frames.last().and_then(|f| f.bones.get(j));
+            bones.push(build_bone(recipe, prev_frame_bone, bones.last()));
+        }
+        frames.push(AnmFrame {
+            id: i as i16,
+            bones,
+            fire_socket: *fire_socket,
+            eb_platform_pos: fire_socket.map(|(x, y)| (y, x)),
+        });
+    }
+    frames
+}
+
+fn build_file(frames: Vec<AnmFrame>) -> AnmFile {
+    let mut animations = AnimationCollection::new();
+    let mut animation = AnmAnimation::new("Anim".into(), frames);
+    animation.loop_start = 1;
+    animation.recovery_start = 2;
+    animation.free_start = 3;
+    animation.preview_frame = 4;
+    animation.base_start = 5;
+    animation.data = vec![6, 7];
+    animations.insert(animation);
+    let mut classes = ClassCollection::new();
+    classes.insert(
+        "Key".into(),
+        AnmClass {
+            index: "Index".into(),
+            file_name: "File.swf".into(),
+            animations,
+        },
+    );
+    AnmFile::new(0, classes)
+}
+
+fn frames_of(file: &AnmFile) -> &[AnmFrame] {
+    file.classes
+        .get("Key")
+        .unwrap()
+        .animations
+        .get("Anim")
+        .unwrap()
+        .frames()
+        .unwrap()
+}
+
+/// Checks the frames byte size field of the single animation written by `build_file`.
+fn assert_frames_byte_size(bytes: &[u8]) {
+    let mut decompressed = Vec::new();
+    ZlibDecoder::new(&bytes[4..])
+        .read_to_end(&mut decompressed)
+        .unwrap();
+    let header_len = 1 + (2 + 3) + (2 + 5) + (2 + 8) + 4 + (2 + 4) + 4 * 6 + (4 + 4 * 2);
+    let byte_count = (&decompressed[header_len..]).read_u32::<LE>().unwrap() as usize;
+    // the frames are followed by the class list terminator
+    assert_eq!(byte_count, decompressed.len() - header_len - 4 - 1);
+}
+
+fn assert_same_bones(expected: &[AnmFrame], actual: &[AnmFrame]) {
+    assert_eq!(expected.len(), actual.len());
+    for (expected, actual) in expected.iter().zip(actual) {
+        assert_eq!(expected.id, actual.id);
+        assert_eq!(expected.fire_socket, actual.fire_socket);
+        assert_eq!(expected.eb_platform_pos, actual.eb_platform_pos);
+        assert_eq!(expected.bones.len(), actual.bones.len());
+        for (expected, actual) in expected.bones.iter().zip(&actual.bones) {
+            assert_eq!(expected.id, actual.id);
+            assert_eq!(expected.scale_x.to_bits(), actual.scale_x.to_bits());
+            assert_eq!(
+                expected.rotate_skew0.to_bits(),
+                actual.rotate_skew0.to_bits()
+            );
+            assert_eq!(
+                expected.rotate_skew1.to_bits(),
+                actual.rotate_skew1.to_bits()
+            );
+            assert_eq!(expected.scale_y.to_bits(), actual.scale_y.to_bits());
+            assert_eq!(expected.x.to_bits(), actual.x.to_bits());
+            assert_eq!(expected.y.to_bits(), actual.y.to_bits());
+            assert_eq!(expected.opacity, actual.opacity);
+            assert_eq!(expected.frame, actual.frame);
+        }
+    }
+}
+
+fn frame_recipes() -> impl Strategy<Value = Vec<FrameRecipe>> {
+    prop::collection::vec(
+        (
+            prop::option::of((-100f64..100f64, -100f64..100f64)),
+            prop::collection::vec(bone_recipe(), 0..6),
+        ),
+        0..6,
+    )
+}
+
+/// A stored bone for every combination of flags, each after a bone it can copy from,
+/// then a frame of clones with and without their own sprite frame.
+fn every_encoding() -> Vec<AnmFrame> {
+    let transforms = [
+        TransformEncoding::CopyPrev,
+        TransformEncoding::Identity,
+        TransformEncoding::Symmetric,
+        TransformEncoding::Full,
+    ];
+    let mut bones = vec![common::bone(0.)];
+    for transform in transforms {
+        for copy_position in [false, true] {
+            for has_frame in [false, true] {
+                for opaque in [false, true] {
+                    let mut bone = bones.last().unwrap().clone();
+                    match transform {
+                        TransformEncoding::CopyPrev => {}
+                        TransformEncoding::Identity => {
+                            bone.set_transform(Affine2::new(1., 0., 0., 1., 0., 0.))
+                        }
+                        TransformEncoding::Symmetric => {
+                            bone.set_transform(Affine2::new(2., 0.5, 0.5, -2., 0., 0.))
+                        }
+                        TransformEncoding::Full => {
+                            bone.set_transform(Affine2::new(2., 0.5, 0.25, 3., 0., 0.))
+                        }
+                    }
+                    let prev = bones.last().unwrap();
+                    (bone.x, bone.y) = if copy_position {
+                        (prev.x, prev.y)
+                    } else {
+                        (prev.x + 1., 2.)
+                    };
+                    bone.frame = if has_frame { 3 } else { 1 };
+                    bone.opacity = if opaque { 1. } else { 0.4 };
+                    bone.set_encoding(Some(BoneEncoding::Stored(StoredBoneEncoding {
+                        transform,
+                        copy_position,
+                        has_frame,
+                        opaque,
+                    })));
+                    bones.push(bone);
+                }
+            }
+        }
+    }
+    // a sprite frame of 1 that is stored anyway
+    bones[0].set_encoding(Some(BoneEncoding::Stored(StoredBoneEncoding {
+        transform: TransformEncoding::Full,
+        copy_position: false,
+        has_frame: true,
+        opaque: true,
+    })));
+
+    let clones = bones
+        .iter()
+        .enumerate()
+        .map(|(i, bone)| {
+            let mut clone = bone.clone();
+            clone.set_encoding(Some(BoneEncoding::ClonePrevFrame {
+                keep_frame: i % 2 == 0,
+            }));
+            clone
+        })
+        .collect();
+    let frame = |id, bones| AnmFrame {
+        id,
+        bones,
+        fire_socket: None,
+        eb_platform_pos: None,
+    };
+    vec![frame(0, bones), frame(1, clones)]
+}
+
+#[test]
+fn every_encoding_flag_round_trips() {
+    let frames = every_encoding();
+    let bytes = write(&build_file(every_encoding()));
+    let read = AnmFile::read(&bytes[..]).unwrap();
+    assert_same_bones(&frames, frames_of(&read));
+    for (expected, actual) in frames.iter().zip(frames_of(&read)) {
+        for (expected, actual) in expected.bones.iter().zip(&actual.bones) {
+            assert_eq!(expected.encoding(), actual.encoding());
+        }
+    }
+    assert_eq!(write(&read), bytes);
+}
+
+/// The file with its data compressed at another level, like an encoder other than ours would.
+fn recompressed(bytes: &[u8], level: Compression) -> Vec<u8> {
+    let mut data = Vec::new();
+    ZlibDecoder::new(&bytes[4..])
+        .read_to_end(&mut data)
+        .unwrap();
+    let mut zlib = ZlibEncoder::new(bytes[..4].to_vec(), level);
+    zlib.write_all(&data).unwrap();
+    zlib.finish().unwrap()
+}
+
+#[test]
+fn compressed_stream_is_kept_until_the_data_changes() {
+    let ours = write(&build_file(every_encoding()));
+    let fixture = recompressed(&ours, Compression::fast());
+    assert_ne!(fixture, ours);
+
+    for lazy_frames in [false, true] {
+        let options = ReadOptions {
+            lazy_frames,
+            ..Default::default()
+        };
+        let (mut file, _) = AnmFile::read_with_options(&fixture[..], &options).unwrap();
+        assert_eq!(write(&file), fixture);
+
+        // the header is not compressed
+        file.header = 7;
+        assert_eq!(write(&file)[4..], fixture[4..]);
+
+        file.classes.get_mut("Key").unwrap().index = "Other".into();
+        let edited = write(&file);
+        assert_ne!(edited[4..], fixture[4..]);
+        let read = AnmFile::read(&edited[..]).unwrap();
+        assert_eq!(read.classes.get("Key").unwrap().index, "Other");
+        assert_same_bones(&every_encoding(), frames_of(&read));
+    }
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(64))]
+
+    #[test]
+    fn written_values_read_back(recipes in frame_recipes()) {
+        let bytes = write(&build_file(build_frames(&recipes)));
+        assert_frames_byte_size(&bytes);
+
+        let read = AnmFile::read(&bytes[..]).unwrap();
+        assert_same_bones(&build_frames(&recipes), frames_of(&read));
+    }
+
+    #[test]
+    fn rewriting_is_byte_stable(recipes in frame_recipes()) {
+        let bytes = write(&build_file(build_frames(&recipes)));
+        let read = AnmFile::read(&bytes[..]).unwrap();
+        prop_assert_eq!(write(&read), bytes);
+    }
+
+    #[test]
+    fn recorded_encodings_survive_edits(recipes in frame_recipes(), dx in 1f32..10f32) {
+        let bytes = write(&build_file(build_frames(&recipes)));
+        let mut read = AnmFile::read(&bytes[..]).unwrap();
+
+        // moving a bone invalidates clones and position copies that were recorded on read
+        let animation = read.classes.get_mut("Key").unwrap().animations.get_mut("Anim").unwrap();
+        for frame in animation.frames_mut().unwrap().iter_mut() {
+            for bone in frame.bones.iter_mut().step_by(2) {
+                bone.x += dx;
+            }
+        }
+
+        let bytes = write(&read);
+        assert_frames_byte_size(&bytes);
+        assert_same_bones(frames_of(&read), frames_of(&AnmFile::read(&bytes[..]).unwrap()));
+    }
+
+    #[test]
+    fn lazy_reading_matches_eager_reading(recipes in frame_recipes()) {
+        let bytes = write(&build_file(build_frames(&recipes)));
+        let options = ReadOptions {
+            lazy_frames: true,
+            ..Default::default()
+        };
+        let (lazy, _) = AnmFile::read_with_options(&bytes[..], &options).unwrap();
+        prop_assert_eq!(write(&lazy), bytes.clone());
+
+        let eager = AnmFile::read(&bytes[..]).unwrap();
+        assert_same_bones(frames_of(&eager), frames_of(&lazy));
+        prop_assert_eq!(write(&lazy), bytes);
+    }
+}
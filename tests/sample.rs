@@ -0,0 +1,81 @@
+mod common;
+
+use bhanm::AnmAnimation;
+use common::frame;
+use std::f64::consts::FRAC_PI_2;
+
+fn animation() -> AnmAnimation {
+    let mut frames = vec![frame(&[0., 0.]), frame(&[10., 0.]), frame(&[0.])];
+    for bone in &mut frames[0].bones {
+        bone.set_rotation(0.);
+        bone.opacity = 0.;
+    }
+    frames[1].bones[0].set_rotation(-FRAC_PI_2);
+    frames[1].bones[1].frame = 2;
+    AnmAnimation::new("Anim".into(), frames)
+}
+
+#[test]
+fn blends_between_frames() {
+    let bones = animation().sample(0.5).unwrap();
+    assert_eq!(bones.len(), 2);
+    let bone = &bones[0];
+    assert_eq!(bone.x, 5.);
+    assert_eq!(bone.opacity, 0.5);
+    assert!((bone.rotation() + FRAC_PI_2 / 2.).abs() < 1e-6);
+    let (sx, sy) = bone.scale();
+    let (sx0, sy0) = animation().frames().unwrap()[0].bones[0].scale();
+    assert!((sx - sx0).abs() < 1e-5 && (sy - sy0).abs() < 1e-5);
+}
+
+#[test]
+fn sprite_changes_snap() {
+    let bone = &animation().sample(0.9).unwrap()[1];
+    assert_eq!(bone.frame, 1);
+    assert_eq!(bone.opacity, 0.);
+    assert_eq!(animation().sample(1.).unwrap()[1].frame, 2);
+}
+
+#[test]
+fn holds_across_bone_count_changes_and_clamps() {
+    let animation = animation();
+    assert_eq!(animation.sample(1.5).unwrap().len(), 2);
+    assert_eq!(animation.sample(2.).unwrap().len(), 1);
+    assert_eq!(animation.sample(100.).unwrap().len(), 1);
+    assert_eq!(animation.sample(-1.).unwrap()[0].x, 0.);
+    assert!(
+        AnmAnimation::new("Empty".into(), vec![])
+            .sample(0.)
+            .unwrap()
+            .is_empty()
+    );
+}
+
+#[test]
+fn flips_keep_the_matrix_but_move() {
+    let mut frames = vec![frame(&[0.]), frame(&[10.])];
+    frames[1].bones[0].set_flipped(true);
+    frames[1].bones[0].x = 10.;
+    frames[1].bones[0].opacity = 0.;
+    let animation = AnmAnimation::new("Anim".into(), frames);
+
+    let bone = &animation.sample(0.5).unwrap()[0];
+    let first = &animation.frames().unwrap()[0].bones[0];
+    assert!(!bone.is_flipped());
+    assert_eq!(
+        [
+            bone.scale_x,
+            bone.rotate_skew0,
+            bone.rotate_skew1,
+            bone.scale_y
+        ],
+        [
+            first.scale_x,
+            first.rotate_skew0,
+            first.rotate_skew1,
+            first.scale_y
+        ]
+    );
+    assert_eq!(bone.x, 5.);
+    assert_eq!(bone.opacity, 0.5);
+}
@@ -0,0 +1,61 @@
+#![cfg(feature = "serde")]
+
+mod common;
+
+use bhanm::{AnmFile, ReadOptions};
+use common::write;
+
+#[test]
+fn json_round_trip_is_byte_stable() {
+    let bytes = write(&common::file());
+    let options = ReadOptions {
+        lazy_frames: true,
+        ..Default::default()
+    };
+    let (read, _) = AnmFile::read_with_options(&bytes[..], &options).unwrap();
+
+    let json = serde_json::to_string(&read).unwrap();
+    let from_json: AnmFile = serde_json::from_str(&json).unwrap();
+    assert_eq!(write(&from_json), bytes);
+}
+
+#[test]
+fn points_are_readable() {
+    let mut file = common::file();
+    let animation = file
+        .classes
+        .get_mut("Key")
+        .unwrap()
+        .animations
+        .get_mut("Anim")
+        .unwrap();
+    animation.frames_mut().unwrap()[0].fire_socket = Some((1.5, -2.));
+
+    let json = serde_json::to_value(&file).unwrap();
+    let frame = &json["classes"][0]["animations"][0]["frames"][0];
+    assert_eq!(
+        frame["fire_socket"],
+        serde_json::json!({ "x": 1.5, "y": -2.0 })
+    );
+    assert_eq!(frame["eb_platform_pos"], serde_json::Value::Null);
+}
+
+#[test]
+fn duplicate_class_keys_survive() {
+    let mut file = common::file();
+    let mut second = common::file().classes.remove("Key").unwrap();
+    second.index = "Second".into();
+    file.classes.push("Key".into(), second);
+
+    let json = serde_json::to_value(&file).unwrap();
+    assert_eq!(json["classes"][1]["key"], "Key");
+    assert_eq!(json["classes"][1]["index"], "Second");
+    let from_json: AnmFile = serde_json::from_value(json).unwrap();
+    let indices: Vec<_> = from_json
+        .classes
+        .iter()
+        .map(|(key, class)| (key, class.index.as_str()))
+        .collect();
+    assert_eq!(indices, [("Key", "Index"), ("Key", "Second")]);
+    assert_eq!(write(&from_json), write(&file));
+}
@@ -0,0 +1,447 @@
+mod common;
+
+use bhanm::{
+    AnimationCollection, AnmAnimation, AnmBone, AnmClass, AnmFrame,
+    spine::{SpineOptions, SpineTransform, to_json},
+};
+#[cfg(feature = "spine-import")]
+use bhanm::{
+    AnmFile,
+    spine::{SpineImportError, SpineImportOptions, from_json},
+};
+use common::{bone, file, frame};
+use serde_json::Value;
+
+fn bone_with(id: i16, matrix: [f32; 4]) -> AnmBone {
+    let [scale_x, rotate_skew0, rotate_skew1, scale_y] = matrix;
+    let mut bone = bone(10.);
+    bone.id = id;
+    bone.scale_x = scale_x;
+    bone.rotate_skew0 = rotate_skew0;
+    bone.rotate_skew1 = rotate_skew1;
+    bone.scale_y = scale_y;
+    bone.y = 5.;
+    bone
+}
+
+/// Spine's matrix for a bone, mirrored back to y down.
+fn spine_matrix(t: &SpineTransform) -> [f64; 6] {
+    let (rx, ry) = (t.rotation + t.shear_x, t.rotation + 90. + t.shear_y);
+    let a = rx.to_radians().cos() * t.scale_x;
+    let b = ry.to_radians().cos() * t.scale_y;
+    let c = rx.to_radians().sin() * t.scale_x;
+    let d = ry.to_radians().sin() * t.scale_y;
+    [a, -c, -b, d, t.x, -t.y]
+}
+
+#[test]
+fn transforms_match_spines_bone_matrix() {
+    let matrices = [
+        [1., 0., 0., 1.],
+        [2., 0.5, 0.25, 3.],
+        [-1., 0., 0., 1.],
+        [0.5, -0.8, 0.8, -0.5],
+        [0., 1., 1., 0.],
+    ];
+    for matrix in matrices {
+        let bone = bone_with(1, matrix);
+        let m = bone.transform();
+        let expected = [m.a, m.b, m.c, m.d, m.tx, m.ty];
+        let actual = spine_matrix(&SpineTransform::from_bone(&bone));
+        for (a, e) in actual.iter().zip(expected) {
+            assert!(
+                (a - e).abs() < 1e-5,
+                "{matrix:?}: {actual:?} != {expected:?}"
+            );
+        }
+    }
+}
+
+fn class() -> AnmClass {
+    let mut head = bone_with(7, [1., 0., 0., 1.]);
+    head.frame = 2;
+    head.opacity = 0.5;
+    let body = bone_with(3, [1., 0., 0., 1.]);
+    let frames = vec![
+        AnmFrame {
+            bones: vec![body.clone(), head.clone()],
+            ..frame(&[])
+        },
+        AnmFrame {
+            bones: vec![head.clone(), body.clone()],
+            ..frame(&[])
+        },
+        AnmFrame {
+            bones: vec![body],
+            ..frame(&[])
+        },
+    ];
+    let mut animations = AnimationCollection::new();
+    animations.insert(AnmAnimation::new("Swing".into(), frames));
+    AnmClass {
+        index: "0".into(),
+        file_name: "Animation_Test.swf".into(),
+        animations,
+    }
+}
+
+/// Spine's way of applying draw order offsets to the setup order.
+fn apply_offsets(slot_names: &[&str], offsets: &[Value]) -> Vec<String> {
+    let count = slot_names.len();
+    let mut order: Vec<Option<usize>> = vec![None; count];
+    let mut unchanged = Vec::new();
+    let mut original = 0;
+    for offset in offsets {
+        let slot = slot_names
+            .iter()
+            .position(|name| *name == offset["slot"])
+            .unwrap();
+        while original != slot {
+            unchanged.push(original);
+            original += 1;
+        }
+        let target = original as i64 + offset["offset"].as_i64().unwrap();
+        order[target as usize] = Some(original);
+        original += 1;
+    }
+    unchanged.extend(original..count);
+    for position in (0..count).rev() {
+        if order[position].is_none() {
+            order[position] = unchanged.pop();
+        }
+    }
+    order
+        .into_iter()
+        .map(|i| slot_names[i.unwrap()].to_string())
+        .collect()
+}
+
+#[test]
+fn exports_slots_attachments_and_timelines() {
+    let options = SpineOptions {
+        bone_names: [(7, "a_Head".to_string())].into(),
+        ..Default::default()
+    };
+    let json: Value = serde_json::from_str(&to_json(&class(), &options).unwrap()).unwrap();
+
+    let bones: Vec<_> = json["bones"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|b| &b["name"])
+        .collect();
+    assert_eq!(bones, ["root", "3", "a_Head"]);
+    let slots: Vec<&str> = json["slots"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|s| s["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(slots, ["3", "a_Head"]);
+    let head = &json["skins"][0]["attachments"]["a_Head"]["a_Head_2"];
+    assert_eq!((&head["width"], &head["height"]), (&40.into(), &40.into()));
+
+    let animation = &json["animations"]["Swing"];
+    let attachments = &animation["slots"]["a_Head"]["attachment"];
+    assert_eq!(attachments[0]["name"], "a_Head_2");
+    assert_eq!(attachments[1]["time"], 2. / 24.);
+    assert_eq!(attachments[1]["name"], Value::Null);
+    assert_eq!(animation["slots"]["a_Head"]["rgba"][0]["color"], "ffffff80");
+
+    // unchanged values are only keyed once, and y is mirrored
+    let translate = animation["bones"]["3"]["translate"].as_array().unwrap();
+    assert_eq!(translate.len(), 1);
+    let position = (translate[0]["x"].as_f64(), translate[0]["y"].as_f64());
+    assert_eq!(position, (Some(10.), Some(-5.)));
+    assert_eq!(translate[0]["curve"], "stepped");
+
+    let draw_order = animation["drawOrder"].as_array().unwrap();
+    assert_eq!(draw_order.len(), 2);
+    assert_eq!(draw_order[0]["time"], 1. / 24.);
+    let order = apply_offsets(&slots, draw_order[0]["offsets"].as_array().unwrap());
+    assert_eq!(order, ["a_Head", "3"]);
+    let order = apply_offsets(&slots, draw_order[1]["offsets"].as_array().unwrap());
+    assert_eq!(order, ["3", "a_Head"]);
+}
+
+#[test]
+fn copies_of_a_bone_get_their_own_slots() {
+    let file = file();
+    let class = file.classes.get("Key").unwrap();
+    let json: Value =
+        serde_json::from_str(&to_json(class, &SpineOptions::default()).unwrap()).unwrap();
+    let slots: Vec<_> = json["slots"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|s| &s["name"])
+        .collect();
+    assert_eq!(slots, ["1", "1#2", "1#3"]);
+    let rotate = &json["animations"]["Anim"]["bones"]["1#2"]["rotate"];
+    assert_eq!(rotate[0]["time"], 1. / 24.);
+}
+
+#[test]
+fn bone_and_slot_names_are_unique() {
+    let mut file = file();
+    let class = file.classes.get_mut("Key").unwrap();
+    let frames = class
+        .animations
+        .get_mut("Anim")
+        .unwrap()
+        .frames_mut()
+        .unwrap();
+    for (bone, id) in frames[1].bones.iter_mut().zip([2, 3, 4]) {
+        bone.id = id;
+    }
+    let options = SpineOptions {
+        bone_names: [
+            (1, "Head".to_string()),
+            (2, "Head".to_string()),
+            (3, "root".to_string()),
+            (4, "Head#2".to_string()),
+        ]
+        .into(),
+        ..Default::default()
+    };
+    let json: Value = serde_json::from_str(&to_json(class, &options).unwrap()).unwrap();
+    let names = |list: &str| -> Vec<_> {
+        json[list]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|s| s["name"].as_str().unwrap().to_string())
+            .collect()
+    };
+    assert_eq!(
+        names("bones"),
+        ["root", "Head", "Head~2", "root~2", "Head#2"]
+    );
+    assert_eq!(names("slots"), ["Head", "Head~2", "root~2", "Head#2"]);
+    let slot_bones: Vec<_> = json["slots"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|s| s["bone"].as_str().unwrap())
+        .collect();
+    assert_eq!(slot_bones, names("slots"));
+    assert!(json["animations"]["Anim"]["bones"]["Head~2"].is_object());
+}
+
+#[cfg(feature = "spine-import")]
+#[test]
+fn imports_what_was_exported() {
+    let class = class();
+    let export_options = SpineOptions {
+        bone_names: [(7, "a_Head".to_string())].into(),
+        ..Default::default()
+    };
+    let import_options = SpineImportOptions {
+        bone_ids: [("a_Head".to_string(), 7)].into(),
+        ..Default::default()
+    };
+    let json = to_json(&class, &export_options).unwrap();
+    let animations = from_json(&json, &import_options).unwrap();
+    assert_eq!(animations.len(), 1);
+    assert_eq!(animations[0].name, "Swing");
+
+    let original = class.animations.get("Swing").unwrap().frames().unwrap();
+    let imported = animations[0].frames().unwrap();
+    assert_eq!(imported.len(), original.len());
+    for (original, imported) in original.iter().zip(imported) {
+        let ids =
+            |frame: &AnmFrame| -> Vec<_> { frame.bones.iter().map(|b| (b.id, b.frame)).collect() };
+        assert_eq!(ids(imported), ids(original));
+        for (a, b) in original.bones.iter().zip(&imported.bones) {
+            let (a, b) = (a.transform(), b.transform());
+            let pairs = [
+                (a.a, b.a),
+                (a.b, b.b),
+                (a.c, b.c),
+                (a.d, b.d),
+                (a.tx, b.tx),
+                (a.ty, b.ty),
+            ];
+            assert!(
+                pairs.iter().all(|(a, b)| (a - b).abs() < 1e-4),
+                "{a:?} != {b:?}"
+            );
+        }
+        let opacities = |frame: &AnmFrame| -> Vec<_> {
+            frame
+                .bones
+                .iter()
+                .map(|b| (b.opacity * 100.).round())
+                .collect()
+        };
+        assert_eq!(opacities(imported), opacities(original));
+    }
+
+    let mut file = file();
+    let class = file.classes.get_mut("Key").unwrap();
+    for animation in animations {
+        class.animations.insert(animation);
+    }
+    let bytes = common::write(&file);
+    let file = AnmFile::read(&bytes[..]).unwrap();
+    let swing = file
+        .classes
+        .get("Key")
+        .unwrap()
+        .animations
+        .get("Swing")
+        .unwrap();
+    assert_eq!(swing.frames().unwrap().len(), 3);
+}
+
+#[cfg(feature = "spine-import")]
+const SKELETON: &str = r#"{
+"skeleton":{"spine":"4.1"},
+"bones":[
+  {"name":"root"},
+  {"name":"arm","parent":"root","rotation":90},
+  {"name":"hand","parent":"arm","x":10}
+],
+"slots":[
+  {"name":"hand","bone":"hand","attachment":"5_2","color":"ffffff80"},
+  {"name":"marker","bone":"root","attachment":"socket"}
+],
+"skins":[{"name":"default","attachments":{
+  "hand":{"5_2":{"x":1,"width":10,"height":10},"Fist":{"path":"5_3"}},
+  "marker":{"socket":{"type":"point"}}
+}}],
+"animations":{
+  "Punch":{
+    "bones":{"arm":{"rotate":[
+      {"time":0,"value":0},
+      {"time":0.5,"value":-90,"curve":"stepped"},
+      {"time":1,"value":-90}
+    ]}},
+    "slots":{"hand":{"attachment":[{"time":0.75,"name":"Fist"}]}}
+  }
+}
+}"#;
+
+#[cfg(feature = "spine-import")]
+#[test]
+fn flattens_the_bone_hierarchy() {
+    let options = SpineImportOptions {
+        fps: 4.,
+        ..Default::default()
+    };
+    let animations = from_json(SKELETON, &options).unwrap();
+    let frames = animations[0].frames().unwrap();
+    assert_eq!(frames.len(), 5);
+    assert!(frames.iter().all(|frame| frame.bones.len() == 1));
+
+    // the arm points up, which is -y in the game, and the attachment is 1 further along it
+    let hand = &frames[0].bones[0];
+    assert_eq!((hand.id, hand.frame), (5, 2));
+    assert!((hand.x - 0.).abs() < 1e-4 && (hand.y + 11.).abs() < 1e-4);
+    assert!((hand.opacity - 128. / 255.).abs() < 1e-9);
+    let rotation = hand.rotation().to_degrees();
+    assert!((rotation + 90.).abs() < 1e-4, "{rotation}");
+
+    // halfway through the linear key
+    let hand = &frames[1].bones[0];
+    let expected = 11. * std::f64::consts::FRAC_1_SQRT_2;
+    assert!((hand.x as f64 - expected).abs() < 1e-4 && (hand.y as f64 + expected).abs() < 1e-4);
+
+    // the arm points along x, and stays there, while the fist has no offset
+    for (frame, x, sprite) in [(2, 11., 2), (3, 10., 3), (4, 10., 3)] {
+        let hand = &frames[frame].bones[0];
+        assert!((hand.x - x).abs() < 1e-4 && hand.y.abs() < 1e-4);
+        assert_eq!(hand.frame, sprite);
+    }
+}
+
+#[cfg(feature = "spine-import")]
+#[test]
+fn reports_bad_input() {
+    let error = from_json("{\"bones\":[}", &SpineImportOptions::default())
+        .err()
+        .unwrap();
+    assert!(matches!(error, SpineImportError::JsonError(e) if e.column() == 11));
+
+    let json = SKELETON.replace("\"5_3\"", "\"Fist\"");
+    let error = from_json(&json, &SpineImportOptions::default())
+        .err()
+        .unwrap();
+    assert!(matches!(error, SpineImportError::UnknownAttachmentError { name } if name == "Fist"));
+
+    let json = SKELETON.replace(
+        "\"parent\":\"root\",\"rotation\"",
+        "\"parent\":\"hand\",\"rotation\"",
+    );
+    let error = from_json(&json, &SpineImportOptions::default())
+        .err()
+        .unwrap();
+    assert!(matches!(error, SpineImportError::UnknownBoneError { name } if name == "hand"));
+
+    let json = "{\"bones\":[{\"name\":\"root\"}],\"animations\":{\"a\":{\"bones\":{\"root\":{\"rotate\":[{\"time\":TIME,\"value\":1}]}}}}}";
+    for time in ["1e30", "1365.35"] {
+        let error = from_json(&json.replace("TIME", time), &SpineImportOptions::default())
+            .err()
+            .unwrap();
+        assert!(matches!(error, SpineImportError::TooManyFramesError { name, .. } if name == "a"));
+    }
+    // the last frame id that fits
+    let animations = from_json(
+        &json.replace("TIME", "1365.29"),
+        &SpineImportOptions::default(),
+    )
+    .unwrap();
+    assert_eq!(animations[0].frames().unwrap().last().unwrap().id, i16::MAX);
+
+    for fps in [0., -24., f64::NAN, f64::INFINITY] {
+        let options = SpineImportOptions {
+            fps,
+            ..Default::default()
+        };
+        let error = from_json(&json.replace("TIME", "1"), &options)
+            .err()
+            .unwrap();
+        assert!(matches!(error, SpineImportError::InvalidFpsError { .. }));
+    }
+}
+
+#[cfg(feature = "spine-import")]
+#[test]
+fn reads_the_curves_of_every_version() {
+    let skeleton = |version: &str, curve: &str| {
+        format!(
+            "{{\"skeleton\":{{\"spine\":\"{version}\"}},\
+             \"bones\":[{{\"name\":\"root\"}}],\
+             \"slots\":[{{\"name\":\"s\",\"bone\":\"root\",\"attachment\":\"1_1\"}}],\
+             \"skins\":[{{\"name\":\"default\",\"attachments\":{{\"s\":{{\"1_1\":{{}}}}}}}}],\
+             \"animations\":{{\"Move\":{{\"bones\":{{\"root\":{{\"translate\":[\
+               {{\"time\":0,\"x\":0{curve}}},{{\"time\":1,\"x\":100}}]}}}}}}}}}}"
+        )
+    };
+    let options = SpineImportOptions {
+        fps: 4.,
+        ..Default::default()
+    };
+    let halfway = |json: String| {
+        let animations = from_json(&json, &options).unwrap();
+        animations[0].frames().unwrap()[2].bones[0].x
+    };
+
+    assert_eq!(halfway(skeleton("4.1", "")), 50.);
+    // the same ease in: Spine 4 in times and values, per channel, and Spine 3 from 0 to 1
+    let eased = [
+        halfway(skeleton("4.1", ",\"curve\":[0.5,0,1,50,0.5,0,1,0]")),
+        halfway(skeleton("3.7.94", ",\"curve\":[0.5,0,1,0.5]")),
+        halfway(skeleton("3.8.99", ",\"curve\":0.5,\"c3\":1,\"c4\":0.5")),
+    ];
+    assert!(eased[0] < 40., "{eased:?}");
+    assert!(
+        eased.iter().all(|x| (x - eased[0]).abs() < 1e-3),
+        "{eased:?}"
+    );
+
+    let error = from_json(&skeleton("3.7.94", ",\"curve\":[0.5,0]"), &options)
+        .err()
+        .unwrap();
+    assert!(matches!(error, SpineImportError::InvalidFieldError { .. }));
+}
@@ -0,0 +1,182 @@
+mod common;
+
+use bhanm::{
+    Affine2, AnmAnimation,
+    svg::{SvgError, SvgImage, SvgOptions, render_animation, render_animation_html, render_frame},
+};
+use common::frame;
+
+#[test]
+fn bones_are_drawn_in_order_with_markers() {
+    let mut frame = frame(&[1., 2.]);
+    frame.bones[1].id = 7;
+    frame.bones[1].opacity = 0.5;
+    frame.fire_socket = Some((100., -50.));
+
+    let mut options = SvgOptions::default();
+    options.sizes.insert(7, (10., 20.));
+    let svg = render_frame(&frame, &options);
+
+    assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\""));
+    assert!(svg.trim_end().ends_with("</svg>"));
+    let first = svg.find("matrix(2 0.5 0.25 3 1 0)").unwrap();
+    let second = svg
+        .find("matrix(2 0.5 0.25 3 2 0)\" opacity=\"0.5\"")
+        .unwrap();
+    assert!(first < second);
+    assert!(svg.contains("<rect x=\"-5\" y=\"-10\" width=\"10\" height=\"20\""));
+    assert!(svg.contains(">1: 7/1</text>"));
+    assert!(svg.contains("<title>fire_socket</title>"));
+    assert!(!svg.contains("eb_platform_pos"));
+
+    let view_box: Vec<f64> = svg
+        .split("viewBox=\"")
+        .nth(1)
+        .and_then(|rest| rest.split('"').next())
+        .unwrap()
+        .split(' ')
+        .map(|n| n.parse().unwrap())
+        .collect();
+    let [x, y, w, h] = view_box[..] else {
+        panic!("bad view box {view_box:?}");
+    };
+    assert!(x < 100. && 100. < x + w);
+    assert!(y < -50. && -50. < y + h);
+}
+
+fn animation() -> AnmAnimation {
+    let mut moved = frame(&[5.]);
+    moved.bones[0].frame = 2;
+    moved.fire_socket = Some((3., 4.));
+    AnmAnimation::new(
+        "Walk & Run".into(),
+        vec![frame(&[1.]), frame(&[1.]), moved, frame(&[])],
+    )
+}
+
+#[test]
+fn animations_hold_each_frame_and_loop() {
+    let svg = render_animation(&animation(), &SvgOptions::default()).unwrap();
+
+    assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\""));
+    // only the frames where a value changes are keyed
+    assert!(svg.contains(
+        "<animateTransform attributeName=\"transform\" type=\"translate\" values=\"1 0;5 0\" keyTimes=\"0;0.5\" dur=\"0.16666667s\" calcMode=\"discrete\" repeatCount=\"indefinite\"/>"
+    ));
+    // the bone is gone on the last frame
+    assert!(svg.contains("values=\"1;0\" keyTimes=\"0;0.75\""));
+    // the sprite frames swap
+    assert!(svg.contains("values=\"1;0\" keyTimes=\"0;0.5\""));
+    assert!(svg.contains("values=\"0;1\" keyTimes=\"0;0.5\""));
+    // the transform doesn't change, so it isn't animated
+    assert!(!svg.contains("type=\"rotate\""));
+    assert!(svg.contains("<title>fire_socket</title>"));
+    assert!(!svg.contains("<script"));
+}
+
+#[test]
+fn animation_parts_compose_to_the_matrix() {
+    let svg = render_animation(&animation(), &SvgOptions::default()).unwrap();
+    let part = |kind: &str| -> Vec<f64> {
+        svg.split(&format!("transform=\"{kind}("))
+            .nth(1)
+            .and_then(|rest| rest.split(')').next())
+            .unwrap()
+            .split(' ')
+            .map(|n| n.parse().unwrap())
+            .collect()
+    };
+    let (translate, rotate, skew, scale) = (
+        part("translate"),
+        part("rotate")[0].to_radians(),
+        part("skewX")[0].to_radians(),
+        part("scale"),
+    );
+    let transform = Affine2::translation(translate[0], translate[1])
+        * Affine2::rotation(rotate)
+        * Affine2::skew(skew)
+        * Affine2::scale(scale[0], scale[1]);
+
+    let expected = animation().frames().unwrap()[0].bones[0].transform();
+    for (actual, expected) in [
+        (transform.a, expected.a),
+        (transform.b, expected.b),
+        (transform.c, expected.c),
+        (transform.d, expected.d),
+        (transform.tx, expected.tx),
+        (transform.ty, expected.ty),
+    ] {
+        assert!((actual - expected).abs() < 1e-5, "{actual} != {expected}");
+    }
+}
+
+#[test]
+fn html_animations_use_css_keyframes() {
+    let mut options = SvgOptions::default();
+    options.images.insert(
+        (1, 2),
+        SvgImage {
+            href: "data:image/png;base64,AA==".into(),
+            x: -1.,
+            y: -2.,
+            width: 3.,
+            height: 4.,
+        },
+    );
+    let html = render_animation_html(&animation(), &options).unwrap();
+
+    assert!(html.starts_with("<!DOCTYPE html>"));
+    assert!(html.contains("<title>Walk &amp; Run</title>"));
+    assert!(html.contains(".b0 { animation: b0 0.16666667s step-end infinite; }"));
+    assert!(html.contains("0% { transform: matrix(2, 0.5, 0.25, 3, 1, 0); opacity: 1; }"));
+    assert!(html.contains("50% { transform: matrix(2, 0.5, 0.25, 3, 5, 0); opacity: 1; }"));
+    assert!(html.contains("75% { transform: matrix(2, 0.5, 0.25, 3, 5, 0); opacity: 0; }"));
+    assert!(html.contains("100% { transform: matrix(2, 0.5, 0.25, 3, 5, 0); opacity: 0; }"));
+    assert!(html.contains(
+        "<image href=\"data:image/png;base64,AA==\" x=\"-1\" y=\"-2\" width=\"3\" height=\"4\" class=\"b0f1\"/>"
+    ));
+    assert!(!html.contains("<script"));
+}
+
+#[test]
+fn non_finite_values_are_written_as_zero() {
+    let mut broken = frame(&[f32::NAN, f32::INFINITY]);
+    broken.bones[0].scale_x = f32::NEG_INFINITY;
+    broken.bones[1].rotate_skew1 = f32::NAN;
+    broken.bones[1].opacity = f64::NAN;
+    broken.fire_socket = Some((f64::INFINITY, 1.));
+    broken.eb_platform_pos = Some((f64::NAN, 1.));
+    let animation = AnmAnimation::new("Broken".into(), vec![frame(&[1., 1.]), broken]);
+    let options = SvgOptions::default();
+
+    let outputs = [
+        render_frame(&animation.frames().unwrap()[1], &options),
+        render_animation(&animation, &options).unwrap(),
+        render_animation_html(&animation, &options).unwrap(),
+    ];
+    for output in outputs {
+        assert!(!output.contains("NaN"), "{output}");
+        // besides the CSS and SMIL `infinite` keywords
+        let numbers = output.replace("infinite", "");
+        assert!(!numbers.contains("inf"), "{output}");
+    }
+}
+
+#[test]
+fn bad_frame_rates_fail() {
+    let animation = animation();
+    for fps in [0., -24., f64::NAN, f64::INFINITY] {
+        let options = SvgOptions {
+            fps,
+            ..Default::default()
+        };
+        assert!(matches!(
+            render_animation(&animation, &options),
+            Err(SvgError::InvalidFrameRateError { .. })
+        ));
+        assert!(matches!(
+            render_animation_html(&animation, &options),
+            Err(SvgError::InvalidFrameRateError { .. })
+        ));
+    }
+}
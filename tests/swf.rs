@@ -0,0 +1,233 @@
+#![cfg(feature = "swf")]
+
+mod common;
+
+use bhanm::render::{
+    RenderOptions, render_frame,
+    swf::{SwfError, SwfFile, SwfSprites},
+};
+use common::frame;
+use flate2::{Compression, write::ZlibEncoder};
+use std::{collections::HashMap, io::Write};
+
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    bit: u32,
+}
+
+impl BitWriter {
+    fn bits(&mut self, count: u32, value: i32) {
+        for i in (0..count).rev() {
+            if self.bit == 0 {
+                self.bytes.push(0);
+            }
+            let bit = ((value >> i) & 1) as u8;
+            *self.bytes.last_mut().unwrap() |= bit << (7 - self.bit);
+            self.bit = (self.bit + 1) % 8;
+        }
+    }
+
+    fn align(&mut self) {
+        self.bit = 0;
+    }
+
+    fn byte_slice(&mut self, bytes: &[u8]) {
+        self.align();
+        self.bytes.extend_from_slice(bytes);
+    }
+
+    fn tag(&mut self, code: u16, body: BitWriter) {
+        self.byte_slice(&((code << 6) | 0x3f).to_le_bytes());
+        self.byte_slice(&(body.bytes.len() as u32).to_le_bytes());
+        self.byte_slice(&body.bytes);
+    }
+}
+
+/// A 10x10 pixel red square.
+fn define_shape() -> BitWriter {
+    let mut w = BitWriter::default();
+    w.byte_slice(&1u16.to_le_bytes());
+    w.bits(5, 0); // bounds
+    w.byte_slice(&[1, 0x00, 255, 0, 0, 255]); // one solid fill
+    w.byte_slice(&[0]); // no lines
+    w.bits(4, 1);
+    w.bits(4, 0);
+    // move to (0, 0) and use fill 1
+    w.bits(1, 0);
+    w.bits(5, 0b00101);
+    w.bits(5, 1);
+    w.bits(1, 0);
+    w.bits(1, 0);
+    w.bits(1, 1);
+    for (dx, dy) in [(200, 0), (0, 200), (-200, 0), (0, -200)] {
+        w.bits(2, 0b11);
+        w.bits(4, 9 - 2);
+        w.bits(1, 0);
+        if dx != 0 {
+            w.bits(1, 0);
+            w.bits(9, dx);
+        } else {
+            w.bits(1, 1);
+            w.bits(9, dy);
+        }
+    }
+    w.bits(6, 0);
+    w
+}
+
+/// A sprite showing the square moved 5 pixels right, then 10 pixels right.
+fn define_sprite() -> BitWriter {
+    let mut w = BitWriter::default();
+    w.byte_slice(&2u16.to_le_bytes());
+    w.byte_slice(&2u16.to_le_bytes());
+    for (flags, tx) in [(0x06u8, 100), (0x05, 200)] {
+        let mut place = BitWriter::default();
+        place.byte_slice(&[flags]);
+        place.byte_slice(&1u16.to_le_bytes());
+        if flags & 0x02 != 0 {
+            place.byte_slice(&1u16.to_le_bytes());
+        }
+        place.bits(2, 0);
+        place.bits(5, 10);
+        place.bits(10, tx);
+        place.bits(10, 0);
+        w.tag(26, place);
+        w.tag(1, BitWriter::default());
+    }
+    w.tag(0, BitWriter::default());
+    w
+}
+
+/// A sprite that places `child` at `count` depths, without moving it.
+fn define_repeating_sprite(id: u16, child: u16, count: u16) -> BitWriter {
+    let mut w = BitWriter::default();
+    w.byte_slice(&id.to_le_bytes());
+    w.byte_slice(&1u16.to_le_bytes());
+    for depth in 1..=count {
+        let mut place = BitWriter::default();
+        place.byte_slice(&[0x06]);
+        place.byte_slice(&depth.to_le_bytes());
+        place.byte_slice(&child.to_le_bytes());
+        place.bits(7, 0);
+        w.tag(26, place);
+    }
+    w.tag(1, BitWriter::default());
+    w.tag(0, BitWriter::default());
+    w
+}
+
+fn swf(compressed: bool) -> Vec<u8> {
+    swf_with(compressed, vec![(39, define_sprite())])
+}
+
+/// A file with the square, `tags`, and the symbol `a_Square` for character 2.
+fn swf_with(compressed: bool, tags: Vec<(u16, BitWriter)>) -> Vec<u8> {
+    let mut body = BitWriter::default();
+    body.bits(5, 0);
+    body.byte_slice(&[0, 24, 1, 0]);
+    body.tag(32, define_shape());
+    for (code, tag) in tags {
+        body.tag(code, tag);
+    }
+    let mut symbols = BitWriter::default();
+    symbols.byte_slice(&1u16.to_le_bytes());
+    symbols.byte_slice(&2u16.to_le_bytes());
+    symbols.byte_slice(b"a_Square\0");
+    body.tag(76, symbols);
+    body.tag(0, BitWriter::default());
+
+    let mut result = if compressed { b"CWS" } else { b"FWS" }.to_vec();
+    result.push(10);
+    result.extend_from_slice(&(body.bytes.len() as u32 + 8).to_le_bytes());
+    if compressed {
+        let mut zlib = ZlibEncoder::new(&mut result, Compression::default());
+        zlib.write_all(&body.bytes).unwrap();
+        zlib.finish().unwrap();
+    } else {
+        result.extend_from_slice(&body.bytes);
+    }
+    result
+}
+
+#[test]
+fn sprites_are_drawn_at_the_bone_frame() {
+    for compressed in [false, true] {
+        let swf = SwfFile::parse(&swf(compressed)).unwrap();
+        assert_eq!(swf.symbol("a_Square"), Some(2));
+        assert_eq!(swf.frame_count(2), 2);
+        let sprites = SwfSprites::new(swf, HashMap::from([(1, "a_Square".to_owned())]));
+
+        let mut frame = frame(&[0.]);
+        let bone = &mut frame.bones[0];
+        bone.set_transform(Default::default());
+        let options = RenderOptions {
+            width: 30,
+            height: 20,
+            origin: (0., 0.),
+            ..Default::default()
+        };
+
+        let image = render_frame(&frame, &sprites, &options).unwrap();
+        assert_eq!(image.pixel(6, 5), [255, 0, 0, 255]);
+        assert_eq!(image.pixel(17, 5), [0, 0, 0, 0]);
+        assert_eq!(image.pixel(6, 12), [0, 0, 0, 0]);
+
+        frame.bones[0].frame = 2;
+        let image = render_frame(&frame, &sprites, &options).unwrap();
+        assert_eq!(image.pixel(6, 5), [0, 0, 0, 0]);
+        assert_eq!(image.pixel(17, 5), [255, 0, 0, 255]);
+    }
+}
+
+#[test]
+fn unknown_bones_and_bad_files() {
+    let sprites = SwfSprites::new(SwfFile::parse(&swf(false)).unwrap(), HashMap::new());
+    let image = render_frame(&frame(&[0.]), &sprites, &RenderOptions::default()).unwrap();
+    assert!(image.pixels.iter().all(|&b| b == 0));
+
+    assert!(SwfFile::parse(b"not a swf").is_err());
+    let truncated = swf(false);
+    assert!(SwfFile::parse(&truncated[..truncated.len() - 20]).is_err());
+
+    // the decompressed body must be as long as the header says
+    let compressed = swf(true);
+    let length = u32::from_le_bytes(compressed[4..8].try_into().unwrap());
+    for wrong in [length - 1, length + 1] {
+        let mut swf = compressed.clone();
+        swf[4..8].copy_from_slice(&wrong.to_le_bytes());
+        assert!(matches!(
+            SwfFile::parse(&swf),
+            Err(SwfError::DecompressedSizeError { expected }) if expected == wrong as u64 - 8
+        ));
+    }
+}
+
+#[test]
+fn repeated_nesting_is_cut_short() {
+    // each level places the one below 4 times, down to the square: 4^40 squares in all
+    let tags = (0..40)
+        .map(|level| {
+            let child = if level == 0 { 1 } else { 99 + level };
+            (39, define_repeating_sprite(100 + level, child, 4))
+        })
+        .collect();
+    let swf = SwfFile::parse(&swf_with(false, tags)).unwrap();
+    assert_eq!(swf.character(101, 0).len(), 16);
+    let fills = swf.character(120, 0);
+    assert!(!fills.is_empty() && fills.len() < 1 << 22);
+}
+
+#[test]
+fn class_file_names_stay_inside_the_directory() {
+    let dir = std::env::temp_dir();
+    for file_name in ["../Animation_Bow.swf", "/etc/passwd", "a/../../b.swf", ""] {
+        let mut class = common::file().classes.remove("Key").unwrap();
+        class.file_name = file_name.into();
+        let error = SwfFile::open_for_class(&dir, &class).err().unwrap();
+        assert!(
+            matches!(error, SwfError::InvalidFileNameError { .. }),
+            "{file_name}: {error}"
+        );
+    }
+}
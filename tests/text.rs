@@ -0,0 +1,63 @@
+mod common;
+
+use bhanm::{
+    AnmAnimation, AnmFile, BoneEncoding, ReadOptions,
+    text::{parse_file, print_file},
+};
+use common::write;
+
+#[test]
+fn parsed_text_writes_the_same_bytes() {
+    let mut file = common::file();
+    file.header = -7;
+    let class = file.classes.get_mut("Key").unwrap();
+    class.index = "with \"quotes\"\n".into();
+
+    let mut frame = common::frame(&[1., 1.]);
+    frame.fire_socket = Some((0.1, -f64::INFINITY));
+    frame.eb_platform_pos = Some((-0., 1e300));
+    frame.bones[0].scale_x = f32::from_bits(0x7fc0_0001);
+    frame.bones[0].rotate_skew0 = -f32::NAN;
+    frame.bones[1].y = f32::MIN_POSITIVE / 2.;
+    frame.bones[1].opacity = 0.3;
+    frame.bones[1].set_encoding(Some(BoneEncoding::ClonePrevFrame { keep_frame: false }));
+    let mut animation = AnmAnimation::new("Anim".into(), vec![frame, common::frame(&[])]);
+    animation.loop_start = 1;
+    animation.base_start = 4;
+    animation.data = vec![u32::MAX, 0];
+    // a duplicate name, kept as is
+    class.animations.push(animation);
+
+    let bytes = write(&file);
+    let options = ReadOptions {
+        lazy_frames: true,
+        ..Default::default()
+    };
+    let (file, _) = AnmFile::read_with_options(&bytes[..], &options).unwrap();
+
+    let text = print_file(&file).unwrap();
+    assert!(text.starts_with("header -7\n\nclass \"Key\"\n"));
+    assert!(text.contains("\nfire_socket 0.1 -inf\n"));
+    assert!(text.contains("\nbone 1 NaN:7fc00001 NaN:ffc00000 "));
+    assert!(text.contains("\ndata 4294967295 0\n"));
+    let parsed = parse_file(&text).unwrap();
+    assert_eq!(write(&parsed), bytes);
+    assert_eq!(print_file(&parsed).unwrap(), text);
+}
+
+#[test]
+fn parse_errors_name_the_line() {
+    let text = print_file(&common::file()).unwrap();
+    let broken = text.replacen("bone 1 2 ", "bone 1 two ", 1);
+    let line = broken
+        .lines()
+        .position(|line| line.starts_with("bone 1 two"))
+        .unwrap();
+
+    let error = parse_file(&broken).err().unwrap();
+    assert_eq!(error.line, line + 1);
+    assert!(error.message.contains("\"two\""));
+
+    let error = parse_file(&format!("{text}\nheader 1\n")).err().unwrap();
+    assert!(error.message.contains("expected a class"));
+}